@@ -0,0 +1,20 @@
+#![no_main]
+
+//! Run with `cargo +nightly fuzz run scan_code -- -timeout=1`, so that libFuzzer reports
+//! any input that takes a second or longer to scan.
+
+use hfscanner::{ScanConfig, count_usages, scan_code, scan_code_for_usage};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let code = String::from_utf8_lossy(data);
+    let config = ScanConfig::default();
+
+    let findings = scan_code(&code, &config);
+    let (safe, partial, unsafe_) = scan_code_for_usage(&code, &config);
+
+    // Ignored findings are matched but not counted, so the counts can only fall short of
+    // the number of matches.
+    assert!(safe + partial + unsafe_ <= findings.len());
+    assert_eq!((safe, partial, unsafe_), count_usages(&findings));
+});
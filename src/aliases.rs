@@ -0,0 +1,189 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+static IMPORT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*import\s+([^#\n]+)").unwrap());
+
+static FROM_IMPORT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*from\s+[\w.]+\s+import\s+([^#\n]+)").unwrap());
+
+static ALIAS_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\(?\s*([\w.]+)\s+as\s+(\w+)\s*\)?$").unwrap());
+
+/// Names bound by `import X as Y` and `from X import Y as Z`, mapped to what they stand for,
+/// so that `AM.from_pretrained(...)` after `from transformers import AutoModel as AM` is
+/// matched like `AutoModel.from_pretrained(...)`.
+#[derive(Debug, Clone, Default)]
+pub struct ImportAliasMap {
+    aliases: HashMap<String, String>,
+    /// Matches any of the aliases; `None` if there are none.
+    use_re: Option<Regex>,
+}
+
+impl PartialEq for ImportAliasMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.aliases == other.aliases
+    }
+}
+
+impl Eq for ImportAliasMap {}
+
+impl ImportAliasMap {
+    /// Collect the aliases of every import statement in `code`. Only the first line of a
+    /// parenthesised import list is read.
+    pub fn from_code(code: &str) -> ImportAliasMap {
+        let mut aliases = HashMap::new();
+        for caps in IMPORT_RE
+            .captures_iter(code)
+            .chain(FROM_IMPORT_RE.captures_iter(code))
+        {
+            for name in caps[1].split(',') {
+                if let Some(alias) = ALIAS_RE.captures(name.trim())
+                    && alias[1] != alias[2]
+                {
+                    aliases.insert(alias[2].to_string(), alias[1].to_string());
+                }
+            }
+        }
+        let use_re = (!aliases.is_empty()).then(|| {
+            let names: Vec<String> = aliases.keys().map(|a| regex::escape(a)).collect();
+            Regex::new(&format!(r"\b(?:{})\b", names.join("|"))).unwrap()
+        });
+        ImportAliasMap { aliases, use_re }
+    }
+
+    /// What `alias` stands for, e.g. `AutoModel` or `transformers.AutoModel`.
+    pub fn get(&self, alias: &str) -> Option<&str> {
+        self.aliases.get(alias).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// `code` with every use of an alias replaced by its target. Attribute accesses such as
+    /// `obj.AM` are left alone.
+    pub fn resolve(&self, code: &str) -> ResolvedCode {
+        let mut resolved = ResolvedCode {
+            text: String::with_capacity(code.len()),
+            replacements: Vec::new(),
+        };
+        let Some(use_re) = &self.use_re else {
+            resolved.text.push_str(code);
+            return resolved;
+        };
+
+        let mut copied = 0;
+        for m in use_re.find_iter(code) {
+            if code[..m.start()].ends_with('.') {
+                continue;
+            }
+            resolved.text.push_str(&code[copied..m.start()]);
+            let text_start = resolved.text.len();
+            resolved.text.push_str(&self.aliases[m.as_str()]);
+            resolved.replacements.push(Replacement {
+                text_start,
+                text_end: resolved.text.len(),
+                original_start: m.start(),
+                original_end: m.end(),
+            });
+            copied = m.end();
+        }
+        resolved.text.push_str(&code[copied..]);
+        resolved
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Replacement {
+    text_start: usize,
+    text_end: usize,
+    original_start: usize,
+    original_end: usize,
+}
+
+/// Source code with its import aliases resolved by [`ImportAliasMap::resolve`]. Line
+/// breaks are never replaced, so line numbers are the same as in the original.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedCode {
+    text: String,
+    replacements: Vec<Replacement>,
+}
+
+impl ResolvedCode {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The span of the original code that `start..end` of [`ResolvedCode::text`] was
+    /// made from. A span starting or ending inside a replaced alias covers all of it.
+    pub fn original_span(&self, start: usize, end: usize) -> (usize, usize) {
+        (
+            self.original_offset(start, false),
+            self.original_offset(end, true),
+        )
+    }
+
+    fn original_offset(&self, offset: usize, is_end: bool) -> usize {
+        let Some(r) = self
+            .replacements
+            .iter()
+            .rev()
+            .find(|r| r.text_start < offset || (r.text_start == offset && is_end))
+        else {
+            return offset;
+        };
+        if offset >= r.text_end {
+            r.original_end + (offset - r.text_end)
+        } else if is_end {
+            r.original_end
+        } else {
+            r.original_start
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_alias_map() {
+        let code = "import transformers.AutoModel as AM\n\
+                    from transformers import AutoTokenizer as Tok, pipeline\n\
+                    from datasets import (load_dataset as ld)\n\
+                    import os\n\
+                    import numpy as numpy\n";
+        let aliases = ImportAliasMap::from_code(code);
+        assert_eq!(aliases.get("AM"), Some("transformers.AutoModel"));
+        assert_eq!(aliases.get("Tok"), Some("AutoTokenizer"));
+        assert_eq!(aliases.get("ld"), Some("load_dataset"));
+        assert_eq!(aliases.get("pipeline"), None);
+        assert_eq!(aliases.get("numpy"), None);
+    }
+
+    #[test]
+    fn test_resolve_maps_offsets_back() {
+        let code =
+            "from transformers import AutoModel as AM\nm = AM.from_pretrained(x)\ny = obj.AM\n";
+        let resolved = ImportAliasMap::from_code(code).resolve(code);
+        assert_eq!(
+            resolved.text(),
+            "from transformers import AutoModel as AutoModel\n\
+             m = AutoModel.from_pretrained(x)\ny = obj.AM\n"
+        );
+
+        let start = resolved.text().find("AutoModel.from").unwrap();
+        let end = resolved.text().find("(x)").unwrap() + 3;
+        let (start, end) = resolved.original_span(start, end);
+        assert_eq!(&code[start..end], "AM.from_pretrained(x)");
+
+        // Offsets after the replacements shift back by the length difference.
+        let y = resolved.text().find("y =").unwrap();
+        assert_eq!(
+            resolved.original_span(y, y + 3),
+            (code.find("y =").unwrap(), code.find("y =").unwrap() + 3)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::remediation::remediation_hint;
+use crate::scanner::{Finding, Status};
+
+/// Escape an annotation message as GitHub's workflow command parser expects.
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape an annotation property value, which additionally may not contain `:` or `,`.
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Format one `::<level> file=<file>,line=<line>::<message>` workflow command.
+pub fn format_annotation(level: &str, file: &str, line: usize, message: &str) -> String {
+    format!(
+        "::{level} file={},line={line}::{}",
+        escape_property(file),
+        escape_data(message)
+    )
+}
+
+/// GitHub Actions annotations for every finding that is not safe: warnings for partially
+/// safe findings, unpinned API versions and remote calls, errors for unsafe ones.
+///
+/// With `remediation_hints`, messages end with the [`remediation_hint`] for the call.
+pub fn github_annotations(findings: &[Finding], remediation_hints: bool) -> Vec<String> {
+    findings
+        .iter()
+        .filter_map(|finding| {
+            let (level, reason) = match finding.status {
+                Status::Ignored | Status::Safe => return None,
+                Status::PartiallySafe => ("warning", "is not pinned to a commit SHA"),
+                Status::MetadataLoad => ("warning", "loads Hub metadata without a pinned revision"),
+                Status::LocalFileLoad => ("warning", "loads a model file of unknown provenance"),
+                Status::ApiVersionUnpinned => ("warning", "does not pin a dated model version"),
+                Status::RemoteCall => ("warning", "calls a remote model without a pinned revision"),
+                Status::Unsafe => ("error", "has no pinned revision"),
+                Status::TrainingWithUnsafeBase => (
+                    "error",
+                    "has no pinned revision and the model is fine-tuned in this file",
+                ),
+                Status::PathTraversal => (
+                    "error",
+                    "passes a subfolder that escapes the model directory",
+                ),
+            };
+            let mut message = format!("{} call {reason}", finding.pattern);
+            if let Some(hint) = remediation_hints
+                .then(|| remediation_hint(finding))
+                .flatten()
+            {
+                message.push_str(&format!("; try: {hint}"));
+            }
+            Some(format_annotation(
+                level,
+                &finding.file,
+                finding.line,
+                &message,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_annotation_escaping() {
+        assert_eq!(
+            format_annotation("error", "org/repo/load.py", 12, "plain message"),
+            "::error file=org/repo/load.py,line=12::plain message"
+        );
+        assert_eq!(
+            format_annotation(
+                "warning",
+                "dir, with: chars/a%b.py",
+                1,
+                "100% sure\r\nnext: line, end"
+            ),
+            "::warning file=dir%2C with%3A chars/a%25b.py,line=1::100%25 sure%0D%0Anext: line, end"
+        );
+    }
+
+    #[test]
+    fn test_github_annotations() {
+        let finding = |line, status| Finding {
+            file: "org/repo/load.py".to_string(),
+            line,
+            pattern: "AutoModel".to_string(),
+            status,
+            call: String::new(),
+            model_name: None,
+            in_loop: false,
+            in_init_empty_weights: false,
+            in_test_fixture: false,
+            enclosing_function: None,
+            shared_cache_dir: false,
+            incomplete_download: false,
+            private_repo: false,
+            context: String::new(),
+            cwe: Vec::new(),
+            suppression_reason: None,
+        };
+        let findings = vec![
+            finding(1, Status::Safe),
+            finding(2, Status::PartiallySafe),
+            finding(3, Status::Unsafe),
+        ];
+        assert_eq!(
+            github_annotations(&findings, false),
+            vec![
+                "::warning file=org/repo/load.py,line=2::AutoModel call is not pinned to a commit SHA",
+                "::error file=org/repo/load.py,line=3::AutoModel call has no pinned revision",
+            ]
+        );
+
+        let mut unsafe_call = finding(4, Status::Unsafe);
+        unsafe_call.call = "AutoModel.from_pretrained(\"gpt2\")".to_string();
+        assert_eq!(
+            github_annotations(&[unsafe_call], true),
+            vec![
+                "::error file=org/repo/load.py,line=4::AutoModel call has no pinned revision; try: AutoModel.from_pretrained(\"gpt2\", revision=\"<commit-sha>\")"
+            ]
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Seed for the synthetic tree, so every benchmark run scans the same files.
+const SEED: u64 = 0x5eed_1a7e_d5ca_7000;
+/// Files per synthetic `org/repo` directory.
+const FILES_PER_REPO: usize = 50;
+
+const UNSAFE_CODE: &str = r#"from transformers import AutoModel, AutoTokenizer
+
+tokenizer = AutoTokenizer.from_pretrained("org/model")
+model = AutoModel.from_pretrained("org/model")
+"#;
+
+const SAFE_CODE: &str = r#"from transformers import AutoModel, AutoTokenizer
+
+tokenizer = AutoTokenizer.from_pretrained("org/model", revision="5d0f2e8a7f1b3c4d5e6f7a8b9c0d1e2f3a4b5c6d")
+model = AutoModel.from_pretrained("org/model", revision="5d0f2e8a7f1b3c4d5e6f7a8b9c0d1e2f3a4b5c6d")
+"#;
+
+/// Padding appended to every file so the scanner has ordinary code to skip over.
+const FILLER: &str = r#"
+def preprocess(batch):
+    return [text.strip().lower() for text in batch]
+
+
+def main():
+    print(preprocess(["Hello", "World"]))
+"#;
+
+/// Minimal linear congruential generator; good enough to pick which files are unsafe
+/// without pulling in a dependency.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Write `n_files` Python files under `dir`, laid out as `org-N/repo-N/file-N.py`.
+///
+/// Each file loads two models, unpinned with probability `unsafe_ratio` and pinned to a
+/// commit SHA otherwise. The choice is seeded, so the same arguments always produce the
+/// same tree. Returns the number of unsafe files written.
+pub fn generate_synthetic_tree(dir: &Path, n_files: usize, unsafe_ratio: f64) -> io::Result<usize> {
+    let mut rng = Lcg(SEED);
+    let mut unsafe_files = 0;
+    for i in 0..n_files {
+        let repo = i / FILES_PER_REPO;
+        let repo_dir = dir
+            .join(format!("org-{}", repo / 10))
+            .join(format!("repo-{repo}"));
+        if i % FILES_PER_REPO == 0 {
+            fs::create_dir_all(&repo_dir)?;
+        }
+        let code = if rng.next_f64() < unsafe_ratio {
+            unsafe_files += 1;
+            UNSAFE_CODE
+        } else {
+            SAFE_CODE
+        };
+        fs::write(
+            repo_dir.join(format!("file-{i}.py")),
+            format!("{code}{FILLER}"),
+        )?;
+    }
+    Ok(unsafe_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::directory::scan_directory;
+
+    #[test]
+    fn test_generate_synthetic_tree() -> io::Result<()> {
+        let first = tempfile::tempdir()?;
+        let second = tempfile::tempdir()?;
+        let unsafe_files = generate_synthetic_tree(first.path(), 120, 0.25)?;
+        assert_eq!(
+            generate_synthetic_tree(second.path(), 120, 0.25)?,
+            unsafe_files
+        );
+        assert!(unsafe_files > 0 && unsafe_files < 60);
+
+        let results = scan_directory(first.path(), &ScanConfig::default());
+        assert_eq!(results.stats.files_scanned, 120);
+        assert_eq!(results.stats.unsafe_usages, 2 * unsafe_files);
+        assert_eq!(results.stats.safe_usages, 2 * (120 - unsafe_files));
+        assert_eq!(
+            fs::read_to_string(first.path().join("org-0/repo-1/file-57.py"))?,
+            fs::read_to_string(second.path().join("org-0/repo-1/file-57.py"))?
+        );
+
+        assert_eq!(generate_synthetic_tree(second.path(), 10, 0.0)?, 0);
+        assert_eq!(generate_synthetic_tree(second.path(), 10, 1.0)?, 10);
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+//! Measure scan throughput on synthetic trees.
+//!
+//! Usage: `bench_scan [--unsafe-ratio <0..1>] [--lazy-walk] [--parallel-io]
+//! [--read-latency-ms <n>] [file counts...]`
+//!
+//! Peak RSS is the high-water mark of the whole process, so compare the two walk modes in
+//! separate runs.
+
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use hfscanner::bench::generate_synthetic_tree;
+use hfscanner::{ScanConfig, scan_directory};
+
+const DEFAULT_SIZES: &[usize] = &[100, 1_000];
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut unsafe_ratio = 0.5;
+    let mut config = ScanConfig::default();
+    let mut sizes = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--unsafe-ratio" {
+            match args.get(i + 1).and_then(|v| v.parse::<f64>().ok()) {
+                Some(ratio) if (0.0..=1.0).contains(&ratio) => unsafe_ratio = ratio,
+                _ => {
+                    eprintln!("--unsafe-ratio expects a number between 0 and 1");
+                    return ExitCode::FAILURE;
+                }
+            }
+            i += 2;
+            continue;
+        }
+        if args[i] == "--lazy-walk" {
+            config.lazy_walk = true;
+            i += 1;
+            continue;
+        }
+        if args[i] == "--parallel-io" {
+            config.parallel_io = true;
+            i += 1;
+            continue;
+        }
+        if args[i] == "--read-latency-ms" {
+            match args.get(i + 1).and_then(|v| v.parse::<u64>().ok()) {
+                Some(ms) => config.read_latency = Some(Duration::from_millis(ms)),
+                None => {
+                    eprintln!("--read-latency-ms expects a number of milliseconds");
+                    return ExitCode::FAILURE;
+                }
+            }
+            i += 2;
+            continue;
+        }
+        match args[i].parse::<usize>() {
+            Ok(n) => sizes.push(n),
+            Err(_) => {
+                eprintln!(
+                    "Usage: bench_scan [--unsafe-ratio <0..1>] [--lazy-walk] [--parallel-io] [--read-latency-ms <n>] [file counts...]"
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+        i += 1;
+    }
+    if sizes.is_empty() {
+        sizes.extend_from_slice(DEFAULT_SIZES);
+    }
+
+    println!(
+        "{:>10} {:>10} {:>10} {:>12} {:>14}",
+        "files", "unsafe", "seconds", "files/sec", "peak RSS (KiB)"
+    );
+    for n_files in sizes {
+        let dir = match tempfile_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                eprintln!("Failed to create temporary directory: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        let unsafe_files = match generate_synthetic_tree(&dir, n_files, unsafe_ratio) {
+            Ok(count) => count,
+            Err(e) => {
+                eprintln!("Failed to generate synthetic tree: {e}");
+                let _ = std::fs::remove_dir_all(&dir);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let start = Instant::now();
+        let results = scan_directory(&dir, &config);
+        let elapsed = start.elapsed().as_secs_f64();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        println!(
+            "{:>10} {:>10} {:>10.3} {:>12.0} {:>14}",
+            results.stats.files_scanned,
+            unsafe_files,
+            elapsed,
+            results.stats.files_scanned as f64 / elapsed,
+            peak_rss_kib().map_or("n/a".to_string(), |kib| kib.to_string())
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+/// The process's peak resident set size (`VmHWM`), only available on Linux.
+fn peak_rss_kib() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// A fresh directory under the system temp dir; `tempfile` is only a dev-dependency.
+fn tempfile_dir() -> std::io::Result<std::path::PathBuf> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let dir = std::env::temp_dir().join(format!("bench_scan-{}-{nanos}", std::process::id()));
+    std::fs::create_dir(&dir)?;
+    Ok(dir)
+}
@@ -0,0 +1,180 @@
+use ring::digest::{SHA256, digest};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::ScanConfig;
+use crate::scanner::Finding;
+
+/// Name of the cache file written to the `--cache-dir` directory.
+pub const CACHE_FILE_NAME: &str = "hfscanner-cache.json";
+
+/// Hex-encoded SHA-256 digest of `content`.
+pub fn content_hash(content: &str) -> String {
+    let mut hex = String::with_capacity(64);
+    for byte in digest(&SHA256, content.as_bytes()).as_ref() {
+        write!(hex, "{byte:02x}").unwrap();
+    }
+    hex
+}
+
+/// Hash of everything besides the file contents that changes the findings of
+/// [`scan_code`]: the scanner version and the pattern and revision options. A cache
+/// written with a different fingerprint is discarded.
+///
+/// [`scan_code`]: crate::scanner::scan_code
+fn config_fingerprint(config: &ScanConfig) -> String {
+    let safe_revision_patterns: Vec<&str> = config
+        .safe_revision_patterns
+        .iter()
+        .map(|re| re.as_str())
+        .collect();
+    let patterns: Vec<(&str, &str, String)> = config
+        .extra_patterns
+        .iter()
+        .map(|p| (p.name.as_str(), p.regex.as_str(), p.severity.to_string()))
+        .collect();
+    content_hash(&format!(
+        "{} {:?} {:?} {:?} {:?} {:?} {:?}",
+        env!("CARGO_PKG_VERSION"),
+        config.accept_short_sha,
+        config.flag_suspicious_shas,
+        safe_revision_patterns,
+        patterns,
+        config.no_builtin_patterns,
+        config.context_lines,
+    ))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    fingerprint: String,
+    entries: HashMap<String, Vec<Finding>>,
+}
+
+/// Findings of previously scanned Python files, keyed by the SHA-256 of their contents
+/// and stored as JSON in a cache directory, so unchanged files are not scanned again.
+///
+/// Shared between the scanning threads through [`ScanConfig::cache`].
+#[derive(Debug)]
+pub struct ScanCache {
+    path: PathBuf,
+    fingerprint: String,
+    entries: Mutex<HashMap<String, Vec<Finding>>>,
+    hits: AtomicUsize,
+}
+
+impl ScanCache {
+    /// Open the cache in `dir`, creating the directory if needed. A missing, unreadable or
+    /// outdated cache file starts an empty cache.
+    pub fn open(dir: &Path, config: &ScanConfig) -> std::io::Result<ScanCache> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(CACHE_FILE_NAME);
+        let fingerprint = config_fingerprint(config);
+        let entries = match fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str::<CacheFile>(&json) {
+                Ok(file) if file.fingerprint == fingerprint => file.entries,
+                Ok(_) => {
+                    tracing::debug!(path = %path.display(), "discarding cache of other options");
+                    HashMap::new()
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), "Ignoring unreadable cache: {e}");
+                    HashMap::new()
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+        Ok(ScanCache {
+            path,
+            fingerprint,
+            entries: Mutex::new(entries),
+            hits: AtomicUsize::new(0),
+        })
+    }
+
+    /// Cached findings of the file with content hash `hash`.
+    pub fn get(&self, hash: &str) -> Option<Vec<Finding>> {
+        let findings = self.entries.lock().unwrap().get(hash).cloned();
+        if findings.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        findings
+    }
+
+    /// Number of [`ScanCache::get`] calls answered from the cache since it was opened.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn insert(&self, hash: String, findings: Vec<Finding>) {
+        self.entries.lock().unwrap().insert(hash, findings);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write the cache back to its file.
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let file = CacheFile {
+            fingerprint: self.fingerprint.clone(),
+            entries: std::mem::take(&mut *entries),
+        };
+        let json = serde_json::to_string(&file);
+        *entries = file.entries;
+        fs::write(&self.path, json.map_err(std::io::Error::other)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::scan_code;
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_cache_round_trip() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = ScanConfig::default();
+        let code = "model = AutoModel.from_pretrained(\"gpt2\", revision=\"main\")\n";
+        let findings = scan_code(code, &config);
+
+        let cache = ScanCache::open(dir.path(), &config)?;
+        assert!(cache.is_empty());
+        cache.insert(content_hash(code), findings.clone());
+        cache.save()?;
+
+        let cache = ScanCache::open(dir.path(), &config)?;
+        assert_eq!(cache.get(&content_hash(code)), Some(findings));
+        assert_eq!(cache.get(&content_hash("other")), None);
+
+        // Findings depend on the options, so a cache of other options is not reused.
+        let short_sha = ScanConfig {
+            accept_short_sha: true,
+            ..Default::default()
+        };
+        assert!(ScanCache::open(dir.path(), &short_sha)?.is_empty());
+
+        fs::write(dir.path().join(CACHE_FILE_NAME), "{not json")?;
+        assert!(ScanCache::open(dir.path(), &config)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use regex::Regex;
+use std::sync::Arc;
+
+use crate::cache::ScanCache;
+use crate::risk::RiskWeights;
+use crate::scanner::{Pattern, Status};
+
+/// `--max-file-size` used by the command line tool when none is given: 5 MiB.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// How [`scan_directories`] runs the per-file scans.
+///
+/// [`scan_directories`]: crate::directory::scan_directories
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConcurrencyMode {
+    /// Read and scan files on the Rayon thread pool.
+    #[default]
+    Rayon,
+    /// Read files with Tokio's async file I/O, a bounded number at a time.
+    Tokio,
+}
+
+/// How [`scan_file`] handles source files that are not valid UTF-8.
+///
+/// [`scan_file`]: crate::scanner::scan_file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingMode {
+    /// Log the read error and skip the file.
+    #[default]
+    Strict,
+    /// Replace invalid bytes with U+FFFD and scan the rest, for legacy Latin-1 or
+    /// Windows-1252 sources.
+    Replace,
+}
+
+/// Options that change how files are selected, classified and reported.
+#[derive(Debug, Default, Clone)]
+pub struct ScanConfig {
+    /// Keep files without any findings in the CSV rows.
+    pub include_safe_in_csv: bool,
+    /// Treat abbreviated (7 to 40 character) commit SHAs as safe revisions.
+    pub accept_short_sha: bool,
+    /// Report revisions that look like commit SHAs but fail [`is_plausible_commit_sha`],
+    /// such as forty zeros, as partially safe.
+    ///
+    /// [`is_plausible_commit_sha`]: crate::scanner::is_plausible_commit_sha
+    pub flag_suspicious_shas: bool,
+    /// Extensions (`sh`) or file names (`Makefile`) of non-Python files that are scanned
+    /// for `huggingface-cli download` commands.
+    pub scan_extensions: Vec<String>,
+    /// Regexes for revision values that are also accepted as immutable, such as
+    /// `sha256:[a-f0-9]{64}`, compiled with [`safe_revision_regex`] so each must match
+    /// the whole value.
+    ///
+    /// [`safe_revision_regex`]: crate::scanner::safe_revision_regex
+    pub safe_revision_patterns: Vec<Regex>,
+    /// Patterns checked in addition to the built-in ones, e.g. from `--pattern-db`.
+    pub extra_patterns: Vec<Pattern>,
+    /// Only check `extra_patterns`.
+    pub no_builtin_patterns: bool,
+    /// Capture this many lines before and after each finding in [`Finding::context`].
+    ///
+    /// [`Finding::context`]: crate::scanner::Finding::context
+    pub context_lines: Option<usize>,
+    /// Record how long each file took to scan in [`FileRow::scan_time`].
+    ///
+    /// [`FileRow::scan_time`]: crate::directory::FileRow::scan_time
+    pub per_file_timing: bool,
+    /// Record the time spent in each phase of the scan in [`ScanResults::phase_timings`].
+    ///
+    /// [`ScanResults::phase_timings`]: crate::directory::ScanResults::phase_timings
+    pub perf_profile: bool,
+    pub concurrency_mode: ConcurrencyMode,
+    pub encoding_errors: EncodingMode,
+    /// Scan files as the directory walk finds them instead of listing them all first,
+    /// which keeps memory flat on huge trees. Only applies to [`ConcurrencyMode::Rayon`].
+    pub lazy_walk: bool,
+    /// Read files on [`IO_THREADS`] dedicated threads and leave only the matching to Rayon,
+    /// so reads from a slow filesystem such as NFS do not hold up CPU work. Only applies to
+    /// [`ConcurrencyMode::Rayon`] without `lazy_walk`.
+    ///
+    /// [`IO_THREADS`]: crate::directory::IO_THREADS
+    pub parallel_io: bool,
+    /// Wait this long before reading each file, to simulate a high-latency filesystem in
+    /// benchmarks and tests. Only built with the `bench` feature.
+    #[cfg(any(test, feature = "bench"))]
+    pub read_latency: Option<std::time::Duration>,
+    /// Skip files with a generated-code marker such as `# DO NOT EDIT` in their first five
+    /// lines; see [`DEFAULT_GENERATED_MARKERS`].
+    ///
+    /// [`DEFAULT_GENERATED_MARKERS`]: crate::scanner::DEFAULT_GENERATED_MARKERS
+    pub skip_generated: bool,
+    /// Regexes recognised as generated-code markers in addition to the default ones.
+    pub generated_markers: Vec<Regex>,
+    /// Also skip what the `.gitignore` at the root of each scanned directory excludes, on
+    /// top of [`EXCLUDED_DIRS`].
+    ///
+    /// [`EXCLUDED_DIRS`]: crate::directory::EXCLUDED_DIRS
+    pub respect_gitignore: bool,
+    /// Skip files smaller than this many bytes.
+    pub min_file_size: u64,
+    /// Skip files larger than this many bytes, logging a warning for each.
+    pub max_file_size: Option<u64>,
+    /// Weights used to compute [`ScanResults::risk_score`].
+    ///
+    /// [`ScanResults::risk_score`]: crate::directory::ScanResults::risk_score
+    pub risk_weights: RiskWeights,
+    /// `org/repo` names of private repositories. Their [`Status::Unsafe`] findings are
+    /// accepted as a lower risk and reported as partially safe; training on an unpinned
+    /// base model keeps [`Status::TrainingWithUnsafeBase`] and a traversing `subfolder`
+    /// keeps [`Status::PathTraversal`].
+    pub private_repos: Vec<String>,
+    /// Reuse the findings of Python files whose contents were scanned before.
+    pub cache: Option<Arc<ScanCache>>,
+    /// Fail [`check_thresholds`] if any finding is at least this severe.
+    ///
+    /// [`check_thresholds`]: crate::directory::check_thresholds
+    pub fail_on: Option<Status>,
+    /// Fail [`check_thresholds`] if the risk score is greater than this.
+    ///
+    /// [`check_thresholds`]: crate::directory::check_thresholds
+    pub fail_on_score: Option<f64>,
+    /// Fail [`check_thresholds`] if a built-in pattern introduced after this release
+    /// reports an unpinned or partially pinned call.
+    ///
+    /// [`check_thresholds`]: crate::directory::check_thresholds
+    pub fail_on_new_patterns_since: Option<String>,
+}
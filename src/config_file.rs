@@ -0,0 +1,733 @@
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Scanner configuration looked up in the working directory, with its options as
+/// top-level keys.
+pub const CONFIG_FILE_NAME: &str = ".silentinjection.toml";
+
+/// `pyproject.toml` table holding the scanner configuration.
+pub const PYPROJECT_TABLE: &str = "tool.silentinjection";
+
+/// A value in a configuration file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Bool(bool),
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Array(Vec<ConfigValue>),
+}
+
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValue::Bool(b) => write!(f, "{b}"),
+            ConfigValue::String(s) => write!(f, "{s}"),
+            ConfigValue::Integer(i) => write!(f, "{i}"),
+            ConfigValue::Float(x) => write!(f, "{x}"),
+            ConfigValue::Array(values) => {
+                let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+                write!(f, "{}", values.join(","))
+            }
+        }
+    }
+}
+
+/// Options from configuration files, keyed by their command line flag without the
+/// leading `--`, e.g. `fail-on`.
+pub type ConfigSettings = BTreeMap<String, ConfigValue>;
+
+#[derive(Debug)]
+pub enum ConfigFileError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// A discovered file setting an option outside [`DISCOVERED_OPTIONS`].
+    Untrusted { path: PathBuf, key: String },
+}
+
+impl fmt::Display for ConfigFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFileError::Io { path, source } => {
+                write!(f, "failed to read {}: {source}", path.display())
+            }
+            ConfigFileError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            ConfigFileError::Untrusted { path, key } => write!(
+                f,
+                "{}: {key} can only be set in a file given with --config",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigFileError {}
+
+/// Options a discovered configuration file, one read from the working directory rather
+/// than given with `--config`, may set. The working directory may be a checkout of
+/// untrusted code, so this leaves out every option that reaches the network or sends a
+/// token, writes to a path, weakens or disables detection, or keeps a run from failing.
+pub const DISCOVERED_OPTIONS: &[&str] = &[
+    "summary",
+    "detailed",
+    "verbose",
+    "ci-mode",
+    "fail-on",
+    "fail-on-score",
+    "severity-threshold",
+    "fail-on-new-patterns-since",
+    "flag-suspicious-sha",
+    "emit-line-annotations",
+    "emit-cwe",
+    "remediation-hint",
+    "format-numbers",
+    "anonymise-models",
+    "include-context",
+    "include-safe-in-csv",
+    "csv-columns",
+    "csv-separator",
+    "csv-no-header",
+    "format-version",
+    "schema-version",
+    "group-by-model",
+    "find-duplicates",
+    "dup-threshold",
+    "detect-reuse",
+    "scan-depth-report",
+    "per-file-timing",
+    "slow-file-threshold",
+    "concurrency-mode",
+    "lazy-walk",
+    "parallel-io",
+];
+
+/// A parsed TOML value: an option value or an inline table of dotted keys and values.
+enum Parsed {
+    Value(ConfigValue),
+    Table(Vec<(Vec<String>, Parsed)>),
+}
+
+/// `text` with its `#` comments removed, with the net depth of `[`/`{` minus `]`/`}` and
+/// whether it ends inside a multi-line string, both outside strings. A statement
+/// continues on the next line while either is set.
+struct Lexed {
+    text: String,
+    depth: i32,
+    open_string: bool,
+}
+
+fn lex(input: &str) -> Lexed {
+    let mut text = String::with_capacity(input.len());
+    let mut depth = 0;
+    let mut quote: Option<&str> = None;
+    let mut rest = input;
+    'chars: while let Some(c) = rest.chars().next() {
+        match quote {
+            None if c == '#' => {
+                rest = &rest[rest.find('\n').unwrap_or(rest.len())..];
+                continue;
+            }
+            None => {
+                for q in [r#"""""#, "'''", "\"", "'"] {
+                    if let Some(after) = rest.strip_prefix(q) {
+                        quote = Some(q);
+                        text.push_str(q);
+                        rest = after;
+                        continue 'chars;
+                    }
+                }
+                match c {
+                    '[' | '{' => depth += 1,
+                    ']' | '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            Some(q) if q.starts_with('"') && c == '\\' => {
+                // Copy escapes whole, so an escaped quote does not end the string.
+                let len = 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+                text.push_str(&rest[..len]);
+                rest = &rest[len..];
+                continue;
+            }
+            Some(q) => {
+                if let Some(after) = rest.strip_prefix(q) {
+                    quote = None;
+                    text.push_str(q);
+                    rest = after;
+                    continue;
+                }
+                // A single-line string left open ends with its line.
+                if c == '\n' && q.len() == 1 {
+                    quote = None;
+                }
+            }
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    Lexed {
+        text,
+        depth,
+        open_string: quote.is_some_and(|q| q.len() == 3),
+    }
+}
+
+/// Parse a basic string after its opening quotes, returning it and the rest of the input.
+/// A `multi_line` string drops a line break right after its opening quotes, and a `\` at
+/// the end of one of its lines removes the line break and the whitespace after it.
+fn parse_basic_string(input: &str, multi_line: bool) -> Result<(String, &str), String> {
+    let mut value = String::new();
+    let mut rest = input;
+    if multi_line {
+        rest = rest
+            .strip_prefix("\r\n")
+            .or_else(|| rest.strip_prefix('\n'))
+            .unwrap_or(rest);
+    }
+    loop {
+        let Some(c) = rest.chars().next() else {
+            return Err("unterminated string".to_string());
+        };
+        if multi_line {
+            let quotes = rest.len() - rest.trim_start_matches('"').len();
+            if quotes >= 3 {
+                // Up to two quotes may come right before the closing `"""`.
+                let extra = (quotes - 3).min(2);
+                value.push_str(&"\"".repeat(extra));
+                return Ok((value, &rest[3 + extra..]));
+            }
+        } else if c == '"' {
+            return Ok((value, &rest[1..]));
+        } else if c == '\n' {
+            return Err("unterminated string".to_string());
+        }
+        rest = &rest[c.len_utf8()..];
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let Some(escape) = rest.chars().next() else {
+            return Err("unterminated string".to_string());
+        };
+        rest = &rest[escape.len_utf8()..];
+        match escape {
+            'b' => value.push('\u{8}'),
+            't' => value.push('\t'),
+            'n' => value.push('\n'),
+            'f' => value.push('\u{c}'),
+            'r' => value.push('\r'),
+            '"' | '\\' => value.push(escape),
+            'u' | 'U' => {
+                let len = if escape == 'u' { 4 } else { 8 };
+                let code = rest
+                    .get(..len)
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or("invalid unicode escape in string")?;
+                value.push(code);
+                rest = &rest[len..];
+            }
+            c if multi_line && c.is_ascii_whitespace() => {
+                let trimmed = rest.trim_start();
+                if c != '\n' && !rest[..rest.len() - trimmed.len()].contains('\n') {
+                    return Err("unsupported escape in string".to_string());
+                }
+                rest = trimmed;
+            }
+            _ => return Err("unsupported escape in string".to_string()),
+        }
+    }
+}
+
+/// Parse a literal string after its opening quotes, returning it and the rest of the
+/// input.
+fn parse_literal_string(input: &str, multi_line: bool) -> Result<(String, &str), String> {
+    if multi_line {
+        let rest = input
+            .strip_prefix("\r\n")
+            .or_else(|| input.strip_prefix('\n'))
+            .unwrap_or(input);
+        let end = rest.find("'''").ok_or("unterminated string")?;
+        let quotes = rest[end..].len() - rest[end..].trim_start_matches('\'').len();
+        let end = end + (quotes - 3).min(2);
+        return Ok((rest[..end].to_string(), &rest[end + 3..]));
+    }
+    match input.find(['\'', '\n']) {
+        Some(end) if input[end..].starts_with('\'') => {
+            Ok((input[..end].to_string(), &input[end + 1..]))
+        }
+        _ => Err("unterminated string".to_string()),
+    }
+}
+
+/// Parse a key, possibly dotted and quoted like `tool."silentinjection".fail-on`, at the
+/// start of `input`, returning its parts and the rest of the input.
+fn parse_key(input: &str) -> Result<(Vec<String>, &str), String> {
+    let mut parts = Vec::new();
+    let mut rest = input;
+    loop {
+        rest = rest.trim_start_matches([' ', '\t']);
+        let (part, after) = if let Some(after) = rest.strip_prefix('"') {
+            parse_basic_string(after, false)?
+        } else if let Some(after) = rest.strip_prefix('\'') {
+            parse_literal_string(after, false)?
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("expected a key, found '{}'", rest.trim()));
+            }
+            (rest[..end].to_string(), &rest[end..])
+        };
+        parts.push(part);
+        rest = after.trim_start_matches([' ', '\t']);
+        match rest.strip_prefix('.') {
+            Some(after) => rest = after,
+            None => return Ok((parts, rest)),
+        }
+    }
+}
+
+/// Parse one TOML value at the start of `input`, returning it and the rest of the input.
+fn parse_value(input: &str) -> Result<(Parsed, &str), String> {
+    let input = input.trim_start();
+    let string = if let Some(rest) = input.strip_prefix(r#"""""#) {
+        Some(parse_basic_string(rest, true)?)
+    } else if let Some(rest) = input.strip_prefix('"') {
+        Some(parse_basic_string(rest, false)?)
+    } else if let Some(rest) = input.strip_prefix("'''") {
+        Some(parse_literal_string(rest, true)?)
+    } else if let Some(rest) = input.strip_prefix('\'') {
+        Some(parse_literal_string(rest, false)?)
+    } else {
+        None
+    };
+    if let Some((value, rest)) = string {
+        return Ok((Parsed::Value(ConfigValue::String(value)), rest));
+    }
+    if let Some(mut rest) = input.strip_prefix('[') {
+        let mut values = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Parsed::Value(ConfigValue::Array(values)), after));
+            }
+            let (Parsed::Value(value), after) = parse_value(rest)? else {
+                return Err("inline tables in arrays are not supported".to_string());
+            };
+            values.push(value);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("expected ',' or ']' in array".to_string());
+            }
+        }
+    }
+    if let Some(rest) = input.strip_prefix('{') {
+        let mut entries = Vec::new();
+        if let Some(after) = rest.trim_start().strip_prefix('}') {
+            return Ok((Parsed::Table(entries), after));
+        }
+        let mut rest = rest;
+        loop {
+            let (key, value, after) = parse_key_value(rest)?;
+            entries.push((key, value));
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('}') {
+                return Ok((Parsed::Table(entries), after));
+            } else {
+                return Err("expected ',' or '}' in inline table".to_string());
+            }
+        }
+    }
+    for (literal, value) in [("true", true), ("false", false)] {
+        if let Some(rest) = input.strip_prefix(literal) {
+            return Ok((Parsed::Value(ConfigValue::Bool(value)), rest));
+        }
+    }
+    let Some(number) = NUMBER_RE.find(input) else {
+        return Err(format!("unsupported value '{input}'"));
+    };
+    let text = number.as_str().replace('_', "");
+    let value = if text.contains(['.', 'e', 'E']) {
+        ConfigValue::Float(
+            text.parse()
+                .map_err(|_| format!("invalid number '{text}'"))?,
+        )
+    } else {
+        ConfigValue::Integer(
+            text.parse()
+                .map_err(|_| format!("invalid number '{text}'"))?,
+        )
+    };
+    Ok((Parsed::Value(value), &input[number.end()..]))
+}
+
+static NUMBER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[+-]?[0-9_]+(\.[0-9_]+)?([eE][+-]?[0-9]+)?").unwrap());
+
+/// Parse `key = value` at the start of `input`, returning the key's parts, the value and
+/// the rest of the input.
+fn parse_key_value(input: &str) -> Result<(Vec<String>, Parsed, &str), String> {
+    let (key, rest) = parse_key(input)?;
+    let Some(rest) = rest.strip_prefix('=') else {
+        return Err(format!("expected key = value, found '{}'", input.trim()));
+    };
+    let (value, rest) = parse_value(rest)?;
+    Ok((key, value, rest))
+}
+
+/// Add `value`, set at the dotted key `path` from the document root, to `settings` if it
+/// is an option in the table `target`, flattening inline tables.
+fn collect_setting(
+    target: &[String],
+    path: Vec<String>,
+    value: Parsed,
+    settings: &mut ConfigSettings,
+) -> Result<(), String> {
+    match value {
+        Parsed::Table(entries) => {
+            for (key, value) in entries {
+                let path = path.iter().cloned().chain(key).collect();
+                collect_setting(target, path, value, settings)?;
+            }
+        }
+        Parsed::Value(value) => {
+            let Some(option) = path.strip_prefix(target) else {
+                return Ok(());
+            };
+            let [key] = option else {
+                return Err(format!("unsupported nested option '{}'", option.join(".")));
+            };
+            let key = key.replace('_', "-");
+            if settings.insert(key.clone(), value).is_some() {
+                return Err(format!("duplicate key '{key}'"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The keys of `table` in the TOML document `content`, or its top-level keys if `table`
+/// is empty, with `_` in keys read as `-`. Keys may be set under a table header, as dotted
+/// keys or in inline tables. Statements that cannot set a key of `table` are skipped
+/// without their values being parsed, so the rest of a `pyproject.toml` may use TOML this
+/// parser does not support.
+///
+/// Only the subset of TOML needed for options is supported: basic, literal and multi-line
+/// strings, integers, floats, booleans, arrays of those and inline tables. Errors are
+/// `(1-based line, message)`.
+pub fn parse_config_table(content: &str, table: &str) -> Result<ConfigSettings, (usize, String)> {
+    let target = if table.is_empty() {
+        Vec::new()
+    } else {
+        parse_key(table).map_err(|message| (0, message))?.0
+    };
+
+    let mut settings = ConfigSettings::new();
+    let mut current = Some(Vec::new());
+    let mut lines = content.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let mut statement = line.to_string();
+        let mut lexed = lex(&statement);
+        while lexed.depth > 0 || lexed.open_string {
+            let Some((_, next)) = lines.next() else {
+                let what = if lexed.open_string {
+                    "multi-line string"
+                } else {
+                    "array or inline table"
+                };
+                return Err((i + 1, format!("unterminated {what}")));
+            };
+            statement.push('\n');
+            statement.push_str(next);
+            lexed = lex(&statement);
+        }
+        let text = lexed.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if let Some(header) = text.strip_prefix('[') {
+            // `[[array.of.tables]]` never holds options, and a header this parser cannot
+            // read is skipped with its table.
+            current = header
+                .strip_suffix(']')
+                .filter(|header| !header.starts_with('['))
+                .and_then(|header| parse_key(header).ok())
+                .filter(|(_, rest)| rest.is_empty())
+                .map(|(parts, _)| parts);
+            continue;
+        }
+        // Only the target table and its parents can set its keys.
+        let Some(current) = current.as_ref().filter(|table| target.starts_with(table)) else {
+            continue;
+        };
+        let (key, rest) = parse_key(text).map_err(|message| (i + 1, message))?;
+        let path: Vec<String> = current.iter().cloned().chain(key).collect();
+        if !path.starts_with(&target) && !target.starts_with(&path) {
+            continue;
+        }
+        let Some(rest) = rest.strip_prefix('=') else {
+            return Err((i + 1, format!("expected key = value, found '{text}'")));
+        };
+        let (value, rest) = parse_value(rest).map_err(|message| (i + 1, message))?;
+        if !rest.trim().is_empty() {
+            return Err((i + 1, format!("unexpected '{}' after value", rest.trim())));
+        }
+        collect_setting(&target, path, value, &mut settings).map_err(|message| (i + 1, message))?;
+    }
+    Ok(settings)
+}
+
+/// Read `path` and parse its `table`; `Ok(None)` if the file does not exist and is not
+/// `required`.
+fn read_config_file(
+    path: &Path,
+    table: &str,
+    required: bool,
+) -> Result<Option<ConfigSettings>, ConfigFileError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(ConfigFileError::Io {
+                path: path.to_path_buf(),
+                source,
+            });
+        }
+    };
+    parse_config_table(&content, table)
+        .map(Some)
+        .map_err(|(line, message)| ConfigFileError::Parse {
+            path: path.to_path_buf(),
+            line,
+            message,
+        })
+}
+
+/// The configured options from, in increasing order of precedence: [`CONFIG_FILE_NAME`]
+/// in `dir`, the [`PYPROJECT_TABLE`] table of `pyproject.toml` in `dir`, and the
+/// `explicit` file given with `--config`. The explicit file is read like `pyproject.toml`
+/// if it has that name and like [`CONFIG_FILE_NAME`] otherwise.
+///
+/// `dir` is the working directory, never a scanned one. The files discovered there may
+/// only set [`DISCOVERED_OPTIONS`]; the explicit file may set any option.
+pub fn load_config_files(
+    dir: &Path,
+    explicit: Option<&Path>,
+) -> Result<ConfigSettings, ConfigFileError> {
+    let table_for = |path: &Path| {
+        if path
+            .file_name()
+            .is_some_and(|name| name == "pyproject.toml")
+        {
+            PYPROJECT_TABLE
+        } else {
+            ""
+        }
+    };
+    let mut settings = ConfigSettings::new();
+    for (path, explicit) in [
+        (dir.join(CONFIG_FILE_NAME), false),
+        (dir.join("pyproject.toml"), false),
+    ]
+    .into_iter()
+    .chain(explicit.map(|path| (path.to_path_buf(), true)))
+    {
+        let Some(file_settings) = read_config_file(&path, table_for(&path), explicit)? else {
+            continue;
+        };
+        if !explicit
+            && let Some(key) = file_settings
+                .keys()
+                .find(|key| !DISCOVERED_OPTIONS.contains(&key.as_str()))
+        {
+            return Err(ConfigFileError::Untrusted {
+                path,
+                key: key.clone(),
+            });
+        }
+        tracing::debug!(path = %path.display(), "loaded configuration");
+        settings.extend(file_settings);
+    }
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const PYPROJECT: &str = r#"[project]
+name = "models"
+dependencies = [
+    "transformers==4.35.0",  # pinned
+    "torch",
+]
+
+[tool.black]
+line-length = 100
+
+[tool.silentinjection]
+fail_on = "unsafe"
+fail-on-score = 25.5
+max-file-size = 1_000_000
+skip-generated = true
+scan-ext = ["sh", 'Makefile']  # shell too
+revision-pattern = [
+    "sha256:[a-f0-9]{64}",
+    "v\\d+",
+]
+
+[[tool.other.items]]
+name = "x"
+"#;
+
+    #[test]
+    fn test_parse_config_table() {
+        let settings = parse_config_table(PYPROJECT, PYPROJECT_TABLE).unwrap();
+        let expected: ConfigSettings = [
+            ("fail-on", ConfigValue::String("unsafe".to_string())),
+            ("fail-on-score", ConfigValue::Float(25.5)),
+            ("max-file-size", ConfigValue::Integer(1_000_000)),
+            ("skip-generated", ConfigValue::Bool(true)),
+            (
+                "scan-ext",
+                ConfigValue::Array(vec![
+                    ConfigValue::String("sh".to_string()),
+                    ConfigValue::String("Makefile".to_string()),
+                ]),
+            ),
+            (
+                "revision-pattern",
+                ConfigValue::Array(vec![
+                    ConfigValue::String("sha256:[a-f0-9]{64}".to_string()),
+                    ConfigValue::String("v\\d+".to_string()),
+                ]),
+            ),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+        assert_eq!(settings, expected);
+
+        assert!(parse_config_table(PYPROJECT, "").unwrap().is_empty());
+        assert_eq!(
+            parse_config_table("[tool.silentinjection]\nfail-on = \n", PYPROJECT_TABLE),
+            Err((2, "unsupported value ''".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_config_table_toml() {
+        let content = r##"tool.silentinjection.verbose = true
+tool = { silentinjection = { detailed = true } }
+
+[tool]
+name = 1979-05-27  # not an option, so never parsed
+silentinjection.summary = true
+
+[ tool . "silentinjection" ]
+csv-separator = "\"#"  # an escaped quote before a hash
+csv-columns = """
+file,\
+  line"""
+include-context = '''
+a # b'''
+fail-on = { score = 1 }
+"##;
+        assert_eq!(
+            parse_config_table(content, PYPROJECT_TABLE),
+            Err((15, "unsupported nested option 'fail-on.score'".to_string()))
+        );
+        let content = content.replace("fail-on = { score = 1 }", "fail-on = \"unsafe\"");
+        let settings = parse_config_table(&content, PYPROJECT_TABLE).unwrap();
+        let expected: ConfigSettings = [
+            ("verbose", ConfigValue::Bool(true)),
+            ("detailed", ConfigValue::Bool(true)),
+            ("summary", ConfigValue::Bool(true)),
+            ("csv-separator", ConfigValue::String("\"#".to_string())),
+            ("csv-columns", ConfigValue::String("file,line".to_string())),
+            ("include-context", ConfigValue::String("a # b".to_string())),
+            ("fail-on", ConfigValue::String("unsafe".to_string())),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+        assert_eq!(settings, expected);
+
+        assert_eq!(
+            parse_config_table("verbose = true\nverbose = false\n", ""),
+            Err((2, "duplicate key 'verbose'".to_string()))
+        );
+        assert_eq!(
+            parse_config_table("csv-columns = \"\"\"\nfile\n", ""),
+            Err((1, "unterminated multi-line string".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_load_config_files() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        assert!(load_config_files(root.path(), None).unwrap().is_empty());
+
+        // A discovered file may not set options outside DISCOVERED_OPTIONS.
+        fs::write(root.path().join("pyproject.toml"), PYPROJECT)?;
+        assert!(matches!(
+            load_config_files(root.path(), None),
+            Err(ConfigFileError::Untrusted { key, .. }) if key == "max-file-size"
+        ));
+
+        // But the explicit file may.
+        let explicit = root.path().join("ci").join("pyproject.toml");
+        fs::create_dir(root.path().join("ci"))?;
+        fs::rename(root.path().join("pyproject.toml"), &explicit)?;
+        let settings = load_config_files(root.path(), Some(&explicit)).unwrap();
+        assert_eq!(settings["max-file-size"], ConfigValue::Integer(1_000_000));
+
+        fs::write(
+            root.path().join("pyproject.toml"),
+            "[tool.silentinjection]\nfail-on = \"unsafe\"\n",
+        )?;
+        fs::write(
+            root.path().join(CONFIG_FILE_NAME),
+            "fail-on = \"partial\"\nlazy-walk = true\n",
+        )?;
+        let explicit = root.path().join("ci.toml");
+        fs::write(&explicit, "fail-on-score = 5\n")?;
+        let settings = load_config_files(root.path(), Some(&explicit)).unwrap();
+        assert_eq!(
+            settings["fail-on"],
+            ConfigValue::String("unsafe".to_string()),
+            "pyproject.toml takes precedence over .silentinjection.toml"
+        );
+        assert_eq!(settings["lazy-walk"], ConfigValue::Bool(true));
+        assert_eq!(settings["fail-on-score"], ConfigValue::Integer(5));
+
+        let missing = root.path().join("missing.toml");
+        assert!(matches!(
+            load_config_files(root.path(), Some(&missing)),
+            Err(ConfigFileError::Io { .. })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,965 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+
+use std::collections::BTreeMap;
+
+use crate::directory::FileRow;
+use crate::models::ModelLoad;
+
+pub fn format_csv_field(field: &str) -> String {
+    format_csv_field_with_separator(field, ',')
+}
+
+/// [`format_csv_field`] for a file delimited by `separator`, which is quoted as well.
+pub fn format_csv_field_with_separator(field: &str, separator: char) -> String {
+    // Quote the field if it contains the separator, commas, quotes, or newlines
+    if field.contains(separator)
+        || field.contains(',')
+        || field.contains('"')
+        || field.contains('\n')
+    {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Version of the CSV schema written by default.
+///
+/// * 1: `org,repo,file,safe_usages,partial_usages,unsafe_usages`
+/// * 2: adds `trust_remote_code_usages`
+pub const CURRENT_FORMAT_VERSION: u8 = 2;
+
+/// Header line of the given schema version, or `None` for an unknown version.
+pub fn csv_header(version: u8) -> Option<&'static str> {
+    match version {
+        1 => Some("org,repo,file,safe_usages,partial_usages,unsafe_usages"),
+        2 => {
+            Some("org,repo,file,safe_usages,partial_usages,unsafe_usages,trust_remote_code_usages")
+        }
+        _ => None,
+    }
+}
+
+/// Rewrite CSV output of schema `from_version` as schema `to_version`.
+///
+/// Columns added by a later version are filled with `0` when upgrading, since older
+/// reports did not track them, and dropped again when downgrading. Records are parsed with
+/// [`parse_csv_records`], so quoted fields may span lines, and written back with
+/// [`format_csv_field`]. Returns the input unchanged if either version is unknown.
+pub fn migrate_csv(from_version: u8, to_version: u8, input: &str) -> String {
+    let (Some(from_header), Some(to_header)) = (csv_header(from_version), csv_header(to_version))
+    else {
+        return input.to_string();
+    };
+    if from_version == to_version {
+        return input.to_string();
+    }
+
+    let mut output = String::with_capacity(input.len());
+    for mut record in parse_csv_records(input) {
+        if record.iter().map(String::as_str).eq(from_header.split(',')) {
+            output.push_str(to_header);
+            output.push('\n');
+            continue;
+        }
+        if from_version < to_version {
+            record.push("0".to_string());
+        } else {
+            record.pop();
+        }
+        let fields: Vec<String> = record.iter().map(|f| format_csv_field(f)).collect();
+        output.push_str(&fields.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+/// Split CSV text into records, honouring quoted fields with embedded commas, doubled
+/// quotes and newlines as written by [`format_csv_field`].
+pub fn parse_csv_records(input: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// A problem found by [`verify_csv`]. Rows are numbered from 1, the header being row 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvError {
+    UnsupportedVersion(u8),
+    /// The first row is not the header of the schema version.
+    HeaderMismatch {
+        expected: String,
+        found: String,
+    },
+    MissingFields {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    ExtraFields {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A usage count that is not a non-negative integer.
+    InvalidCount {
+        row: usize,
+        column: String,
+        value: String,
+    },
+}
+
+impl std::fmt::Display for CsvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvError::UnsupportedVersion(version) => {
+                write!(f, "unsupported CSV format version {version}")
+            }
+            CsvError::HeaderMismatch { expected, found } => {
+                write!(f, "row 1: expected header {expected:?}, found {found:?}")
+            }
+            CsvError::MissingFields {
+                row,
+                expected,
+                found,
+            } => write!(f, "row {row}: {found} fields, expected {expected}"),
+            CsvError::ExtraFields {
+                row,
+                expected,
+                found,
+            } => write!(f, "row {row}: {found} fields, expected only {expected}"),
+            CsvError::InvalidCount { row, column, value } => {
+                write!(f, "row {row}: {column} is not a count: {value:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+/// Check CSV text against schema `schema_version`: the header line, the number of fields
+/// in every row and that every usage count parses as a `usize`.
+pub fn verify_csv(content: &str, schema_version: u8) -> Vec<CsvError> {
+    let Some(header) = csv_header(schema_version) else {
+        return vec![CsvError::UnsupportedVersion(schema_version)];
+    };
+    let columns: Vec<&str> = header.split(',').collect();
+    let mut errors = Vec::new();
+    let mut records = parse_csv_records(content).into_iter();
+
+    let found = records.next().map(|r| r.join(",")).unwrap_or_default();
+    if found != header {
+        errors.push(CsvError::HeaderMismatch {
+            expected: header.to_string(),
+            found,
+        });
+    }
+    for (i, record) in records.enumerate() {
+        let row = i + 2;
+        if record.len() < columns.len() {
+            errors.push(CsvError::MissingFields {
+                row,
+                expected: columns.len(),
+                found: record.len(),
+            });
+        } else if record.len() > columns.len() {
+            errors.push(CsvError::ExtraFields {
+                row,
+                expected: columns.len(),
+                found: record.len(),
+            });
+        }
+        // The first three columns are org, repo and file; the rest are counts.
+        for (column, value) in columns.iter().zip(&record).skip(3) {
+            if value.parse::<usize>().is_err() {
+                errors.push(CsvError::InvalidCount {
+                    row,
+                    column: column.to_string(),
+                    value: value.clone(),
+                });
+            }
+        }
+    }
+    errors
+}
+
+/// How [`deduplicate_csv`] merges rows for the same `(org, repo, file)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupStrategy {
+    KeepFirst,
+    KeepLast,
+    /// Add up the usage counts of all rows.
+    SumCounts,
+    /// Take the largest value of each usage count.
+    MaxCounts,
+}
+
+/// Collapse rows with the same `(org, repo, file)` into one, e.g. after appending the
+/// same files from several runs.
+///
+/// The header line, if present, is kept. Rows stay in the order of their first occurrence.
+/// Count columns missing from shorter rows, as in mixed format versions, are treated as 0.
+pub fn deduplicate_csv(input: &str, strategy: DedupStrategy) -> String {
+    let mut records = parse_csv_records(input).into_iter().peekable();
+    let header = records.next_if(|r| r.len() >= 3 && r[..3] == ["org", "repo", "file"]);
+
+    let mut order: Vec<(String, String, String)> = Vec::new();
+    let mut rows: std::collections::HashMap<(String, String, String), Vec<String>> =
+        std::collections::HashMap::new();
+    for record in records {
+        if record.len() < 3 {
+            continue;
+        }
+        let key = (record[0].clone(), record[1].clone(), record[2].clone());
+        let Some(existing) = rows.get_mut(&key) else {
+            order.push(key.clone());
+            rows.insert(key, record);
+            continue;
+        };
+        match strategy {
+            DedupStrategy::KeepFirst => {}
+            DedupStrategy::KeepLast => *existing = record,
+            DedupStrategy::SumCounts | DedupStrategy::MaxCounts => {
+                if record.len() > existing.len() {
+                    existing.resize(record.len(), "0".to_string());
+                }
+                for (i, value) in record.iter().enumerate().skip(3) {
+                    let a: u64 = existing[i].parse().unwrap_or(0);
+                    let b: u64 = value.parse().unwrap_or(0);
+                    let merged = if strategy == DedupStrategy::SumCounts {
+                        a + b
+                    } else {
+                        a.max(b)
+                    };
+                    existing[i] = merged.to_string();
+                }
+            }
+        }
+    }
+
+    let mut output = String::new();
+    for record in header.iter().chain(order.iter().map(|key| &rows[key])) {
+        let fields: Vec<String> = record.iter().map(|f| format_csv_field(f)).collect();
+        output.push_str(&fields.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+/// A column that can be selected with [`CsvOptions::columns`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvColumnSpec {
+    Org,
+    Repo,
+    File,
+    SafeUsages,
+    PartialUsages,
+    UnsafeUsages,
+    TrustRemoteCodeUsages,
+    Lines,
+    Bytes,
+}
+
+impl CsvColumnSpec {
+    pub const ALL: [CsvColumnSpec; 9] = [
+        CsvColumnSpec::Org,
+        CsvColumnSpec::Repo,
+        CsvColumnSpec::File,
+        CsvColumnSpec::SafeUsages,
+        CsvColumnSpec::PartialUsages,
+        CsvColumnSpec::UnsafeUsages,
+        CsvColumnSpec::TrustRemoteCodeUsages,
+        CsvColumnSpec::Lines,
+        CsvColumnSpec::Bytes,
+    ];
+
+    /// Header name of the column.
+    pub fn name(self) -> &'static str {
+        match self {
+            CsvColumnSpec::Org => "org",
+            CsvColumnSpec::Repo => "repo",
+            CsvColumnSpec::File => "file",
+            CsvColumnSpec::SafeUsages => "safe_usages",
+            CsvColumnSpec::PartialUsages => "partial_usages",
+            CsvColumnSpec::UnsafeUsages => "unsafe_usages",
+            CsvColumnSpec::TrustRemoteCodeUsages => "trust_remote_code_usages",
+            CsvColumnSpec::Lines => "lines",
+            CsvColumnSpec::Bytes => "bytes",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|column| column.name() == name)
+    }
+
+    /// The formatted field of `row` for this column.
+    fn field(self, row: &FileRow, separator: char) -> String {
+        match self {
+            CsvColumnSpec::Org => format_csv_field_with_separator(&row.org, separator),
+            CsvColumnSpec::Repo => format_csv_field_with_separator(&row.repo, separator),
+            CsvColumnSpec::File => format_csv_field_with_separator(&row.file, separator),
+            CsvColumnSpec::SafeUsages => row.safe_usages.to_string(),
+            CsvColumnSpec::PartialUsages => row.partial_usages.to_string(),
+            CsvColumnSpec::UnsafeUsages => row.unsafe_usages.to_string(),
+            CsvColumnSpec::TrustRemoteCodeUsages => row.trust_remote_code_usages.to_string(),
+            CsvColumnSpec::Lines => row.lines.to_string(),
+            CsvColumnSpec::Bytes => row.bytes.to_string(),
+        }
+    }
+}
+
+/// Parse a comma-separated list of column names such as `org,repo,file,unsafe_usages`.
+/// Returns the first unknown name as the error.
+pub fn parse_csv_columns(spec: &str) -> Result<Vec<CsvColumnSpec>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .map(|name| CsvColumnSpec::from_name(name).ok_or_else(|| name.to_string()))
+        .collect()
+}
+
+/// Whether `write_file_csv` writes the header line, the data rows, or both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeaderMode {
+    #[default]
+    Include,
+    /// Data rows only, for appending to or stitching together existing files.
+    Omit,
+    /// The header line only.
+    Only,
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub header: HeaderMode,
+    /// Append to an existing file instead of truncating it. The header is only written
+    /// when the file is empty.
+    pub append: bool,
+    /// Schema version to write, so consumers can pin the columns they parse.
+    pub format_version: u8,
+    /// Write exactly these columns, in this order, instead of those of `format_version`.
+    pub columns: Option<Vec<CsvColumnSpec>>,
+    /// Column delimiter, e.g. `;` for spreadsheets in locales with a decimal comma.
+    pub separator: char,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            header: HeaderMode::default(),
+            append: false,
+            format_version: CURRENT_FORMAT_VERSION,
+            columns: None,
+            separator: ',',
+        }
+    }
+}
+
+/// Open `output_path` for writing, or appending with `options.append`, and lock it.
+/// Also returns whether the file is empty, i.e. still needs a header.
+fn open_csv(output_path: &str, options: &CsvOptions) -> std::io::Result<(File, bool)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(options.append)
+        .write(true)
+        .truncate(false)
+        .open(output_path)?;
+    // Hold an exclusive advisory lock so concurrent scans appending to the same file
+    // do not interleave their rows, and only truncate once it is held.
+    file.lock()?;
+    if !options.append {
+        file.set_len(0)?;
+    }
+    let is_empty = file.metadata()?.len() == 0;
+    Ok((file, is_empty))
+}
+
+pub fn write_file_csv(
+    output_path: &str,
+    file_data: &[FileRow],
+    options: &CsvOptions,
+) -> std::io::Result<()> {
+    let Some(header) = csv_header(options.format_version) else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("unsupported CSV format version {}", options.format_version),
+        ));
+    };
+    let (file, is_empty) = open_csv(output_path, options)?;
+
+    let sep = options.separator;
+    let mut writer = BufWriter::new(file);
+    if options.header != HeaderMode::Omit && is_empty {
+        match &options.columns {
+            Some(columns) => {
+                let names: Vec<&str> = columns.iter().map(|column| column.name()).collect();
+                writeln!(writer, "{}", names.join(&sep.to_string()))?;
+            }
+            None => writeln!(writer, "{}", header.replace(',', &sep.to_string()))?,
+        }
+    }
+    if options.header == HeaderMode::Only {
+        return Ok(());
+    }
+    if let Some(columns) = &options.columns {
+        for row in file_data {
+            let fields: Vec<String> = columns
+                .iter()
+                .map(|column| column.field(row, sep))
+                .collect();
+            writeln!(writer, "{}", fields.join(&sep.to_string()))?;
+        }
+        return writer.flush();
+    }
+    for row in file_data {
+        let formatted_org = format_csv_field_with_separator(&row.org, sep);
+        let formatted_repo = format_csv_field_with_separator(&row.repo, sep);
+        let formatted_file = format_csv_field_with_separator(&row.file, sep);
+        write!(
+            writer,
+            "{formatted_org}{sep}{formatted_repo}{sep}{formatted_file}{sep}{}{sep}{}{sep}{}",
+            row.safe_usages, row.partial_usages, row.unsafe_usages,
+        )?;
+        if options.format_version >= 2 {
+            write!(writer, "{sep}{}", row.trust_remote_code_usages)?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Header of the CSV written by [`write_models_csv`].
+pub const MODELS_CSV_HEADER: &str =
+    "model_name,org,repo,file,status,safe_usages,partial_usages,unsafe_usages";
+
+/// Write one row per model and file, as grouped by [`group_by_model`]. The header mode,
+/// append flag and separator of `options` apply; its format version and columns do not.
+///
+/// [`group_by_model`]: crate::models::group_by_model
+pub fn write_models_csv(
+    output_path: &str,
+    models: &BTreeMap<String, Vec<ModelLoad>>,
+    options: &CsvOptions,
+) -> std::io::Result<()> {
+    let (file, is_empty) = open_csv(output_path, options)?;
+    let sep = options.separator;
+    let mut writer = BufWriter::new(file);
+    if options.header != HeaderMode::Omit && is_empty {
+        writeln!(
+            writer,
+            "{}",
+            MODELS_CSV_HEADER.replace(',', &sep.to_string())
+        )?;
+    }
+    if options.header == HeaderMode::Only {
+        return writer.flush();
+    }
+    for (model_name, loads) in models {
+        for load in loads {
+            let fields = [
+                model_name.as_str(),
+                &load.org,
+                &load.repo,
+                &load.file,
+                &load.status.to_string(),
+            ]
+            .map(|field| format_csv_field_with_separator(field, sep));
+            writeln!(
+                writer,
+                "{}{sep}{}{sep}{}{sep}{}",
+                fields.join(&sep.to_string()),
+                load.safe_usages,
+                load.partial_usages,
+                load.unsafe_usages
+            )?;
+        }
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::NamedTempFile;
+
+    fn row(
+        org: &str,
+        repo: &str,
+        file: &str,
+        safe: usize,
+        partial: usize,
+        unsafe_: usize,
+    ) -> FileRow {
+        FileRow {
+            org: org.to_string(),
+            repo: repo.to_string(),
+            file: file.to_string(),
+            safe_usages: safe,
+            partial_usages: partial,
+            unsafe_usages: unsafe_,
+            trust_remote_code_usages: 0,
+            lines: 0,
+            bytes: 0,
+            scan_time: None,
+        }
+    }
+
+    #[test]
+    fn test_format_csv_field_normal() {
+        assert_eq!(format_csv_field("normal_project"), "normal_project");
+        assert_eq!(format_csv_field("project-name"), "project-name");
+        assert_eq!(format_csv_field("project_123"), "project_123");
+    }
+
+    #[test]
+    fn test_format_csv_field_with_comma() {
+        assert_eq!(
+            format_csv_field("project, with comma"),
+            "\"project, with comma\""
+        );
+        assert_eq!(format_csv_field("a,b,c"), "\"a,b,c\"");
+        assert_eq!(
+            format_csv_field("comma, at, multiple, places"),
+            "\"comma, at, multiple, places\""
+        );
+    }
+
+    #[test]
+    fn test_format_csv_field_with_quotes() {
+        assert_eq!(
+            format_csv_field("project \"quoted\""),
+            "\"project \"\"quoted\"\"\""
+        );
+        assert_eq!(format_csv_field("\"start quote"), "\"\"\"start quote\"");
+        assert_eq!(format_csv_field("end quote\""), "\"end quote\"\"\"");
+        assert_eq!(
+            format_csv_field("multiple \"quotes\" here"),
+            "\"multiple \"\"quotes\"\" here\""
+        );
+    }
+
+    #[test]
+    fn test_format_csv_field_with_newline() {
+        assert_eq!(
+            format_csv_field("project\nwith\nnewlines"),
+            "\"project\nwith\nnewlines\""
+        );
+        assert_eq!(format_csv_field("line1\nline2"), "\"line1\nline2\"");
+    }
+
+    #[test]
+    fn test_format_csv_field_combined_special_chars() {
+        assert_eq!(
+            format_csv_field("project, \"with\" both"),
+            "\"project, \"\"with\"\" both\""
+        );
+        assert_eq!(
+            format_csv_field("all: comma, \"quote\", and\nnewline"),
+            "\"all: comma, \"\"quote\"\", and\nnewline\""
+        );
+    }
+
+    #[test]
+    fn test_write_file_csv_basic() -> std::io::Result<()> {
+        let file_data = vec![
+            row("org1", "repo1", "file1.py", 1, 2, 3),
+            row("org2", "repo2", "file2.py", 0, 1, 0),
+        ];
+
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+        // Leftovers of an earlier, longer report are truncated away.
+        std::fs::write(temp_path, "stale\n".repeat(100))?;
+
+        write_file_csv(temp_path, &file_data, &CsvOptions::default())?;
+
+        let mut contents = String::new();
+        let mut file = File::open(temp_path)?;
+        file.read_to_string(&mut contents)?;
+
+        assert!(contents.contains("org,repo,file,safe_usages,partial_usages,unsafe_usages"));
+        assert!(contents.contains("org1,repo1,file1.py,1,2,3"));
+        assert!(contents.contains("org2,repo2,file2.py,0,1,0"));
+        assert!(!contents.contains("stale"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_file_csv_with_special_chars() -> std::io::Result<()> {
+        let file_data = vec![
+            row("normal_org", "normal_repo", "normal.py", 1, 0, 0),
+            row("org, with comma", "repo", "file.py", 0, 1, 0),
+            row("org", "repo \"quoted\"", "file.py", 0, 0, 1),
+            row("org", "repo", "path/with, comma/file.py", 1, 1, 1),
+        ];
+
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+
+        write_file_csv(temp_path, &file_data, &CsvOptions::default())?;
+
+        let mut contents = String::new();
+        let mut file = File::open(temp_path)?;
+        file.read_to_string(&mut contents)?;
+
+        // Check header
+        assert!(contents.contains("org,repo,file,safe_usages,partial_usages,unsafe_usages"));
+
+        // Check each entry is properly formatted
+        assert!(contents.contains("normal_org,normal_repo,normal.py,1,0,0"));
+        assert!(contents.contains("\"org, with comma\",repo,file.py,0,1,0"));
+        assert!(contents.contains("org,\"repo \"\"quoted\"\"\",file.py,0,0,1"));
+        assert!(contents.contains("org,repo,\"path/with, comma/file.py\",1,1,1"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_file_csv_header_modes() -> std::io::Result<()> {
+        let file_data = vec![row("org1", "repo1", "file1.py", 1, 2, 3)];
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+
+        let options = CsvOptions {
+            header: HeaderMode::Omit,
+            ..Default::default()
+        };
+        write_file_csv(temp_path, &file_data, &options)?;
+        let contents = std::fs::read_to_string(temp_path)?;
+        assert!(contents.starts_with("org1,repo1,file1.py,1,2,3"));
+        assert_eq!(contents.lines().count(), 1);
+
+        let options = CsvOptions {
+            header: HeaderMode::Only,
+            ..Default::default()
+        };
+        write_file_csv(temp_path, &file_data, &options)?;
+        let contents = std::fs::read_to_string(temp_path)?;
+        assert_eq!(
+            contents,
+            "org,repo,file,safe_usages,partial_usages,unsafe_usages,trust_remote_code_usages\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_file_csv_append() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("report.csv");
+        let path = path.to_str().unwrap();
+        let options = CsvOptions {
+            append: true,
+            ..Default::default()
+        };
+
+        write_file_csv(path, &[row("org1", "repo1", "a.py", 1, 0, 0)], &options)?;
+        write_file_csv(
+            path,
+            &[
+                row("org2", "repo2", "b.py", 0, 1, 0),
+                row("org2", "repo2", "c.py", 0, 0, 1),
+            ],
+            &options,
+        )?;
+
+        let contents = std::fs::read_to_string(path)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], csv_header(CURRENT_FORMAT_VERSION).unwrap());
+        assert_eq!(
+            contents
+                .matches("org,repo,file,safe_usages,partial_usages,unsafe_usages")
+                .count(),
+            1
+        );
+        assert_eq!(lines[3], "org2,repo2,c.py,0,0,1,0");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_file_csv_zero_counts() -> std::io::Result<()> {
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+
+        write_file_csv(
+            temp_path,
+            &[row("org", "repo", "org/repo/plain.py", 0, 0, 0)],
+            &CsvOptions::default(),
+        )?;
+
+        let contents = std::fs::read_to_string(temp_path)?;
+        assert!(contents.contains("org,repo,org/repo/plain.py,0,0,0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_file_csv_format_version_1() -> std::io::Result<()> {
+        let mut trusting = row("org", "repo", "a.py", 0, 0, 2);
+        trusting.trust_remote_code_usages = 1;
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+
+        let options = CsvOptions {
+            format_version: 1,
+            ..Default::default()
+        };
+        write_file_csv(temp_path, &[trusting.clone()], &options)?;
+        assert_eq!(
+            std::fs::read_to_string(temp_path)?,
+            "org,repo,file,safe_usages,partial_usages,unsafe_usages\norg,repo,a.py,0,0,2\n"
+        );
+
+        write_file_csv(temp_path, &[trusting], &CsvOptions::default())?;
+        assert!(std::fs::read_to_string(temp_path)?.ends_with("org,repo,a.py,0,0,2,1\n"));
+
+        let options = CsvOptions {
+            format_version: 9,
+            ..Default::default()
+        };
+        assert!(write_file_csv(temp_path, &[], &options).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_file_csv_separator() -> std::io::Result<()> {
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+
+        let options = CsvOptions {
+            separator: ';',
+            ..Default::default()
+        };
+        write_file_csv(
+            temp_path,
+            &[
+                row("org, inc", "repo", "a.py", 1, 0, 2),
+                row("org", "re;po", "b.py", 0, 1, 0),
+            ],
+            &options,
+        )?;
+        assert_eq!(
+            std::fs::read_to_string(temp_path)?,
+            "org;repo;file;safe_usages;partial_usages;unsafe_usages;trust_remote_code_usages\n\
+             \"org, inc\";repo;a.py;1;0;2;0\n\
+             org;\"re;po\";b.py;0;1;0;0\n"
+        );
+
+        // Semicolons only need quoting when they are the separator.
+        assert_eq!(format_csv_field("re;po"), "re;po");
+        assert_eq!(format_csv_field_with_separator("re;po", ';'), "\"re;po\"");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_models_csv() -> std::io::Result<()> {
+        use crate::scanner::Status;
+
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+        let load = |repo: &str, status, counts: (usize, usize, usize)| ModelLoad {
+            org: "org".to_string(),
+            repo: repo.to_string(),
+            file: format!("org/{repo}/load.py"),
+            status,
+            safe_usages: counts.0,
+            partial_usages: counts.1,
+            unsafe_usages: counts.2,
+        };
+        let models = BTreeMap::from([(
+            "bert-base-uncased".to_string(),
+            vec![
+                load("repo-a", Status::Unsafe, (0, 1, 1)),
+                load("repo-b", Status::Safe, (1, 0, 0)),
+            ],
+        )]);
+        write_models_csv(temp_path, &models, &CsvOptions::default())?;
+        assert_eq!(
+            std::fs::read_to_string(temp_path)?,
+            "model_name,org,repo,file,status,safe_usages,partial_usages,unsafe_usages\n\
+             bert-base-uncased,org,repo-a,org/repo-a/load.py,unsafe,0,1,1\n\
+             bert-base-uncased,org,repo-b,org/repo-b/load.py,safe,1,0,0\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_file_csv_columns() -> std::io::Result<()> {
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+
+        let options = CsvOptions {
+            columns: Some(parse_csv_columns("file,unsafe_usages").unwrap()),
+            ..Default::default()
+        };
+        write_file_csv(
+            temp_path,
+            &[
+                row("org", "repo", "a.py", 1, 0, 2),
+                row("org", "repo", "with, comma.py", 0, 1, 0),
+            ],
+            &options,
+        )?;
+        assert_eq!(
+            std::fs::read_to_string(temp_path)?,
+            "file,unsafe_usages\na.py,2\n\"with, comma.py\",0\n"
+        );
+
+        assert_eq!(
+            parse_csv_columns("org, repo,lines"),
+            Ok(vec![
+                CsvColumnSpec::Org,
+                CsvColumnSpec::Repo,
+                CsvColumnSpec::Lines
+            ])
+        );
+        assert_eq!(
+            parse_csv_columns("file,model_name"),
+            Err("model_name".to_string())
+        );
+        assert_eq!(parse_csv_columns("file,"), Err(String::new()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_csv_v1_to_v2() {
+        let v1 = "org,repo,file,safe_usages,partial_usages,unsafe_usages\n\
+                  org1,repo1,file1.py,1,2,3\n\
+                  \"org, with comma\",repo,file.py,0,1,0\n";
+        let v2 = migrate_csv(1, 2, v1);
+        assert_eq!(
+            v2,
+            "org,repo,file,safe_usages,partial_usages,unsafe_usages,trust_remote_code_usages\n\
+             org1,repo1,file1.py,1,2,3,0\n\
+             \"org, with comma\",repo,file.py,0,1,0,0\n"
+        );
+        assert_eq!(migrate_csv(2, 1, &v2), v1);
+        assert_eq!(migrate_csv(1, 7, v1), v1);
+
+        let multiline = "org,repo,file,safe_usages,partial_usages,unsafe_usages\n\
+                         org,repo,\"odd\nname.py\",1,0,0\n";
+        assert_eq!(
+            migrate_csv(1, 2, multiline),
+            "org,repo,file,safe_usages,partial_usages,unsafe_usages,trust_remote_code_usages\n\
+             org,repo,\"odd\nname.py\",1,0,0,0\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_records() {
+        let records = parse_csv_records("a,\"b, c\",\"say \"\"hi\"\"\"\r\n\"multi\nline\",x,\n");
+        assert_eq!(
+            records,
+            vec![
+                vec!["a", "b, c", "say \"hi\""],
+                vec!["multi\nline", "x", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_csv() {
+        let header = csv_header(2).unwrap();
+        assert!(verify_csv(&format!("{header}\n\"org, inc\",repo,a.py,1,0,2,0\n"), 2).is_empty());
+
+        let input = format!(
+            "{header}\n\
+             org,repo,a.py,1,0\n\
+             org,repo,b.py,1,x,2,-1\n\
+             org,repo,c.py,1,0,2,0,9\n"
+        );
+        assert_eq!(
+            verify_csv(&input, 2),
+            vec![
+                CsvError::MissingFields {
+                    row: 2,
+                    expected: 7,
+                    found: 5
+                },
+                CsvError::InvalidCount {
+                    row: 3,
+                    column: "partial_usages".to_string(),
+                    value: "x".to_string()
+                },
+                CsvError::InvalidCount {
+                    row: 3,
+                    column: "trust_remote_code_usages".to_string(),
+                    value: "-1".to_string()
+                },
+                CsvError::ExtraFields {
+                    row: 4,
+                    expected: 7,
+                    found: 8
+                },
+            ]
+        );
+
+        let errors = verify_csv(&input, 1);
+        assert!(matches!(errors[0], CsvError::HeaderMismatch { .. }));
+        assert_eq!(verify_csv("", 3), vec![CsvError::UnsupportedVersion(3)]);
+    }
+
+    #[test]
+    fn test_deduplicate_csv_strategies() {
+        let input = "org,repo,file,safe_usages,partial_usages,unsafe_usages\n\
+                     org,repo,a.py,1,0,2\n\
+                     \"org, inc\",repo,b.py,0,1,0\n\
+                     org,repo,a.py,0,3,1\n";
+        let header = "org,repo,file,safe_usages,partial_usages,unsafe_usages\n";
+
+        assert_eq!(
+            deduplicate_csv(input, DedupStrategy::KeepFirst),
+            format!("{header}org,repo,a.py,1,0,2\n\"org, inc\",repo,b.py,0,1,0\n")
+        );
+        assert_eq!(
+            deduplicate_csv(input, DedupStrategy::KeepLast),
+            format!("{header}org,repo,a.py,0,3,1\n\"org, inc\",repo,b.py,0,1,0\n")
+        );
+        assert_eq!(
+            deduplicate_csv(input, DedupStrategy::SumCounts),
+            format!("{header}org,repo,a.py,1,3,3\n\"org, inc\",repo,b.py,0,1,0\n")
+        );
+        assert_eq!(
+            deduplicate_csv(input, DedupStrategy::MaxCounts),
+            format!("{header}org,repo,a.py,1,3,2\n\"org, inc\",repo,b.py,0,1,0\n")
+        );
+
+        let without_header = "org,repo,a.py,1,0,0\norg,repo,a.py,1,0,0,2\n";
+        assert_eq!(
+            deduplicate_csv(without_header, DedupStrategy::SumCounts),
+            "org,repo,a.py,2,0,0,2\n"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+use crate::risk::PICKLE_PATTERNS;
+use crate::scanner::{Finding, Status, has_suspicious_sha, uses_trust_remote_code};
+
+/// A Common Weakness Enumeration entry, such as CWE-494.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CweId(pub u16);
+
+impl CweId {
+    /// CWE-22: Improper Limitation of a Pathname to a Restricted Directory.
+    pub const PATH_TRAVERSAL: CweId = CweId(22);
+    /// CWE-494: Download of Code Without Integrity Check.
+    pub const DOWNLOAD_WITHOUT_INTEGRITY_CHECK: CweId = CweId(494);
+    /// CWE-502: Deserialization of Untrusted Data.
+    pub const UNTRUSTED_DESERIALIZATION: CweId = CweId(502);
+    /// CWE-829: Inclusion of Functionality from Untrusted Control Sphere.
+    pub const UNTRUSTED_FUNCTIONALITY: CweId = CweId(829);
+
+    /// The entry's name, for the weaknesses defined above.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            CweId::PATH_TRAVERSAL => {
+                Some("Improper Limitation of a Pathname to a Restricted Directory")
+            }
+            CweId::DOWNLOAD_WITHOUT_INTEGRITY_CHECK => {
+                Some("Download of Code Without Integrity Check")
+            }
+            CweId::UNTRUSTED_DESERIALIZATION => Some("Deserialization of Untrusted Data"),
+            CweId::UNTRUSTED_FUNCTIONALITY => {
+                Some("Inclusion of Functionality from Untrusted Control Sphere")
+            }
+            _ => None,
+        }
+    }
+
+    /// The entry's page on cwe.mitre.org.
+    pub fn url(self) -> String {
+        format!("https://cwe.mitre.org/data/definitions/{}.html", self.0)
+    }
+}
+
+impl fmt::Display for CweId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CWE-{}", self.0)
+    }
+}
+
+/// Serialised as `{"id": "CWE-494", "url": "https://..."}`.
+impl Serialize for CweId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CweId", 2)?;
+        state.serialize_field("id", &self.to_string())?;
+        state.serialize_field("url", &self.url())?;
+        state.end()
+    }
+}
+
+/// Reads the `{"id": "CWE-494", ...}` form written by [`Serialize`]; `url` is ignored.
+impl<'de> Deserialize<'de> for CweId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Repr {
+            id: String,
+        }
+        let repr = Repr::deserialize(deserializer)?;
+        repr.id
+            .strip_prefix("CWE-")
+            .and_then(|id| id.parse().ok())
+            .map(CweId)
+            .ok_or_else(|| de::Error::custom(format!("invalid CWE ID {:?}", repr.id)))
+    }
+}
+
+/// Properties of a finding, besides its status, that add weaknesses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingModifier {
+    /// The call passes `trust_remote_code=True` and runs code from the model repository.
+    TrustRemoteCode,
+    /// The call unpickles the downloaded file; see [`PICKLE_PATTERNS`].
+    PickleLoad,
+    /// The pinned revision looks like a commit SHA but is implausible, e.g. all zeros; see
+    /// [`has_suspicious_sha`].
+    SuspiciousSha,
+    /// A `snapshot_download` that skips weight files through `ignore_patterns`, so checks
+    /// over the whole snapshot may be skipped; see [`excludes_weight_files`].
+    ///
+    /// [`excludes_weight_files`]: crate::scanner::excludes_weight_files
+    IncompleteDownload,
+    /// The call is in a private repository, where an unsafe status is lowered to partially
+    /// safe as an accepted risk; see [`ScanConfig::private_repos`]. Adds no weakness.
+    ///
+    /// [`ScanConfig::private_repos`]: crate::config::ScanConfig::private_repos
+    PrivateRepo,
+}
+
+impl FindingModifier {
+    /// The modifiers that apply to `finding`.
+    pub fn of(finding: &Finding) -> Vec<FindingModifier> {
+        let mut modifiers = Vec::new();
+        if uses_trust_remote_code(&finding.call) {
+            modifiers.push(FindingModifier::TrustRemoteCode);
+        }
+        if PICKLE_PATTERNS.contains(&finding.pattern.as_str()) {
+            modifiers.push(FindingModifier::PickleLoad);
+        }
+        if has_suspicious_sha(&finding.call) {
+            modifiers.push(FindingModifier::SuspiciousSha);
+        }
+        if finding.incomplete_download {
+            modifiers.push(FindingModifier::IncompleteDownload);
+        }
+        if finding.private_repo {
+            modifiers.push(FindingModifier::PrivateRepo);
+        }
+        modifiers
+    }
+}
+
+/// The weaknesses of a finding with `status` and `modifiers`, sorted by ID.
+///
+/// Unpinned and mutably pinned downloads, including metadata loads, are CWE-494, calls to an unpinned remote model
+/// CWE-829 and path traversals CWE-22. Ignored and safe findings and unpinned API model versions have none of their
+/// own, but `trust_remote_code=True` adds CWE-829, pickle loads CWE-502 and suspicious SHAs
+/// and incomplete downloads CWE-494 to any status above safe.
+pub fn cwe_for_status(status: Status, modifiers: &[FindingModifier]) -> Vec<CweId> {
+    let mut cwes = match status {
+        Status::Ignored | Status::Safe | Status::ApiVersionUnpinned => Vec::new(),
+        Status::RemoteCall => vec![CweId::UNTRUSTED_FUNCTIONALITY],
+        Status::PathTraversal => vec![CweId::PATH_TRAVERSAL],
+        Status::PartiallySafe
+        | Status::MetadataLoad
+        | Status::LocalFileLoad
+        | Status::Unsafe
+        | Status::TrainingWithUnsafeBase => {
+            vec![CweId::DOWNLOAD_WITHOUT_INTEGRITY_CHECK]
+        }
+    };
+    if status > Status::Safe {
+        cwes.extend(modifiers.iter().filter_map(|modifier| match modifier {
+            FindingModifier::TrustRemoteCode => Some(CweId::UNTRUSTED_FUNCTIONALITY),
+            FindingModifier::PickleLoad => Some(CweId::UNTRUSTED_DESERIALIZATION),
+            FindingModifier::SuspiciousSha => Some(CweId::DOWNLOAD_WITHOUT_INTEGRITY_CHECK),
+            FindingModifier::IncompleteDownload => Some(CweId::DOWNLOAD_WITHOUT_INTEGRITY_CHECK),
+            FindingModifier::PrivateRepo => None,
+        }));
+    }
+    cwes.sort();
+    cwes.dedup();
+    cwes
+}
+
+/// Fill in [`Finding::cwe`] for every finding.
+pub fn annotate_cwe(findings: &mut [Finding]) {
+    for finding in findings {
+        finding.cwe = cwe_for_status(finding.status, &FindingModifier::of(finding));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::scanner::scan_code;
+
+    #[test]
+    fn test_cwe_for_status() {
+        assert_eq!(cwe_for_status(Status::Unsafe, &[]), vec![CweId(494)]);
+        assert!(cwe_for_status(Status::Safe, &[FindingModifier::TrustRemoteCode]).is_empty());
+        assert_eq!(
+            cwe_for_status(
+                Status::Unsafe,
+                &[
+                    FindingModifier::TrustRemoteCode,
+                    FindingModifier::PickleLoad
+                ]
+            ),
+            vec![CweId(494), CweId(502), CweId(829)]
+        );
+        assert_eq!(cwe_for_status(Status::RemoteCall, &[]), vec![CweId(829)]);
+        assert_eq!(cwe_for_status(Status::PathTraversal, &[]), vec![CweId(22)]);
+        assert_eq!(CweId(494).to_string(), "CWE-494");
+        assert_eq!(
+            CweId(494).url(),
+            "https://cwe.mitre.org/data/definitions/494.html"
+        );
+    }
+
+    #[test]
+    fn test_annotate_cwe() {
+        let code = "a = AutoModel.from_pretrained(\"gpt2\", trust_remote_code=True)\n\
+                    b = joblib.load(path)\n";
+        let mut findings = scan_code(code, &ScanConfig::default());
+        assert!(findings.iter().all(|f| f.cwe.is_empty()));
+        annotate_cwe(&mut findings);
+        assert_eq!(findings[0].cwe, vec![CweId(494), CweId(829)]);
+        assert_eq!(findings[1].cwe, vec![CweId(494), CweId(502)]);
+
+        let json = serde_json::to_value(&findings[0]).unwrap();
+        assert_eq!(json["cwe"][0]["id"], "CWE-494");
+        assert_eq!(
+            json["cwe"][1]["url"],
+            "https://cwe.mitre.org/data/definitions/829.html"
+        );
+        let parsed: Finding = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.cwe, findings[0].cwe);
+    }
+
+    #[test]
+    fn test_finding_modifiers() {
+        let code = "a = AutoModel.from_pretrained(\"gpt2\", revision=\"0000000000000000000000000000000000000000\")\n\
+                    b = torch.load(path)\n";
+        let findings = scan_code(code, &ScanConfig::default());
+        assert_eq!(
+            FindingModifier::of(&findings[0]),
+            vec![FindingModifier::SuspiciousSha]
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cache::content_hash;
+use crate::cwe::{CweId, FindingModifier, cwe_for_status};
+use crate::directory::ScanResults;
+use crate::models::{ModelRegistry, RegistryEntry};
+use crate::remediation::remediation_action;
+use crate::scanner::{ModelName, Status, extract_revision};
+use crate::vex::rfc3339;
+
+/// CycloneDX specification version written by [`cyclonedx_bom`], the first with
+/// machine learning model components.
+pub const CYCLONEDX_SPEC_VERSION: &str = "1.5";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CycloneDxBom {
+    pub bom_format: &'static str,
+    pub spec_version: &'static str,
+    pub serial_number: String,
+    pub version: u32,
+    pub metadata: CycloneDxMetadata,
+    pub components: Vec<CycloneDxComponent>,
+    pub vulnerabilities: Vec<CycloneDxVulnerability>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycloneDxMetadata {
+    pub timestamp: String,
+    pub tools: CycloneDxTools,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycloneDxTools {
+    pub components: Vec<CycloneDxTool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycloneDxTool {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub name: &'static str,
+    pub version: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supplier: Option<CycloneDxSupplier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Left out for names replaced by `--anonymise-models`, which are not real packages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purl: Option<String>,
+    pub properties: Vec<CycloneDxProperty>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycloneDxSupplier {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycloneDxProperty {
+    pub name: &'static str,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycloneDxVulnerability {
+    pub id: String,
+    pub source: CycloneDxSource,
+    pub cwes: Vec<u16>,
+    pub description: String,
+    pub recommendation: String,
+    pub affects: Vec<CycloneDxAffects>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycloneDxSource {
+    pub name: &'static str,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CycloneDxAffects {
+    #[serde(rename = "ref")]
+    pub bom_ref: String,
+}
+
+/// `urn:uuid:` serial number derived from `seed`, so the same findings give the same BOM.
+/// The hash bits are laid out as a version 4 UUID.
+fn serial_number(seed: &str) -> String {
+    let hash = content_hash(seed);
+    format!(
+        "urn:uuid:{}-{}-4{}-8{}-{}",
+        &hash[0..8],
+        &hash[8..12],
+        &hash[13..16],
+        &hash[17..20],
+        &hash[20..32]
+    )
+}
+
+/// The component for one registry entry. Hugging Face repository IDs are `org/name`; the
+/// org becomes the supplier and group, and the revision the version. Anonymised names get
+/// no purl and a `model:` bom-ref instead.
+fn component(entry: &RegistryEntry, anonymised: bool) -> CycloneDxComponent {
+    let (org, name) = match entry.model_name.split_once('/') {
+        Some((org, name)) => (Some(org.to_string()), name.to_string()),
+        None => (None, entry.model_name.clone()),
+    };
+    let scheme = if anonymised {
+        "model:"
+    } else {
+        "pkg:huggingface/"
+    };
+    let mut bom_ref = format!("{scheme}{}", entry.model_name);
+    if let Some(revision) = &entry.revision {
+        bom_ref.push('@');
+        bom_ref.push_str(revision);
+    }
+    CycloneDxComponent {
+        kind: "machine-learning-model",
+        purl: (!anonymised).then(|| bom_ref.clone()),
+        bom_ref,
+        supplier: org.clone().map(|name| CycloneDxSupplier { name }),
+        group: org,
+        name,
+        version: entry.revision.clone(),
+        properties: entry
+            .locations
+            .iter()
+            .map(|(file, line)| CycloneDxProperty {
+                name: "hfscanner:location",
+                value: format!("{file}:{line}"),
+            })
+            .collect(),
+    }
+}
+
+/// The models loaded by the unsafe calls with one weakness, and what to do about them.
+#[derive(Default)]
+struct Weakness {
+    loads: BTreeSet<(String, Option<String>)>,
+    actions: Vec<String>,
+}
+
+/// A CycloneDX SBOM of the models loaded in a scan: one `machine-learning-model`
+/// component per model name and revision in the [`ModelRegistry`], and one vulnerability
+/// per weakness of the unsafe calls, affecting every component they load and recommending
+/// the [`remediation_hint`] of each call, or a reviewed local copy where there is none.
+///
+/// [`remediation_hint`]: crate::remediation::remediation_hint
+pub fn cyclonedx_bom(results: &ScanResults, timestamp: SystemTime) -> CycloneDxBom {
+    let mut weaknesses: BTreeMap<CweId, Weakness> = BTreeMap::new();
+    for finding in results
+        .findings
+        .iter()
+        .filter(|finding| finding.status >= Status::Unsafe)
+    {
+        let Some(ModelName::Literal(name) | ModelName::Anonymised(name)) = &finding.model_name
+        else {
+            continue;
+        };
+        let load = (name.clone(), extract_revision(&finding.call));
+        let action = remediation_action(finding);
+        for cwe in cwe_for_status(finding.status, &FindingModifier::of(finding)) {
+            let weakness = weaknesses.entry(cwe).or_default();
+            weakness.loads.insert(load.clone());
+            if !weakness.actions.contains(&action) {
+                weakness.actions.push(action.clone());
+            }
+        }
+    }
+    let anonymised: BTreeSet<&str> = results
+        .findings
+        .iter()
+        .filter_map(|finding| match &finding.model_name {
+            Some(ModelName::Anonymised(name)) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    let entries = ModelRegistry::from_findings(&results.findings).entries();
+    let components: Vec<CycloneDxComponent> = entries
+        .iter()
+        .map(|entry| component(entry, anonymised.contains(entry.model_name.as_str())))
+        .collect();
+    let vulnerabilities = weaknesses
+        .into_iter()
+        .map(|(cwe, weakness)| {
+            let affected: Vec<(&RegistryEntry, &CycloneDxComponent)> = entries
+                .iter()
+                .zip(&components)
+                .filter(|(entry, _)| {
+                    weakness
+                        .loads
+                        .contains(&(entry.model_name.clone(), entry.revision.clone()))
+                })
+                .collect();
+            let mut models: Vec<&str> = affected
+                .iter()
+                .map(|(entry, _)| entry.model_name.as_str())
+                .collect();
+            models.dedup();
+            CycloneDxVulnerability {
+                id: cwe.to_string(),
+                source: CycloneDxSource {
+                    name: "CWE",
+                    url: cwe.url(),
+                },
+                cwes: vec![cwe.0],
+                description: format!(
+                    "{} in the unsafe loads of {}.",
+                    cwe.name().unwrap_or("A weakness"),
+                    models.join(", ")
+                ),
+                recommendation: weakness.actions.join("\n"),
+                affects: affected
+                    .iter()
+                    .map(|(_, component)| CycloneDxAffects {
+                        bom_ref: component.bom_ref.clone(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    let refs: Vec<&str> = components.iter().map(|c| c.bom_ref.as_str()).collect();
+    let seconds = timestamp
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    CycloneDxBom {
+        bom_format: "CycloneDX",
+        spec_version: CYCLONEDX_SPEC_VERSION,
+        serial_number: serial_number(&refs.join("\n")),
+        version: 1,
+        metadata: CycloneDxMetadata {
+            timestamp: rfc3339(seconds),
+            tools: CycloneDxTools {
+                components: vec![CycloneDxTool {
+                    kind: "application",
+                    name: "hfscanner",
+                    version: env!("CARGO_PKG_VERSION"),
+                }],
+            },
+        },
+        components,
+        vulnerabilities,
+    }
+}
+
+impl CycloneDxBom {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("CycloneDX BOM serializes")
+    }
+}
+
+/// Write the [`cyclonedx_bom`] of `results` to `output_path` as JSON.
+pub fn write_cyclonedx(output_path: &str, results: &ScanResults) -> std::io::Result<()> {
+    std::fs::write(
+        output_path,
+        cyclonedx_bom(results, SystemTime::now()).to_json() + "\n",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::directory::scan_directory;
+    use serde_json::Value;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cyclonedx_bom() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("load.py"),
+            "a = AutoModel.from_pretrained(\"gpt2\", trust_remote_code=True)\n\
+             b = AutoModel.from_pretrained(\"meta-llama/Llama-2-7b\", revision=\"5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d\")\n\
+             c = AutoModel.from_pretrained(\"meta-llama/Llama-2-7b\", revision=\"main\")\n\
+             d = AutoTokenizer.from_pretrained(\"gpt2\")\n\
+             e = AutoModel.from_pretrained(\"bert-base-uncased\")  # noscan\n",
+        )?;
+        let results = scan_directory(root.path(), &ScanConfig::default());
+
+        let timestamp = UNIX_EPOCH + Duration::from_secs(1_735_689_599);
+        let bom = cyclonedx_bom(&results, timestamp);
+        let doc: Value = serde_json::from_str(&bom.to_json())?;
+
+        assert_eq!(doc["metadata"]["timestamp"], "2024-12-31T23:59:59Z");
+        let refs: Vec<&str> = doc["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["bom-ref"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            refs,
+            vec![
+                "pkg:huggingface/bert-base-uncased",
+                "pkg:huggingface/gpt2",
+                "pkg:huggingface/meta-llama/Llama-2-7b@5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d",
+                "pkg:huggingface/meta-llama/Llama-2-7b@main",
+            ]
+        );
+        let llama = &doc["components"][2];
+        assert_eq!(llama["type"], "machine-learning-model");
+        assert_eq!(llama["supplier"]["name"], "meta-llama");
+        assert_eq!(llama["name"], "Llama-2-7b");
+        assert_eq!(llama["version"], "5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d");
+        let gpt2 = &doc["components"][1];
+        assert!(gpt2.get("supplier").is_none());
+        assert_eq!(gpt2["properties"].as_array().unwrap().len(), 2);
+
+        // Only gpt2 is loaded by unsafe calls; the ignored load is not reported. Both
+        // calls are CWE-494, and the one trusting remote code is also CWE-829.
+        let vulnerabilities = doc["vulnerabilities"].as_array().unwrap();
+        assert_eq!(vulnerabilities.len(), 2);
+        assert_eq!(vulnerabilities[0]["id"], "CWE-494");
+        assert_eq!(vulnerabilities[0]["cwes"], serde_json::json!([494]));
+        assert_eq!(
+            vulnerabilities[0]["source"]["url"],
+            "https://cwe.mitre.org/data/definitions/494.html"
+        );
+        assert_eq!(
+            vulnerabilities[0]["affects"],
+            serde_json::json!([{"ref": "pkg:huggingface/gpt2"}])
+        );
+        assert_eq!(
+            vulnerabilities[0]["description"],
+            "Download of Code Without Integrity Check in the unsafe loads of gpt2."
+        );
+        assert_eq!(
+            vulnerabilities[0]["recommendation"],
+            "Pin the download to a commit SHA: AutoModel.from_pretrained(\"gpt2\", \
+             trust_remote_code=True, revision=\"<commit-sha>\")\n\
+             Pin the download to a commit SHA: AutoTokenizer.from_pretrained(\"gpt2\", \
+             revision=\"<commit-sha>\")"
+        );
+        assert_eq!(vulnerabilities[1]["id"], "CWE-829");
+        assert_eq!(
+            vulnerabilities[1]["recommendation"],
+            "Pin the download to a commit SHA: AutoModel.from_pretrained(\"gpt2\", \
+             trust_remote_code=True, revision=\"<commit-sha>\")"
+        );
+        assert_eq!(
+            cyclonedx_bom(&results, timestamp).serial_number,
+            bom.serial_number
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cyclonedx_bom_anonymised() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("load.py"),
+            "a = AutoModel.from_pretrained(\"org/secret\", revision=\"main\")\n",
+        )?;
+        let mut results = scan_directory(root.path(), &ScanConfig::default());
+        crate::models::anonymise_model_names(&mut results);
+
+        let bom = cyclonedx_bom(&results, UNIX_EPOCH);
+        let doc: Value = serde_json::from_str(&bom.to_json())?;
+        let component = &doc["components"][0];
+        assert_eq!(component["bom-ref"], "model:<model_1>@main");
+        assert!(component.get("purl").is_none());
+        assert!(!bom.to_json().contains("secret"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1466 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+use crate::config::{ConcurrencyMode, ScanConfig};
+use crate::git_index::staged_files_matching;
+use crate::gitignore::Gitignore;
+use crate::risk::risk_score;
+use crate::scanner::{
+    FileScan, Finding, JOBLIB_LOAD, Status, count_usages, decode_source, patterns_added_since,
+    read_source, scan_content, uses_trust_remote_code,
+};
+
+pub const EXCLUDED_DIRS: &[&str] = &[
+    ".git",
+    "node_modules",
+    "__pycache__",
+    ".mypy_cache",
+    ".venv",
+    "venv",
+    ".env",
+];
+
+/// Usage counts for one scanned file, as written to the CSV report.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FileRow {
+    pub org: String,
+    pub repo: String,
+    /// Path relative to the scan root.
+    pub file: String,
+    pub safe_usages: usize,
+    pub partial_usages: usize,
+    pub unsafe_usages: usize,
+    /// Findings passing `trust_remote_code=True`, regardless of their status.
+    pub trust_remote_code_usages: usize,
+    pub lines: usize,
+    pub bytes: u64,
+    /// Time spent reading and scanning the file, when `per_file_timing` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_time: Option<Duration>,
+}
+
+impl FileRow {
+    pub fn has_findings(&self) -> bool {
+        self.safe_usages > 0 || self.partial_usages > 0 || self.unsafe_usages > 0
+    }
+}
+
+/// Safe, partial and unsafe usages as counted by [`count_usages`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PatternCounts {
+    pub safe_usages: usize,
+    pub partial_usages: usize,
+    pub unsafe_usages: usize,
+}
+
+/// The findings of one `(org, repo)` pair.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Project {
+    pub org: String,
+    pub repo: String,
+    /// Same as [`ScanResults::project_statuses`]: unsafe, partially safe or safe.
+    pub status: Status,
+    /// Findings of all files in the project, in scan order.
+    pub findings: Vec<Finding>,
+}
+
+impl Project {
+    /// The most severe status of any finding, which unlike [`Project::status`] keeps
+    /// statuses such as [`Status::RemoteCall`] or [`Status::TrainingWithUnsafeBase`].
+    pub fn worst_status(&self) -> Option<Status> {
+        self.findings.iter().map(|f| f.status).max()
+    }
+
+    /// Findings that are unsafe or worse.
+    pub fn unsafe_findings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.status >= Status::Unsafe)
+    }
+
+    pub fn total_counts(&self) -> PatternCounts {
+        let (safe_usages, partial_usages, unsafe_usages) = count_usages(&self.findings);
+        PatternCounts {
+            safe_usages,
+            partial_usages,
+            unsafe_usages,
+        }
+    }
+}
+
+/// Aggregated totals for a whole scan.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ScanStats {
+    pub files_scanned: usize,
+    /// Files left out by `skip_generated`; not counted in `files_scanned`.
+    pub files_skipped_generated: usize,
+    /// Files larger than `max_file_size`; not counted in `files_scanned`.
+    pub files_skipped_too_large: usize,
+    pub safe_usages: usize,
+    pub partial_usages: usize,
+    pub unsafe_usages: usize,
+    pub safe_projects: usize,
+    pub partial_projects: usize,
+    pub unsafe_projects: usize,
+    /// `joblib.load` calls, also counted in `unsafe_usages`.
+    pub joblib_unsafe_usages: usize,
+    /// Model card and dataset info loads with [`Status::MetadataLoad`], also counted in
+    /// `partial_usages`.
+    pub metadata_load_usages: usize,
+    /// XGBoost and LightGBM model file loads with [`Status::LocalFileLoad`], also counted in
+    /// `partial_usages`.
+    pub local_file_load_usages: usize,
+    /// Unsafe calls inside test fixtures, also counted in `unsafe_usages`.
+    pub test_fixture_unsafe_usages: usize,
+    /// Findings suppressed with [`Status::Ignored`]; not counted in any of the usages above.
+    pub ignored_usages: usize,
+    /// Lines in all scanned files, including those without findings.
+    pub total_lines_scanned: usize,
+    pub total_bytes_scanned: u64,
+}
+
+/// Coarse time spent in each phase of a scan, recorded when `config.perf_profile` is set.
+///
+/// `read` and `regex_match` are summed over all files, so with parallel scanning they can
+/// exceed the wall time of the scan. With `lazy_walk` the walk overlaps the scan and is
+/// not recorded separately.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    pub walk: Duration,
+    pub read: Duration,
+    pub regex_match: Duration,
+    pub aggregate: Duration,
+    /// Writing reports; filled in by the caller after the scan.
+    pub output: Duration,
+}
+
+impl PhaseTimings {
+    /// A JSON object of phase name to duration in milliseconds.
+    pub fn to_json(&self) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let profile = serde_json::json!({
+            "walk": ms(self.walk),
+            "read": ms(self.read),
+            "regex_match": ms(self.regex_match),
+            "aggregate": ms(self.aggregate),
+            "output": ms(self.output),
+        });
+        serde_json::to_string_pretty(&profile).expect("phase timings serialize")
+    }
+}
+
+/// [`PhaseTimings::read`] and [`PhaseTimings::regex_match`] as they are summed up by the
+/// scanning threads.
+#[derive(Debug, Default)]
+struct PhaseClock {
+    read_nanos: AtomicU64,
+    match_nanos: AtomicU64,
+}
+
+impl PhaseClock {
+    fn add(counter: &AtomicU64, elapsed: Duration) {
+        counter.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Timings of a scan that walked for `walk` and aggregated for `aggregate`, or `None`
+    /// when not profiling.
+    fn timings(
+        &self,
+        config: &ScanConfig,
+        walk: Duration,
+        aggregate: Duration,
+    ) -> Option<PhaseTimings> {
+        config.perf_profile.then(|| PhaseTimings {
+            walk,
+            read: Duration::from_nanos(self.read_nanos.load(Ordering::Relaxed)),
+            regex_match: Duration::from_nanos(self.match_nanos.load(Ordering::Relaxed)),
+            aggregate,
+            output: Duration::ZERO,
+        })
+    }
+}
+
+/// Read `path`, first waiting `config.read_latency`, and add the time taken to `clock`
+/// when `config.perf_profile` is set.
+fn read_file(path: &Path, config: &ScanConfig, clock: &PhaseClock) -> std::io::Result<String> {
+    let start = config.perf_profile.then(Instant::now);
+    #[cfg(any(test, feature = "bench"))]
+    if let Some(latency) = config.read_latency {
+        std::thread::sleep(latency);
+    }
+    #[cfg(test)]
+    READS_ON_RAYON
+        .lock()
+        .unwrap()
+        .push((path.to_path_buf(), rayon::current_thread_index().is_some()));
+    let content = read_source(path, config.encoding_errors);
+    if let Some(start) = start {
+        PhaseClock::add(&clock.read_nanos, start.elapsed());
+    }
+    content
+}
+
+/// Every file read by [`read_file`], and whether it was read on a Rayon thread.
+#[cfg(test)]
+static READS_ON_RAYON: std::sync::Mutex<Vec<(PathBuf, bool)>> = std::sync::Mutex::new(Vec::new());
+
+/// [`scan_file`], adding the time spent reading and matching to `clock` when
+/// `config.perf_profile` is set.
+///
+/// [`scan_file`]: crate::scanner::scan_file
+fn profiled_scan_file(path: &Path, config: &ScanConfig, clock: &PhaseClock) -> FileScan {
+    match read_file(path, config, clock) {
+        Ok(content) => profiled_scan_content(path, &content, config, clock),
+        Err(e) => {
+            tracing::error!(path = %path.display(), "Failed to read file: {e}");
+            FileScan::default()
+        }
+    }
+}
+
+/// [`scan_content`], adding the time spent matching to `clock` when `config.perf_profile`
+/// is set.
+fn profiled_scan_content(
+    path: &Path,
+    content: &str,
+    config: &ScanConfig,
+    clock: &PhaseClock,
+) -> FileScan {
+    let start = config.perf_profile.then(Instant::now);
+    let scan = scan_content(path, content, config);
+    if let Some(start) = start {
+        PhaseClock::add(&clock.match_nanos, start.elapsed());
+    }
+    scan
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanResults {
+    /// Every finding in every scanned file.
+    pub findings: Vec<Finding>,
+    /// One row per file with findings, plus files without findings when
+    /// `include_safe_in_csv` is set.
+    pub file_rows: Vec<FileRow>,
+    /// Worst status seen for each `(org, repo)` pair.
+    pub project_statuses: HashMap<(String, String), Status>,
+    /// Every project with findings, sorted by org and repo.
+    pub projects: Vec<Project>,
+    /// Files with unsafe usages for each `(org, repo)` pair, sorted. Projects without any
+    /// unsafe file have no entry.
+    pub project_unsafe_files: HashMap<(String, String), Vec<String>>,
+    pub stats: ScanStats,
+    /// [`risk_score`] of all findings.
+    pub risk_score: f64,
+    /// Number of scanned files at each directory depth below their root: 0 for files in
+    /// the root itself, 1 inside an org directory, 2 inside a repo, and so on.
+    pub files_by_depth: BTreeMap<usize, usize>,
+    /// Time spent in each phase, when `perf_profile` is set.
+    pub phase_timings: Option<PhaseTimings>,
+}
+
+/// Whether `results` are within the failure thresholds of `config`: no finding at or above
+/// [`ScanConfig::fail_on`], a risk score no greater than [`ScanConfig::fail_on_score`], and
+/// no unpinned call reported by a pattern newer than
+/// [`ScanConfig::fail_on_new_patterns_since`]. Every threshold that is exceeded is logged.
+pub fn check_thresholds(results: &ScanResults, config: &ScanConfig) -> bool {
+    let mut passed = true;
+    if let Some(threshold) = config.fail_on
+        && results.worst_status().is_some_and(|s| s >= threshold)
+    {
+        tracing::error!("Findings at or above the --fail-on level were reported");
+        passed = false;
+    }
+    if let Some(threshold) = config.fail_on_score
+        && results.risk_score > threshold
+    {
+        tracing::error!(
+            "Risk score {} exceeds the severity threshold {threshold}",
+            results.risk_score
+        );
+        passed = false;
+    }
+    if let Some(version) = &config.fail_on_new_patterns_since {
+        let names = patterns_added_since(version).unwrap_or_default();
+        let mut fired: Vec<&str> = results
+            .findings
+            .iter()
+            .filter(|f| f.status > Status::Safe && names.contains(&f.pattern))
+            .map(|f| f.pattern.as_str())
+            .collect();
+        fired.sort_unstable();
+        fired.dedup();
+        if !fired.is_empty() {
+            tracing::error!(
+                "Patterns added since {version} reported findings: {}",
+                fired.join(", ")
+            );
+            passed = false;
+        }
+    }
+    passed
+}
+
+impl ScanResults {
+    /// The most severe status of any finding, or `None` if nothing was found.
+    pub fn worst_status(&self) -> Option<Status> {
+        self.findings.iter().map(|f| f.status).max()
+    }
+
+    /// Rows with a recorded scan time, slowest first.
+    pub fn slowest_files(&self) -> Vec<&FileRow> {
+        let mut rows: Vec<&FileRow> = self
+            .file_rows
+            .iter()
+            .filter(|row| row.scan_time.is_some())
+            .collect();
+        rows.sort_by_key(|row| std::cmp::Reverse(row.scan_time));
+        rows
+    }
+}
+
+pub fn is_excluded(entry: &walkdir::DirEntry) -> bool {
+    entry.file_type().is_dir()
+        && EXCLUDED_DIRS
+            .iter()
+            .any(|&e| entry.file_name().to_string_lossy().contains(e))
+}
+
+/// Whether `gitignore`, read from `root`, excludes `entry`; never without a `.gitignore`.
+fn is_gitignored(gitignore: Option<&Gitignore>, root: &Path, entry: &walkdir::DirEntry) -> bool {
+    gitignore.is_some_and(|gitignore| gitignore.is_ignored_entry(root, entry))
+}
+
+/// Python files, plus files whose extension or name is in `config.scan_extensions`.
+pub fn is_scanned_file(path: &Path, config: &ScanConfig) -> bool {
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let file_name = path.file_name().and_then(|name| name.to_str());
+    extension == Some("py")
+        || config
+            .scan_extensions
+            .iter()
+            .any(|ext| extension == Some(ext.as_str()) || file_name == Some(ext.as_str()))
+}
+
+/// Whether the file's size is within `config.min_file_size` and `config.max_file_size`.
+/// Files over the maximum are counted in `skipped_too_large`.
+fn within_size_limits(
+    entry: &walkdir::DirEntry,
+    config: &ScanConfig,
+    skipped_too_large: &AtomicUsize,
+) -> bool {
+    let Ok(len) = entry.metadata().map(|m| m.len()) else {
+        return true;
+    };
+    if len < config.min_file_size {
+        return false;
+    }
+    if let Some(max) = config.max_file_size
+        && len > max
+    {
+        tracing::warn!(
+            path = %entry.path().display(),
+            size = len,
+            "Skipping file larger than the maximum file size of {max} bytes"
+        );
+        skipped_too_large.fetch_add(1, Ordering::Relaxed);
+        return false;
+    }
+    true
+}
+
+/// Resolve `.` and `..` components of `path` without touching the file system, after
+/// making it absolute against the current directory.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// `path` with symlinks and `..` resolved, or lexically normalised if it does not exist.
+fn resolve_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| normalize_lexically(path))
+}
+
+/// Extract (org, repo) from a path like `root/org/repo/file.py`
+///
+/// When `path` does not start with `root` as given, or goes through a `..`, both are
+/// resolved first, so a relative root, a symlinked root or `org/other/../repo` still match.
+pub fn get_org_repo(path: &Path, root: &Path) -> (String, String) {
+    let direct = path
+        .strip_prefix(root)
+        .ok()
+        .filter(|rel| !rel.components().any(|c| c == Component::ParentDir))
+        .map(Path::to_path_buf);
+    let rel = match direct {
+        Some(rel) => rel,
+        None => match resolve_path(path).strip_prefix(resolve_path(root)) {
+            Ok(rel) => rel.to_path_buf(),
+            Err(_) => return ("unknown".to_string(), "unknown".to_string()),
+        },
+    };
+    let rel_components = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+
+    if rel_components.len() < 3 {
+        return ("unknown".to_string(), "unknown".to_string());
+    }
+
+    (rel_components[0].clone(), rel_components[1].clone())
+}
+
+fn merge_status(current: Option<Status>, new_status: Status) -> Status {
+    current.map_or(new_status, |current| current.max(new_status))
+}
+
+/// The row and findings of one scanned file, `None` for a skipped generated file.
+type ScannedFile = Option<(FileRow, Vec<Finding>)>;
+
+/// Files read concurrently in [`ConcurrencyMode::Tokio`].
+const MAX_OPEN_FILES: usize = 64;
+
+/// Build the row for one scanned file and make the findings' paths relative to `root`.
+/// `None` for a skipped generated file.
+fn file_result(
+    root: &Path,
+    path: &Path,
+    scan: FileScan,
+    scan_time: Option<Duration>,
+    config: &ScanConfig,
+) -> ScannedFile {
+    if scan.generated {
+        return None;
+    }
+    let mut findings = scan.findings;
+    let (org, repo) = get_org_repo(path, root);
+    let file_rel = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string();
+    let private_repo = config
+        .private_repos
+        .iter()
+        .any(|name| name.split_once('/') == Some((org.as_str(), repo.as_str())));
+    for finding in &mut findings {
+        finding.file = file_rel.clone();
+        if private_repo {
+            finding.private_repo = true;
+            if finding.status == Status::Unsafe {
+                finding.status = Status::PartiallySafe;
+            }
+        }
+    }
+
+    let (safe, partial, unsafe_) = count_usages(&findings);
+    let row = FileRow {
+        org,
+        repo,
+        file: file_rel,
+        safe_usages: safe,
+        partial_usages: partial,
+        unsafe_usages: unsafe_,
+        trust_remote_code_usages: findings
+            .iter()
+            .filter(|f| uses_trust_remote_code(&f.call))
+            .count(),
+        lines: scan.lines,
+        bytes: scan.bytes,
+        scan_time,
+    };
+    Some((row, findings))
+}
+
+/// Read and scan `(root, path)` pairs on a Tokio runtime, at most [`MAX_OPEN_FILES`] at a
+/// time. Results keep the input order, like the Rayon path.
+///
+/// Starts its own runtime, so it must not be called from within one.
+fn scan_files_async(
+    file_paths: Vec<(PathBuf, PathBuf)>,
+    config: &ScanConfig,
+    clock: &Arc<PhaseClock>,
+) -> Vec<ScannedFile> {
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            tracing::error!("Failed to start Tokio runtime: {e}");
+            return Vec::new();
+        }
+    };
+    let config = Arc::new(config.clone());
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(MAX_OPEN_FILES));
+
+    runtime.block_on(async {
+        let tasks: Vec<_> = file_paths
+            .into_iter()
+            .map(|(root, path)| {
+                let config = Arc::clone(&config);
+                let semaphore = Arc::clone(&semaphore);
+                let clock = Arc::clone(clock);
+                tokio::task::spawn(async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    let start = config.per_file_timing.then(Instant::now);
+                    let read_start = config.perf_profile.then(Instant::now);
+                    #[cfg(any(test, feature = "bench"))]
+                    if let Some(latency) = config.read_latency {
+                        tokio::time::sleep(latency).await;
+                    }
+                    let content = tokio::fs::read(&path)
+                        .await
+                        .and_then(|bytes| decode_source(bytes, config.encoding_errors));
+                    if let Some(read_start) = read_start {
+                        PhaseClock::add(&clock.read_nanos, read_start.elapsed());
+                    }
+                    let scan = match content {
+                        Ok(content) => profiled_scan_content(&path, &content, &config, &clock),
+                        Err(e) => {
+                            tracing::error!(path = %path.display(), "Failed to read file: {e}");
+                            FileScan::default()
+                        }
+                    };
+                    let scan_time = start.map(|start| start.elapsed());
+                    file_result(&root, &path, scan, scan_time, &config)
+                })
+            })
+            .collect();
+
+        let mut scanned = Vec::new();
+        for task in tasks {
+            match task.await {
+                Ok(result) => scanned.push(result),
+                Err(e) => tracing::error!("Scan task failed: {e}"),
+            }
+        }
+        scanned
+    })
+}
+
+/// Walk `root`, scan every Python file in parallel and aggregate the results.
+///
+/// Files without any findings are only kept in `file_rows` when
+/// `config.include_safe_in_csv` is set; they never contribute to the project statuses.
+pub fn scan_directory(root: &Path, config: &ScanConfig) -> ScanResults {
+    scan_directories(&[root], config)
+}
+
+/// Scan several roots into one set of results.
+///
+/// `org` and `repo` are taken relative to the root each file was found under. A project
+/// present under more than one root gets the most severe status seen in any of them.
+pub fn scan_directories<P: AsRef<Path> + Sync>(roots: &[P], config: &ScanConfig) -> ScanResults {
+    let clock = Arc::new(PhaseClock::default());
+    let skipped_too_large = AtomicUsize::new(0);
+    if config.lazy_walk && config.concurrency_mode == ConcurrencyMode::Rayon {
+        let (depths, scanned) = scan_directories_lazy(roots, config, &clock, &skipped_too_large);
+        let aggregate_start = Instant::now();
+        let mut results = aggregate(&depths, scanned, config);
+        results.stats.files_skipped_too_large = skipped_too_large.into_inner();
+        results.phase_timings = clock.timings(config, Duration::ZERO, aggregate_start.elapsed());
+        return results;
+    }
+
+    let walk_start = Instant::now();
+    let mut file_paths = Vec::new();
+    for root in roots {
+        let root = root.as_ref();
+        let before = file_paths.len();
+        let gitignore = config.respect_gitignore.then(|| Gitignore::from_root(root));
+        file_paths.extend(
+            WalkDir::new(root)
+                .into_iter()
+                .filter_entry(|e| !is_excluded(e) && !is_gitignored(gitignore.as_ref(), root, e))
+                .filter_map(|e| e.ok())
+                .filter(|e| {
+                    e.file_type().is_file()
+                        && is_scanned_file(e.path(), config)
+                        && within_size_limits(e, config, &skipped_too_large)
+                })
+                .map(|e| (root, e)),
+        );
+        let files = file_paths.len() - before;
+        tracing::debug!(root = %root.display(), files, "scanning Python files");
+    }
+    let walk = walk_start.elapsed();
+
+    let scanned: Vec<ScannedFile> = match config.concurrency_mode {
+        ConcurrencyMode::Rayon if config.parallel_io => {
+            scan_files_parallel_io(&file_paths, config, &clock)
+        }
+        ConcurrencyMode::Rayon => file_paths
+            .par_iter()
+            .map(|(root, entry)| {
+                let path = entry.path();
+                let start = config.per_file_timing.then(Instant::now);
+                let scan = profiled_scan_file(path, config, &clock);
+                let scan_time = start.map(|start| start.elapsed());
+                file_result(root, path, scan, scan_time, config)
+            })
+            .collect(),
+        ConcurrencyMode::Tokio => scan_files_async(
+            file_paths
+                .iter()
+                .map(|(root, entry)| (root.to_path_buf(), entry.path().to_path_buf()))
+                .collect(),
+            config,
+            &clock,
+        ),
+    };
+
+    let aggregate_start = Instant::now();
+    let depths: Vec<usize> = file_paths
+        .iter()
+        .map(|(root, entry)| file_depth(root, entry.path()))
+        .collect();
+    let mut results = aggregate(&depths, scanned, config);
+    results.stats.files_skipped_too_large = skipped_too_large.into_inner();
+    results.phase_timings = clock.timings(config, walk, aggregate_start.elapsed());
+    results
+}
+
+/// Threads reading files with `parallel_io`.
+pub const IO_THREADS: usize = 16;
+
+/// [`ConcurrencyMode::Rayon`] with the reads moved off the Rayon pool: [`IO_THREADS`] OS
+/// threads read the files and send their contents through a bounded channel to Rayon,
+/// which only does the matching. Results keep the order of `file_paths`.
+fn scan_files_parallel_io(
+    file_paths: &[(&Path, walkdir::DirEntry)],
+    config: &ScanConfig,
+    clock: &PhaseClock,
+) -> Vec<ScannedFile> {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::sync_channel(IO_THREADS * 4);
+    std::thread::scope(|scope| {
+        for _ in 0..IO_THREADS.min(file_paths.len()) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((_, entry)) = file_paths.get(index) else {
+                        break;
+                    };
+                    let start = Instant::now();
+                    let content = read_file(entry.path(), config, clock);
+                    if sender.send((index, content, start.elapsed())).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // The channel closes once every reader has finished.
+        drop(sender);
+
+        let mut scanned: Vec<(usize, ScannedFile)> = receiver
+            .into_iter()
+            .par_bridge()
+            .map(|(index, content, read_time)| {
+                let (root, entry) = &file_paths[index];
+                let path = entry.path();
+                let start = config.per_file_timing.then(Instant::now);
+                let scan = match content {
+                    Ok(content) => profiled_scan_content(path, &content, config, clock),
+                    Err(e) => {
+                        tracing::error!(path = %path.display(), "Failed to read file: {e}");
+                        FileScan::default()
+                    }
+                };
+                let scan_time = start.map(|start| read_time + start.elapsed());
+                (index, file_result(root, path, scan, scan_time, config))
+            })
+            .collect();
+        scanned.sort_by_key(|(index, _)| *index);
+        scanned.into_iter().map(|(_, file)| file).collect()
+    })
+}
+
+/// Scan the files staged in the git index under each of `roots` instead of the working
+/// tree, so a pre-commit hook checks exactly what is about to be committed.
+///
+/// Fails if a root is not inside a git repository or `git` cannot be run.
+pub fn scan_git_index<P: AsRef<Path> + Sync>(
+    roots: &[P],
+    config: &ScanConfig,
+) -> std::io::Result<ScanResults> {
+    let clock = PhaseClock::default();
+    let read_start = Instant::now();
+    let mut staged = Vec::new();
+    let mut skipped_too_large = 0;
+    for root in roots {
+        let root = root.as_ref();
+        let before = staged.len();
+        staged.extend(
+            staged_files_matching(root, |path| is_scanned_file(path, config))?
+                .into_iter()
+                .filter(|file| {
+                    let len = file.content.len() as u64;
+                    if len < config.min_file_size {
+                        return false;
+                    }
+                    let too_large = config.max_file_size.is_some_and(|max| len > max);
+                    skipped_too_large += usize::from(too_large);
+                    !too_large
+                })
+                .map(|file| (root, root.join(&file.path), file.content)),
+        );
+        let files = staged.len() - before;
+        tracing::debug!(root = %root.display(), files, "scanning staged files");
+    }
+    if config.perf_profile {
+        PhaseClock::add(&clock.read_nanos, read_start.elapsed());
+    }
+
+    let scanned: Vec<ScannedFile> = staged
+        .par_iter()
+        .map(|(root, path, content)| {
+            let start = config.per_file_timing.then(Instant::now);
+            let scan = profiled_scan_content(path, content, config, &clock);
+            let scan_time = start.map(|start| start.elapsed());
+            file_result(root, path, scan, scan_time, config)
+        })
+        .collect();
+    let aggregate_start = Instant::now();
+    let depths: Vec<usize> = staged
+        .iter()
+        .map(|(root, path, _)| file_depth(root, path))
+        .collect();
+    let mut results = aggregate(&depths, scanned, config);
+    results.stats.files_skipped_too_large = skipped_too_large;
+    results.phase_timings = clock.timings(config, Duration::ZERO, aggregate_start.elapsed());
+    Ok(results)
+}
+
+/// Directory depth of `path` below `root`: 0 for files in the root itself.
+fn file_depth(root: &Path, path: &Path) -> usize {
+    path.strip_prefix(root)
+        .map_or(0, |rel| rel.components().count().saturating_sub(1))
+}
+
+/// [`scan_directories`] without collecting the file list first: each root's walk is fed
+/// straight into Rayon with `par_bridge`, so scanning starts right away and memory does
+/// not grow with the number of files before the scan. Results are sorted by root and
+/// path afterwards, since `par_bridge` does not keep the walk order.
+fn scan_directories_lazy<P: AsRef<Path> + Sync>(
+    roots: &[P],
+    config: &ScanConfig,
+    clock: &PhaseClock,
+    skipped_too_large: &AtomicUsize,
+) -> (Vec<usize>, Vec<ScannedFile>) {
+    let mut scanned: Vec<(usize, PathBuf, usize, ScannedFile)> = roots
+        .iter()
+        .enumerate()
+        .flat_map(|(index, root)| {
+            let root = root.as_ref();
+            let gitignore = config.respect_gitignore.then(|| Gitignore::from_root(root));
+            WalkDir::new(root)
+                .into_iter()
+                .filter_entry(move |e| {
+                    !is_excluded(e) && !is_gitignored(gitignore.as_ref(), root, e)
+                })
+                .filter_map(|e| e.ok())
+                .filter(|e| {
+                    e.file_type().is_file()
+                        && is_scanned_file(e.path(), config)
+                        && within_size_limits(e, config, skipped_too_large)
+                })
+                .par_bridge()
+                .map(|entry| {
+                    let path = entry.path();
+                    let start = config.per_file_timing.then(Instant::now);
+                    let scan = profiled_scan_file(path, config, clock);
+                    let scan_time = start.map(|start| start.elapsed());
+                    let result = file_result(root, path, scan, scan_time, config);
+                    (index, path.to_path_buf(), file_depth(root, path), result)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    scanned.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+    scanned
+        .into_iter()
+        .map(|(_, _, depth, result)| (depth, result))
+        .unzip()
+}
+
+/// Combine per-file results, one per scanned file at the given `depths`, into totals and
+/// project statuses.
+fn aggregate(depths: &[usize], scanned: Vec<ScannedFile>, config: &ScanConfig) -> ScanResults {
+    let mut results = ScanResults::default();
+    results.stats.files_skipped_generated = scanned.iter().filter(|s| s.is_none()).count();
+    results.stats.files_scanned = depths.len() - results.stats.files_skipped_generated;
+    for &depth in depths {
+        *results.files_by_depth.entry(depth).or_default() += 1;
+    }
+
+    let mut project_findings: BTreeMap<(String, String), Vec<Finding>> = BTreeMap::new();
+    for (row, findings) in scanned.into_iter().flatten() {
+        results.stats.total_lines_scanned += row.lines;
+        results.stats.total_bytes_scanned += row.bytes;
+        if findings.is_empty() && !config.include_safe_in_csv {
+            continue;
+        }
+        if row.has_findings() {
+            results.stats.safe_usages += row.safe_usages;
+            results.stats.partial_usages += row.partial_usages;
+            results.stats.unsafe_usages += row.unsafe_usages;
+
+            let new_status = if row.unsafe_usages > 0 {
+                Status::Unsafe
+            } else if row.partial_usages > 0 {
+                Status::PartiallySafe
+            } else {
+                Status::Safe
+            };
+            let key = (row.org.clone(), row.repo.clone());
+            if row.unsafe_usages > 0 {
+                results
+                    .project_unsafe_files
+                    .entry(key.clone())
+                    .or_default()
+                    .push(row.file.clone());
+            }
+            project_findings
+                .entry(key.clone())
+                .or_default()
+                .extend(findings.iter().cloned());
+            let current = results.project_statuses.get(&key).cloned();
+            results
+                .project_statuses
+                .insert(key, merge_status(current, new_status));
+        }
+
+        results.stats.joblib_unsafe_usages += findings
+            .iter()
+            .filter(|f| f.pattern == JOBLIB_LOAD && f.status >= Status::Unsafe)
+            .count();
+        results.stats.metadata_load_usages += findings
+            .iter()
+            .filter(|f| f.status == Status::MetadataLoad)
+            .count();
+        results.stats.local_file_load_usages += findings
+            .iter()
+            .filter(|f| f.status == Status::LocalFileLoad)
+            .count();
+        results.stats.ignored_usages += findings
+            .iter()
+            .filter(|f| f.status == Status::Ignored)
+            .count();
+        results.stats.test_fixture_unsafe_usages += findings
+            .iter()
+            .filter(|f| f.in_test_fixture && f.status >= Status::Unsafe)
+            .count();
+        results.findings.extend(findings);
+        results.file_rows.push(row);
+    }
+
+    for files in results.project_unsafe_files.values_mut() {
+        files.sort();
+    }
+    results.projects = project_findings
+        .into_iter()
+        .map(|(key, findings)| {
+            let status = results.project_statuses[&key];
+            let (org, repo) = key;
+            Project {
+                org,
+                repo,
+                status,
+                findings,
+            }
+        })
+        .collect();
+    for status in results.project_statuses.values() {
+        match status {
+            Status::Ignored | Status::Safe => results.stats.safe_projects += 1,
+            Status::PartiallySafe
+            | Status::MetadataLoad
+            | Status::LocalFileLoad
+            | Status::ApiVersionUnpinned
+            | Status::RemoteCall => results.stats.partial_projects += 1,
+            Status::Unsafe | Status::TrainingWithUnsafeBase | Status::PathTraversal => {
+                results.stats.unsafe_projects += 1
+            }
+        }
+    }
+    results.risk_score = risk_score(&results.findings, &config.risk_weights);
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_scan_directory_include_safe_in_csv() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("unsafe.py"),
+            "model = AutoModel.from_pretrained(\"model\")\n",
+        )?;
+        fs::write(repo_dir.join("plain.py"), "print(\"no models here\")\n")?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert_eq!(results.file_rows.len(), 1);
+        assert_eq!(results.stats.total_lines_scanned, 2);
+
+        let config = ScanConfig {
+            include_safe_in_csv: true,
+            ..Default::default()
+        };
+        let results = scan_directory(root.path(), &config);
+        assert_eq!(results.file_rows.len(), 2);
+        let plain = results
+            .file_rows
+            .iter()
+            .find(|row| row.file.ends_with("plain.py"))
+            .expect("file without findings should be listed");
+        assert!(!plain.has_findings());
+        assert_eq!((plain.lines, plain.bytes), (1, 24));
+        assert_eq!(results.stats.total_lines_scanned, 2);
+        assert_eq!(results.stats.total_bytes_scanned, 67);
+        assert_eq!(results.stats.unsafe_usages, 1);
+        assert_eq!(results.project_statuses.len(), 1);
+        assert_eq!(results.worst_status(), Some(Status::Unsafe));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_thresholds() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("load.py"),
+            "model = AutoModel.from_pretrained(\"gpt2\")\n",
+        )?;
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert_eq!(results.risk_score, 10.0);
+
+        let thresholds = |fail_on, fail_on_score| ScanConfig {
+            fail_on,
+            fail_on_score,
+            ..Default::default()
+        };
+        assert!(check_thresholds(&results, &thresholds(None, None)));
+        assert!(!check_thresholds(&results, &thresholds(None, Some(5.0))));
+        assert!(check_thresholds(&results, &thresholds(None, Some(15.0))));
+        assert!(check_thresholds(&results, &thresholds(None, Some(10.0))));
+        // Either condition fails the scan.
+        assert!(!check_thresholds(
+            &results,
+            &thresholds(Some(Status::Unsafe), Some(15.0))
+        ));
+        assert!(!check_thresholds(
+            &results,
+            &thresholds(Some(Status::TrainingWithUnsafeBase), Some(5.0))
+        ));
+
+        let new_patterns = ScanConfig {
+            fail_on_new_patterns_since: Some("0.1.0".to_string()),
+            ..Default::default()
+        };
+        assert!(check_thresholds(&results, &new_patterns));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_private_repos() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        for repo in ["internal", "public"] {
+            let repo_dir = root.path().join("org").join(repo);
+            fs::create_dir_all(&repo_dir)?;
+            fs::write(
+                repo_dir.join("load.py"),
+                "model = AutoModel.from_pretrained(\"gpt2\")\n",
+            )?;
+        }
+        // Fine-tuning an unpinned base model is not downgraded.
+        fs::write(
+            root.path().join("org").join("internal").join("train.py"),
+            "model = AutoModel.from_pretrained(\"gpt2\")\n\
+             trainer = Trainer(model=model)\n\
+             trainer.train()\n",
+        )?;
+        // Nor is a `subfolder` escaping the model directory.
+        fs::write(
+            root.path()
+                .join("org")
+                .join("internal")
+                .join("traversal.py"),
+            "model = AutoModel.from_pretrained(\"gpt2\", subfolder=\"../../etc\")\n",
+        )?;
+        let config = ScanConfig {
+            private_repos: vec!["org/internal".to_string()],
+            ..Default::default()
+        };
+        let results = scan_directory(root.path(), &config);
+
+        let status = |file: &str| {
+            let finding = results.findings.iter().find(|f| f.file == file).unwrap();
+            (finding.status, finding.private_repo)
+        };
+        assert_eq!(
+            status("org/internal/load.py"),
+            (Status::PartiallySafe, true)
+        );
+        assert_eq!(
+            status("org/internal/train.py"),
+            (Status::TrainingWithUnsafeBase, true)
+        );
+        assert_eq!(
+            status("org/internal/traversal.py"),
+            (Status::PathTraversal, true)
+        );
+        assert_eq!(status("org/public/load.py"), (Status::Unsafe, false));
+        assert_eq!(results.stats.unsafe_usages, 3);
+        assert_eq!(results.stats.partial_usages, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_io() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let unsafe_files = crate::bench::generate_synthetic_tree(root.path(), 8, 0.5)?;
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let latency = Duration::from_millis(10);
+        let scan = |parallel_io| {
+            let config = ScanConfig {
+                parallel_io,
+                read_latency: Some(latency),
+                per_file_timing: true,
+                ..Default::default()
+            };
+            let results = pool.install(|| scan_directory(root.path(), &config));
+            // Whether each file of this scan was read on a Rayon thread.
+            let mut reads = READS_ON_RAYON.lock().unwrap();
+            let on_rayon: Vec<bool> = reads
+                .iter()
+                .filter(|(path, _)| path.starts_with(root.path()))
+                .map(|&(_, on_rayon)| on_rayon)
+                .collect();
+            reads.retain(|(path, _)| !path.starts_with(root.path()));
+            (results, on_rayon)
+        };
+        let (expected, on_rayon) = scan(false);
+        assert_eq!(on_rayon, vec![true; 8]);
+        let (results, on_rayon) = scan(true);
+        assert_eq!(
+            on_rayon,
+            vec![false; 8],
+            "parallel I/O reads off the Rayon pool"
+        );
+
+        assert_eq!(results.findings, expected.findings);
+        assert_eq!(results.stats, expected.stats);
+        assert_eq!(results.stats.unsafe_usages, 2 * unsafe_files);
+        // The time spent waiting for the read still counts towards the file's scan time.
+        assert!(
+            results
+                .file_rows
+                .iter()
+                .all(|row| row.scan_time.is_some_and(|time| time >= latency))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_respect_gitignore() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        fs::write(root.path().join(".gitignore"), "custom_env/\n")?;
+        for dir in ["org/repo", "org/repo/custom_env/lib"] {
+            let dir = root.path().join(dir);
+            fs::create_dir_all(&dir)?;
+            fs::write(
+                dir.join("load.py"),
+                "model = AutoModel.from_pretrained(\"gpt2\")\n",
+            )?;
+        }
+
+        for lazy_walk in [false, true] {
+            let results = scan_directory(
+                root.path(),
+                &ScanConfig {
+                    lazy_walk,
+                    ..Default::default()
+                },
+            );
+            assert_eq!(results.stats.unsafe_usages, 2);
+
+            let config = ScanConfig {
+                respect_gitignore: true,
+                lazy_walk,
+                ..Default::default()
+            };
+            let results = scan_directory(root.path(), &config);
+            assert_eq!(results.stats.unsafe_usages, 1);
+            assert_eq!(results.findings[0].file, "org/repo/load.py");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_file_timing_slowest_files() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        for i in 0..5 {
+            fs::write(
+                repo_dir.join(format!("small-{i}.py")),
+                "model = AutoModel.from_pretrained(\"model\")\n",
+            )?;
+        }
+        let large = "x = 1\n".repeat(100_000) + "model = AutoModel.from_pretrained(\"model\")\n";
+        fs::write(repo_dir.join("generated.py"), large)?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert!(results.slowest_files().is_empty());
+
+        let config = ScanConfig {
+            per_file_timing: true,
+            ..Default::default()
+        };
+        let results = scan_directory(root.path(), &config);
+        let slowest = results.slowest_files();
+        assert_eq!(slowest.len(), 6);
+        assert!(slowest[0].file.ends_with("generated.py"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_files_by_depth() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(repo_dir.join("src").join("models"))?;
+        for file in [
+            root.path().join("setup.py"),
+            root.path().join("org").join("conftest.py"),
+            repo_dir.join("a.py"),
+            repo_dir.join("b.py"),
+            repo_dir.join("src").join("models").join("c.py"),
+        ] {
+            fs::write(file, "print(\"no models here\")\n")?;
+        }
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert_eq!(
+            results.files_by_depth,
+            BTreeMap::from([(0, 1), (1, 1), (2, 2), (4, 1)])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_size_limits() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        for (name, size) in [
+            ("tiny.py", 49),
+            ("min.py", 50),
+            ("max.py", 100),
+            ("big.py", 101),
+        ] {
+            fs::write(repo_dir.join(name), "#".repeat(size))?;
+        }
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert_eq!(results.stats.files_scanned, 4);
+
+        let config = ScanConfig {
+            include_safe_in_csv: true,
+            min_file_size: 50,
+            max_file_size: Some(100),
+            ..Default::default()
+        };
+        let results = scan_directory(root.path(), &config);
+        let mut files: Vec<&str> = results.file_rows.iter().map(|r| r.file.as_str()).collect();
+        files.sort();
+        assert_eq!(files, vec!["org/repo/max.py", "org/repo/min.py"]);
+        assert_eq!(results.stats.files_skipped_too_large, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_unsafe_files() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("safe.py"),
+            "AutoModel.from_pretrained(\"model\", revision=\"5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d\")\n",
+        )?;
+        fs::write(
+            repo_dir.join("unsafe.py"),
+            "AutoModel.from_pretrained(\"model\")\n",
+        )?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        let key = ("org".to_string(), "repo".to_string());
+        assert_eq!(results.project_statuses[&key], Status::Unsafe);
+        assert_eq!(
+            results.project_unsafe_files,
+            HashMap::from([(key, vec!["org/repo/unsafe.py".to_string()])])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_load_usages() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("card.py"),
+            "card = ModelCard.load(\"org/model\")\nmodel = AutoModel.from_pretrained(\"org/model\")\n",
+        )?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert_eq!(results.stats.metadata_load_usages, 1);
+        assert_eq!(results.stats.partial_usages, 1);
+        assert_eq!(results.stats.unsafe_usages, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_joblib_unsafe_usages() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("clf.py"),
+            "clf = joblib.load(\"model.pkl\")\nold = joblib.load(\"old.pkl\")  # noscan\n",
+        )?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert_eq!(results.stats.joblib_unsafe_usages, 1);
+        assert_eq!(results.stats.unsafe_usages, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_generated() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        let load = "model = AutoModel.from_pretrained(\"model\")\n";
+        fs::write(
+            repo_dir.join("generated_pb2.py"),
+            format!("# -*- coding: utf-8 -*-\n# DO NOT EDIT!\n{load}"),
+        )?;
+        fs::write(
+            repo_dir.join("custom.py"),
+            format!("# built by codegen-tool\n{load}"),
+        )?;
+        fs::write(repo_dir.join("load.py"), load)?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert_eq!(results.stats.files_scanned, 3);
+        assert_eq!(results.stats.unsafe_usages, 3);
+
+        let config = ScanConfig {
+            skip_generated: true,
+            ..Default::default()
+        };
+        let results = scan_directory(root.path(), &config);
+        assert_eq!(results.stats.files_scanned, 2);
+        assert_eq!(results.stats.files_skipped_generated, 1);
+        assert_eq!(results.stats.unsafe_usages, 2);
+
+        let config = ScanConfig {
+            skip_generated: true,
+            generated_markers: vec![regex::Regex::new("built by codegen").unwrap()],
+            ..Default::default()
+        };
+        let results = scan_directory(root.path(), &config);
+        assert_eq!(results.stats.files_skipped_generated, 2);
+        assert_eq!(results.file_rows.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_org_repo() {
+        let root = PathBuf::from("/home/user/repos");
+        let path = PathBuf::from("/home/user/repos/microsoft/DialoGPT/src/model.py");
+
+        let (org, repo) = get_org_repo(&path, &root);
+        assert_eq!(org, "microsoft");
+        assert_eq!(repo, "DialoGPT");
+    }
+
+    #[test]
+    fn test_get_org_repo_with_comma() {
+        let root = PathBuf::from("/repos");
+        let path = PathBuf::from("/repos/org, with comma/repo-name/file.py");
+
+        let (org, repo) = get_org_repo(&path, &root);
+        assert_eq!(org, "org, with comma");
+        assert_eq!(repo, "repo-name");
+    }
+
+    #[test]
+    fn test_get_org_repo_relative_root() {
+        // Neither path exists, so this goes through the lexical fallback.
+        let root = PathBuf::from("./not-a-real-dir/repos");
+        let path = std::env::current_dir()
+            .unwrap()
+            .join("not-a-real-dir/repos/org/repo/model.py");
+        assert_eq!(
+            get_org_repo(&path, &root),
+            ("org".to_string(), "repo".to_string())
+        );
+        assert_eq!(
+            get_org_repo(&path, Path::new("elsewhere")),
+            ("unknown".to_string(), "unknown".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_org_repo_parent_dir_components() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        fs::create_dir_all(root.path().join("org/repo"))?;
+        fs::create_dir_all(root.path().join("org/other"))?;
+        fs::write(root.path().join("org/repo/model.py"), "")?;
+
+        let path = root.path().join("org/other/../repo/model.py");
+        assert_eq!(
+            get_org_repo(&path, root.path()),
+            ("org".to_string(), "repo".to_string())
+        );
+        let missing = PathBuf::from("/repos/org/other/../../org2/repo/missing.py");
+        assert_eq!(
+            get_org_repo(&missing, Path::new("/repos")),
+            ("org2".to_string(), "repo".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_org_repo_symlinked_root() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let real = dir.path().join("real");
+        fs::create_dir_all(real.join("org/repo"))?;
+        fs::write(real.join("org/repo/model.py"), "")?;
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&real, &link)?;
+
+        assert_eq!(
+            get_org_repo(&real.join("org/repo/model.py"), &link),
+            ("org".to_string(), "repo".to_string())
+        );
+        assert_eq!(
+            get_org_repo(&link.join("org/repo/model.py"), &real),
+            ("org".to_string(), "repo".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_directories_aggregates_roots() -> std::io::Result<()> {
+        let first = tempfile::tempdir()?;
+        let second = tempfile::tempdir()?;
+        for (root, org, code) in [
+            (
+                &first,
+                "org-a",
+                "AutoModel.from_pretrained(\"model\", revision=\"main\")\n",
+            ),
+            (
+                &first,
+                "shared",
+                "AutoModel.from_pretrained(\"model\", revision=\"main\")\n",
+            ),
+            (&second, "org-b", "AutoModel.from_pretrained(\"model\")\n"),
+            (&second, "shared", "AutoModel.from_pretrained(\"model\")\n"),
+        ] {
+            let repo_dir = root.path().join(org).join("repo");
+            fs::create_dir_all(&repo_dir)?;
+            fs::write(repo_dir.join("load.py"), code)?;
+        }
+
+        let results = scan_directories(&[first.path(), second.path()], &ScanConfig::default());
+        assert_eq!(results.stats.files_scanned, 4);
+        assert_eq!(results.stats.partial_usages, 2);
+        assert_eq!(results.stats.unsafe_usages, 2);
+        assert_eq!(results.project_statuses.len(), 3);
+        assert_eq!(
+            results.project_statuses[&("org-a".to_string(), "repo".to_string())],
+            Status::PartiallySafe
+        );
+        assert_eq!(
+            results.project_statuses[&("shared".to_string(), "repo".to_string())],
+            Status::Unsafe
+        );
+        assert_eq!(results.stats.partial_projects, 1);
+        assert_eq!(results.stats.unsafe_projects, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_directory_scan_extensions() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("fetch.sh"),
+            "huggingface-cli download org/model\n",
+        )?;
+        fs::write(
+            repo_dir.join("Makefile"),
+            "models:\n\thuggingface-cli download org/model --revision main\n",
+        )?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert_eq!(results.stats.files_scanned, 0);
+
+        let config = ScanConfig {
+            scan_extensions: vec!["sh".to_string(), "Makefile".to_string()],
+            ..Default::default()
+        };
+        let results = scan_directory(root.path(), &config);
+        assert_eq!(results.stats.files_scanned, 2);
+        assert_eq!(results.stats.unsafe_usages, 1);
+        assert_eq!(results.stats.partial_usages, 1);
+
+        Ok(())
+    }
+}
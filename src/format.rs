@@ -0,0 +1,29 @@
+use std::fmt::Display;
+
+/// Format an unsigned integer with `,` thousand separators: `1234567` becomes `1,234,567`.
+pub fn format_count(n: impl Display) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0usize), "0");
+        assert_eq!(format_count(999usize), "999");
+        assert_eq!(format_count(1_000usize), "1,000");
+        assert_eq!(format_count(12_345usize), "12,345");
+        assert_eq!(format_count(1_000_000usize), "1,000,000");
+        assert_eq!(format_count(u64::MAX), "18,446,744,073,709,551,615");
+    }
+}
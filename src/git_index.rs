@@ -0,0 +1,244 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::ScanConfig;
+use crate::scanner::scan_code_for_usage;
+
+/// A file as staged in the git index, i.e. as it will be committed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedFile {
+    /// Path relative to the directory the index was listed from.
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Run `git` in `dir` and return its stdout, failing with git's stderr when it exits
+/// unsuccessfully.
+fn git(dir: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Files staged in the index of the git repository containing `dir`, limited to those
+/// below `dir`, with their contents read from the index rather than the working tree.
+///
+/// Unmerged entries, submodules and symlinks are skipped, as are blobs that are not
+/// valid UTF-8.
+pub fn staged_files(dir: &Path) -> io::Result<Vec<StagedFile>> {
+    staged_files_matching(dir, |_| true)
+}
+
+/// [`staged_files`] limited to the paths for which `include` returns `true`. Paths are
+/// filtered from the index listing before any blob is read, and the remaining blobs are
+/// read through a single `git cat-file --batch`.
+pub fn staged_files_matching(
+    dir: &Path,
+    mut include: impl FnMut(&Path) -> bool,
+) -> io::Result<Vec<StagedFile>> {
+    let listing = git(dir, &["ls-files", "--stage", "-z"])?;
+    let mut entries = Vec::new();
+    for entry in listing.split(|&b| b == 0).filter(|e| !e.is_empty()) {
+        // `<mode> <object> <stage>\t<path>`
+        let entry = String::from_utf8_lossy(entry);
+        let Some((info, path)) = entry.split_once('\t') else {
+            continue;
+        };
+        let [mode, object, stage] = info.split(' ').collect::<Vec<_>>()[..] else {
+            continue;
+        };
+        if stage != "0" || !mode.starts_with("100") || !include(Path::new(path)) {
+            continue;
+        }
+        entries.push((PathBuf::from(path), object.to_string()));
+    }
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let objects: Vec<&str> = entries.iter().map(|(_, object)| object.as_str()).collect();
+    let blobs = cat_file_batch(dir, &objects)?;
+    let mut files = Vec::new();
+    for ((path, _), blob) in entries.into_iter().zip(blobs) {
+        match String::from_utf8(blob) {
+            Ok(content) => files.push(StagedFile { path, content }),
+            Err(_) => {
+                tracing::debug!(path = %path.display(), "skipping staged file that is not UTF-8")
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Contents of each of `objects`, in order, read with one `git cat-file --batch` process.
+fn cat_file_batch(dir: &Path, objects: &[&str]) -> io::Result<Vec<Vec<u8>>> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Written from another thread so a full stdout pipe cannot block git while it waits
+    // for more input.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input: String = objects.iter().map(|object| format!("{object}\n")).collect();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut blobs = Vec::with_capacity(objects.len());
+    let mut header = String::new();
+    for object in objects {
+        // `<object> <type> <size>`, then the contents and a newline, or `<object> missing`.
+        header.clear();
+        stdout.read_line(&mut header)?;
+        let size = match header.trim_end().split(' ').collect::<Vec<_>>()[..] {
+            [_, "blob", size] => size.parse::<usize>().ok(),
+            _ => None,
+        };
+        let Some(size) = size else {
+            child.kill()?;
+            child.wait()?;
+            return Err(io::Error::other(format!(
+                "git cat-file --batch failed on {object}: {}",
+                header.trim_end()
+            )));
+        };
+        let mut blob = vec![0; size + 1];
+        stdout.read_exact(&mut blob)?;
+        blob.pop();
+        blobs.push(blob);
+    }
+    writer.join().expect("stdin writer panicked")?;
+    drop(stdout);
+    child.wait()?;
+    Ok(blobs)
+}
+
+/// Contents of `path`, relative to the repository root, in the tree of commit `rev`, read
+/// from the object database of the repository at `repo`. The working tree is never
+/// touched, so this works on bare repositories and without checking `rev` out.
+pub fn read_blob(repo: &Path, rev: &str, path: &str) -> io::Result<String> {
+    let blob = git(repo, &["cat-file", "blob", &format!("{rev}:{path}")])?;
+    String::from_utf8(blob).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// [`scan_code_for_usage`] on `path` as it was at commit `rev`; see [`read_blob`].
+pub fn scan_file_from_git(
+    repo: &Path,
+    rev: &str,
+    path: &str,
+    config: &ScanConfig,
+) -> io::Result<(usize, usize, usize)> {
+    Ok(scan_code_for_usage(&read_blob(repo, rev, path)?, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(repo: &Path, path: &str, content: &str) -> io::Result<()> {
+        std::fs::write(repo.join(path), content)?;
+        git(repo, &["add", path])?;
+        git(
+            repo,
+            &[
+                "-c",
+                "user.name=hfscanner",
+                "-c",
+                "user.email=hfscanner@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                path,
+            ],
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_staged_files_matching() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let repo = dir.path();
+        git(repo, &["init", "--quiet"])?;
+        std::fs::create_dir(repo.join("sub"))?;
+        std::fs::write(repo.join("a.py"), "import transformers\n")?;
+        std::fs::write(repo.join("empty.py"), "")?;
+        std::fs::write(repo.join("sub").join("b.py"), "x = 1\n\n")?;
+        std::fs::write(repo.join("notes.txt"), "notes\n")?;
+        std::fs::write(repo.join("binary.py"), [0xff, 0xfe, 0x00])?;
+        git(repo, &["add", "."])?;
+        std::fs::write(repo.join("a.py"), "changed\n")?;
+
+        let files =
+            staged_files_matching(repo, |path| path.extension().is_some_and(|ext| ext == "py"))?;
+        let files: Vec<(&str, &str)> = files
+            .iter()
+            .map(|f| (f.path.to_str().unwrap(), f.content.as_str()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("a.py", "import transformers\n"),
+                ("empty.py", ""),
+                ("sub/b.py", "x = 1\n\n"),
+            ]
+        );
+        assert_eq!(staged_files(repo)?.len(), 4);
+        assert!(staged_files_matching(repo, |_| false)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_file_from_git() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo)?;
+        git(&repo, &["init", "--quiet"])?;
+        commit(
+            &repo,
+            "load.py",
+            "m = AutoModel.from_pretrained(\"gpt2\")\n",
+        )?;
+        commit(
+            &repo,
+            "load.py",
+            "m = AutoModel.from_pretrained(\"gpt2\", revision=\"main\")\n",
+        )?;
+        std::fs::write(repo.join("load.py"), "")?;
+
+        let config = ScanConfig::default();
+        assert_eq!(
+            scan_file_from_git(&repo, "HEAD~1", "load.py", &config)?,
+            (0, 0, 1)
+        );
+        assert_eq!(
+            scan_file_from_git(&repo, "HEAD", "load.py", &config)?,
+            (0, 1, 0)
+        );
+        assert!(scan_file_from_git(&repo, "HEAD", "missing.py", &config).is_err());
+
+        let bare = dir.path().join("bare.git");
+        let output = Command::new("git")
+            .args(["clone", "--quiet", "--bare"])
+            .arg(&repo)
+            .arg(&bare)
+            .output()?;
+        assert!(output.status.success());
+        assert_eq!(
+            scan_file_from_git(&bare, "HEAD~1", "load.py", &config)?,
+            (0, 0, 1)
+        );
+
+        Ok(())
+    }
+}
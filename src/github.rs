@@ -0,0 +1,384 @@
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde_json::json;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::directory::{ScanResults, ScanStats};
+
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+/// Heading of the review comment unless a report title is given.
+pub const DEFAULT_REPORT_TITLE: &str = "silentinjection scan summary";
+
+/// Attempts made before giving up on a rate-limited request.
+const MAX_ATTEMPTS: u32 = 3;
+/// Longest rate-limit reset we are willing to sleep through before failing instead.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// The pull request a review comment is posted to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullRequest {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl PullRequest {
+    /// Build from an `owner/repo` string and a PR number, as passed on the command line.
+    pub fn parse(owner_repo: &str, number: &str) -> Option<Self> {
+        let (owner, repo) = owner_repo.split_once('/')?;
+        if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+            return None;
+        }
+        Some(PullRequest {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number: number.parse().ok()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum GithubError {
+    Http(reqwest::Error),
+    /// GitHub answered with a non-success status.
+    Status {
+        status: StatusCode,
+        body: String,
+    },
+    /// Still rate limited after retrying, or the reset is too far away to wait for.
+    RateLimited,
+}
+
+impl fmt::Display for GithubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GithubError::Http(e) => write!(f, "request to GitHub failed: {e}"),
+            GithubError::Status { status, body } => {
+                write!(f, "GitHub returned {status}: {body}")
+            }
+            GithubError::RateLimited => write!(f, "GitHub API rate limit exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for GithubError {}
+
+fn format_delta(current: usize, baseline: usize) -> String {
+    match current.cmp(&baseline) {
+        std::cmp::Ordering::Greater => format!("+{}", current - baseline),
+        std::cmp::Ordering::Less => format!("-{}", baseline - current),
+        std::cmp::Ordering::Equal => "0".to_string(),
+    }
+}
+
+/// Escape `&`, `<` and `>` so user-supplied text cannot inject HTML, which GitHub renders
+/// inside Markdown.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Render the Markdown review body under `title` (default [`DEFAULT_REPORT_TITLE`]): the
+/// scan summary and, if a scan of the base branch is given, how the counts changed
+/// relative to it.
+pub fn render_pr_comment(
+    results: &ScanResults,
+    baseline: Option<&ScanStats>,
+    title: Option<&str>,
+) -> String {
+    let stats = &results.stats;
+    let title = escape_html(title.unwrap_or(DEFAULT_REPORT_TITLE).trim());
+    let mut out = format!("## {}\n\n", title.replace('\n', " "));
+    out.push_str("| | Safe | Partially safe | Unsafe |\n");
+    out.push_str("|---|---:|---:|---:|\n");
+    out.push_str(&format!(
+        "| Usages | {} | {} | {} |\n",
+        stats.safe_usages, stats.partial_usages, stats.unsafe_usages
+    ));
+    out.push_str(&format!(
+        "| Projects | {} | {} | {} |\n",
+        stats.safe_projects, stats.partial_projects, stats.unsafe_projects
+    ));
+    out.push_str(&format!("\nRisk score: **{}**\n", results.risk_score));
+
+    if let Some(base) = baseline {
+        out.push_str("\n### Change from base branch\n\n");
+        out.push_str("| | Safe | Partially safe | Unsafe |\n");
+        out.push_str("|---|---:|---:|---:|\n");
+        out.push_str(&format!(
+            "| Usages | {} | {} | {} |\n",
+            format_delta(stats.safe_usages, base.safe_usages),
+            format_delta(stats.partial_usages, base.partial_usages),
+            format_delta(stats.unsafe_usages, base.unsafe_usages)
+        ));
+        out.push_str(&format!(
+            "| Projects | {} | {} | {} |\n",
+            format_delta(stats.safe_projects, base.safe_projects),
+            format_delta(stats.partial_projects, base.partial_projects),
+            format_delta(stats.unsafe_projects, base.unsafe_projects)
+        ));
+    }
+    out
+}
+
+/// How long to wait before retrying, if `response` is a rate-limit rejection.
+fn rate_limit_wait(response: &Response) -> Option<Duration> {
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+    let exhausted =
+        response.status() == StatusCode::FORBIDDEN && header("x-ratelimit-remaining") == Some(0);
+    if response.status() != StatusCode::TOO_MANY_REQUESTS && !exhausted {
+        return None;
+    }
+
+    if let Some(secs) = header("retry-after") {
+        return Some(Duration::from_secs(secs));
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    Some(match header("x-ratelimit-reset") {
+        Some(reset) => Duration::from_secs(reset.saturating_sub(now)),
+        None => MAX_RATE_LIMIT_WAIT,
+    })
+}
+
+/// Send the request built by `request` with the GitHub headers, retrying when rate limited.
+fn send(token: &str, request: impl Fn() -> RequestBuilder) -> Result<Response, GithubError> {
+    for _ in 0..MAX_ATTEMPTS {
+        let response = request()
+            .bearer_auth(token)
+            .header("Accept", "application/vnd.github+json")
+            .header(
+                "User-Agent",
+                concat!("hfscanner/", env!("CARGO_PKG_VERSION")),
+            )
+            .send()
+            .map_err(GithubError::Http)?;
+
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        match rate_limit_wait(&response) {
+            Some(wait) if wait <= MAX_RATE_LIMIT_WAIT => std::thread::sleep(wait),
+            Some(_) => return Err(GithubError::RateLimited),
+            None => {
+                return Err(GithubError::Status {
+                    status: response.status(),
+                    body: response.text().unwrap_or_default(),
+                });
+            }
+        }
+    }
+    Err(GithubError::RateLimited)
+}
+
+/// Post `body` as a review comment on `pr`, retrying when rate limited.
+///
+/// `api_url` is `https://api.github.com` unless talking to GitHub Enterprise.
+pub fn post_pr_review(
+    api_url: &str,
+    token: &str,
+    pr: &PullRequest,
+    body: &str,
+) -> Result<(), GithubError> {
+    let url = format!(
+        "{}/repos/{}/{}/pulls/{}/reviews",
+        api_url.trim_end_matches('/'),
+        pr.owner,
+        pr.repo,
+        pr.number
+    );
+    let client = Client::new();
+    let payload = json!({ "body": body, "event": "COMMENT" });
+    send(token, || client.post(&url).json(&payload))?;
+    Ok(())
+}
+
+/// Repositories listed per page by [`private_repos`], the most GitHub allows.
+const REPOS_PER_PAGE: usize = 100;
+
+/// `org/repo` names of the private repositories of `org` that `token` can see.
+pub fn private_repos(api_url: &str, token: &str, org: &str) -> Result<Vec<String>, GithubError> {
+    let url = format!("{}/orgs/{org}/repos", api_url.trim_end_matches('/'));
+    let client = Client::new();
+    let mut names = Vec::new();
+    for page in 1.. {
+        let query = [
+            ("type", "private".to_string()),
+            ("per_page", REPOS_PER_PAGE.to_string()),
+            ("page", page.to_string()),
+        ];
+        let response = send(token, || client.get(&url).query(&query))?;
+        let repos: Vec<serde_json::Value> = response.json().map_err(GithubError::Http)?;
+        names.extend(
+            repos
+                .iter()
+                .filter_map(|repo| repo["full_name"].as_str().map(str::to_string)),
+        );
+        if repos.len() < REPOS_PER_PAGE {
+            break;
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// Answer one request per canned response and forward each raw request to the channel.
+    fn mock_github(responses: Vec<&'static str>) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = len.trim().parse().unwrap();
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                request.push_str(&String::from_utf8(body).unwrap());
+                tx.send(request).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (format!("http://{addr}"), rx)
+    }
+
+    fn pr() -> PullRequest {
+        PullRequest::parse("octo/scanner", "42").unwrap()
+    }
+
+    #[test]
+    fn test_pull_request_parse() {
+        assert_eq!(
+            pr(),
+            PullRequest {
+                owner: "octo".to_string(),
+                repo: "scanner".to_string(),
+                number: 42
+            }
+        );
+        assert_eq!(PullRequest::parse("octo", "42"), None);
+        assert_eq!(PullRequest::parse("octo/a/b", "42"), None);
+        assert_eq!(PullRequest::parse("octo/scanner", "abc"), None);
+    }
+
+    #[test]
+    fn test_render_pr_comment_with_baseline() {
+        let mut results = ScanResults::default();
+        results.stats.unsafe_usages = 3;
+        results.stats.safe_usages = 2;
+        let baseline = ScanStats {
+            unsafe_usages: 5,
+            ..Default::default()
+        };
+
+        let comment = render_pr_comment(&results, None, None);
+        assert!(comment.contains("| Usages | 2 | 0 | 3 |"));
+        assert!(!comment.contains("Change from base branch"));
+
+        let comment = render_pr_comment(&results, Some(&baseline), None);
+        assert!(comment.contains("### Change from base branch"));
+        assert!(comment.contains("| Usages | +2 | 0 | -2 |"));
+    }
+
+    #[test]
+    fn test_render_pr_comment_title() {
+        let results = ScanResults::default();
+        assert!(
+            render_pr_comment(&results, None, None)
+                .starts_with("## silentinjection scan summary\n")
+        );
+
+        let comment = render_pr_comment(
+            &results,
+            None,
+            Some("Q1 Audit <script>alert(1)</script> & more"),
+        );
+        assert!(
+            comment.starts_with("## Q1 Audit &lt;script&gt;alert(1)&lt;/script&gt; &amp; more\n")
+        );
+        assert!(!comment.contains('<'));
+    }
+
+    #[test]
+    fn test_post_pr_review() {
+        let (api_url, requests) = mock_github(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+        ]);
+
+        post_pr_review(&api_url, "secret", &pr(), "scan summary").unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("POST /repos/octo/scanner/pulls/42/reviews HTTP/1.1"));
+        assert!(request.contains("authorization: Bearer secret"));
+        assert!(request.contains(r#""body":"scan summary""#));
+        assert!(request.contains(r#""event":"COMMENT""#));
+    }
+
+    #[test]
+    fn test_post_pr_review_retries_when_rate_limited() {
+        let (api_url, requests) = mock_github(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+        ]);
+
+        post_pr_review(&api_url, "secret", &pr(), "scan summary").unwrap();
+        assert_eq!(requests.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_private_repos() {
+        let (api_url, requests) = mock_github(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 47\r\nConnection: close\r\n\r\n[{\"full_name\":\"octo/a\"},{\"full_name\":\"octo/b\"}]",
+        ]);
+
+        assert_eq!(
+            private_repos(&api_url, "secret", "octo").unwrap(),
+            vec!["octo/a".to_string(), "octo/b".to_string()]
+        );
+        let request = requests.recv().unwrap();
+        assert!(
+            request.starts_with("GET /orgs/octo/repos?type=private&per_page=100&page=1 HTTP/1.1")
+        );
+        assert!(request.contains("authorization: Bearer secret"));
+    }
+
+    #[test]
+    fn test_post_pr_review_error_status() {
+        let (api_url, _requests) = mock_github(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\nConnection: close\r\n\r\nNot Found",
+        ]);
+
+        let err = post_pr_review(&api_url, "secret", &pr(), "scan summary").unwrap_err();
+        assert!(matches!(
+            err,
+            GithubError::Status { status: StatusCode::NOT_FOUND, ref body } if body == "Not Found"
+        ));
+    }
+}
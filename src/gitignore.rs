@@ -0,0 +1,163 @@
+use regex::Regex;
+use std::path::Path;
+
+/// One pattern line of a `.gitignore`.
+#[derive(Debug, Clone)]
+struct Rule {
+    regex: Regex,
+    /// `!pattern`: re-include what an earlier pattern excluded.
+    negated: bool,
+    /// `pattern/`: only match directories.
+    dir_only: bool,
+}
+
+/// The patterns of a `.gitignore` file, matched against paths relative to the directory
+/// containing it.
+///
+/// Blank lines, `#` comments, `!` negation, a trailing `/` for directories, anchoring by a
+/// leading or inner `/`, and the `*`, `?`, `[...]` and `**` wildcards are supported, which
+/// covers the usual `venv/`, `build/` and `*.egg-info` entries. As with git, the last
+/// matching pattern decides, and nested `.gitignore` files are not read.
+#[derive(Debug, Clone, Default)]
+pub struct Gitignore {
+    rules: Vec<Rule>,
+}
+
+/// The regex for one glob, matching a whole relative path when `anchored` and any
+/// trailing run of its components otherwise.
+fn glob_regex(glob: &str, anchored: bool) -> Option<Regex> {
+    let mut regex = String::from(if anchored { "^" } else { "(?:^|/)" });
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                let class: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let class = class
+                    .strip_prefix('!')
+                    .map_or(class.clone(), |rest| format!("^{rest}"));
+                regex.push('[');
+                regex.push_str(&class.replace('\\', "\\\\"));
+                regex.push(']');
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).ok()
+}
+
+impl Gitignore {
+    /// Parse the contents of a `.gitignore`, skipping patterns that cannot be converted.
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim_end();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let (negated, pattern) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let (dir_only, pattern) = match pattern.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, pattern),
+                };
+                let anchored = pattern.contains('/');
+                let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+                if pattern.is_empty() {
+                    return None;
+                }
+                let Some(regex) = glob_regex(pattern, anchored) else {
+                    tracing::warn!(pattern = line, "skipping unsupported .gitignore pattern");
+                    return None;
+                };
+                Some(Rule {
+                    regex,
+                    negated,
+                    dir_only,
+                })
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// The `.gitignore` directly in `root`, or no patterns if there is none.
+    pub fn from_root(root: &Path) -> Self {
+        match std::fs::read_to_string(root.join(".gitignore")) {
+            Ok(content) => Self::parse(&content),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Whether `path`, relative to the `.gitignore`'s directory and `/`-separated, is
+    /// ignored. Only the path itself is checked, not its parent directories.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.regex.is_match(path))
+            .is_some_and(|rule| !rule.negated)
+    }
+
+    /// [`Gitignore::is_ignored`] for a directory walk entry below `root`.
+    pub fn is_ignored_entry(&self, root: &Path, entry: &walkdir::DirEntry) -> bool {
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            return false;
+        };
+        let path: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        !path.is_empty() && self.is_ignored(&path.join("/"), entry.file_type().is_dir())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore() {
+        let gitignore = Gitignore::parse(
+            "\
+# environments
+custom_env/
+/build
+*.egg-info
+docs/**/generated
+!keep.egg-info
+tmp[0-9]
+",
+        );
+        assert!(gitignore.is_ignored("custom_env", true));
+        assert!(gitignore.is_ignored("org/repo/custom_env", true));
+        assert!(!gitignore.is_ignored("custom_env", false));
+        assert!(gitignore.is_ignored("build", true));
+        assert!(!gitignore.is_ignored("org/build", true));
+        assert!(gitignore.is_ignored("org/pkg.egg-info", true));
+        assert!(!gitignore.is_ignored("keep.egg-info", true));
+        assert!(gitignore.is_ignored("docs/generated", true));
+        assert!(gitignore.is_ignored("docs/api/v1/generated", true));
+        assert!(gitignore.is_ignored("tmp7", false));
+        assert!(!gitignore.is_ignored("tmpx", false));
+        assert!(!gitignore.is_ignored("load.py", false));
+    }
+}
@@ -0,0 +1,267 @@
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+use walkdir::WalkDir;
+
+use crate::directory::{ScanResults, get_org_repo, is_excluded};
+use crate::scanner::Status;
+
+/// Modules named by the `import` and `from ... import` statements of `code`.
+///
+/// `from X import Y` yields both `X` and `X.Y`, since `Y` may be a submodule. Relative
+/// imports keep their leading dots (`.loader`). Only the first line of a parenthesised
+/// import list is read.
+pub fn python_imports(code: &str) -> Vec<String> {
+    // `name` or `name as alias`, without the alias.
+    let names = |list: &str| -> Vec<String> {
+        list.split(',')
+            .map(|name| name.trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace()))
+            .filter_map(|name| name.split_whitespace().next())
+            .filter(|name| *name != "*" && *name != "\\")
+            .map(str::to_string)
+            .collect()
+    };
+
+    let mut modules = Vec::new();
+    for caps in IMPORT_RE.captures_iter(code) {
+        modules.extend(names(&caps[1]));
+    }
+    for caps in FROM_IMPORT_RE.captures_iter(code) {
+        let module = &caps[1];
+        if !module.trim_start_matches('.').is_empty() {
+            modules.push(module.to_string());
+        }
+        for name in names(&caps[2]) {
+            if module.ends_with('.') {
+                modules.push(format!("{module}{name}"));
+            } else {
+                modules.push(format!("{module}.{name}"));
+            }
+        }
+    }
+    modules
+}
+
+static IMPORT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*import\s+([^#\n]+)").unwrap());
+
+static FROM_IMPORT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*from\s+(\.*[\w.]*)\s+import\s+([^#\n]+)").unwrap());
+
+/// Dotted module path of a file relative to its scan root: `org/repo/pkg/mod.py` is
+/// `org.repo.pkg.mod` and `org/repo/pkg/__init__.py` is `org.repo.pkg`.
+fn module_path(file: &str) -> String {
+    let without_ext = file.strip_suffix(".py").unwrap_or(file);
+    let without_init = without_ext.strip_suffix("/__init__").unwrap_or(without_ext);
+    without_init.replace(['/', '\\'], ".")
+}
+
+/// Files that import a model-loading file, for rendering with [`ImportGraph::to_dot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportGraph {
+    /// Every file in the graph, with the worst status of its own findings.
+    pub nodes: BTreeMap<String, Option<Status>>,
+    /// `(importer, loader)` pairs: `importer` imports `loader`, which has findings.
+    pub edges: BTreeSet<(String, String)>,
+}
+
+impl ImportGraph {
+    /// Graphviz DOT source with unsafe files in red, partially safe ones in yellow and safe
+    /// ones in green. Files without findings of their own are left white.
+    pub fn to_dot(&self) -> String {
+        let quote = |id: &str| format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut dot = String::from(
+            "digraph model_loads {\n    node [shape=box, style=filled, fillcolor=white];\n",
+        );
+        for (file, status) in &self.nodes {
+            let color = match status {
+                None | Some(Status::Ignored) => None,
+                Some(Status::Safe) => Some("green"),
+                Some(
+                    Status::PartiallySafe
+                    | Status::MetadataLoad
+                    | Status::LocalFileLoad
+                    | Status::ApiVersionUnpinned
+                    | Status::RemoteCall,
+                ) => Some("yellow"),
+                Some(Status::Unsafe | Status::TrainingWithUnsafeBase | Status::PathTraversal) => {
+                    Some("red")
+                }
+            };
+            match color {
+                Some(color) => writeln!(dot, "    {} [fillcolor={color}];", quote(file)),
+                None => writeln!(dot, "    {};", quote(file)),
+            }
+            .unwrap();
+        }
+        for (from, to) in &self.edges {
+            writeln!(dot, "    {} -> {};", quote(from), quote(to)).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Link the Python files under `roots` to the files with findings in `results` that they
+/// import.
+///
+/// Absolute imports are matched against the end of each loader's module path within the
+/// same `(org, repo)`, so `import pkg.loader` finds `org/repo/pkg/loader.py` as well as
+/// `org/repo/src/pkg/loader.py`. Relative imports are resolved against the importer's
+/// package.
+pub fn build_import_graph<P: AsRef<Path>>(roots: &[P], results: &ScanResults) -> ImportGraph {
+    let mut loader_status: HashMap<&str, Status> = HashMap::new();
+    for finding in &results.findings {
+        let status = loader_status.entry(&finding.file).or_insert(finding.status);
+        *status = (*status).max(finding.status);
+    }
+    let loaders: Vec<(&str, String)> = loader_status
+        .keys()
+        .map(|&file| (file, module_path(file)))
+        .collect();
+
+    let mut graph = ImportGraph::default();
+    for (file, status) in &loader_status {
+        graph.nodes.insert(file.to_string(), Some(*status));
+    }
+    for root in roots {
+        let root = root.as_ref();
+        let files = WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|e| !is_excluded(e))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && e.path().extension() == Some("py".as_ref()));
+        for entry in files {
+            let path = entry.path();
+            let Ok(code) = fs::read_to_string(path) else {
+                continue;
+            };
+            let file = path
+                .strip_prefix(root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string();
+            let project = get_org_repo(path, root);
+            let package = module_path(&file);
+            let package = if file.ends_with("__init__.py") {
+                package.as_str()
+            } else {
+                package.rsplit_once('.').map_or("", |(package, _)| package)
+            };
+
+            for import in python_imports(&code) {
+                let dots = import.len() - import.trim_start_matches('.').len();
+                let target = if dots > 0 {
+                    // `.x` is relative to the package itself, `..x` to its parent, ...
+                    let mut base = package;
+                    for _ in 1..dots {
+                        base = base.rsplit_once('.').map_or("", |(parent, _)| parent);
+                    }
+                    format!("{base}.{}", &import[dots..])
+                        .trim_end_matches('.')
+                        .to_string()
+                } else {
+                    import
+                };
+                for (loader, module) in &loaders {
+                    let matches = if dots > 0 {
+                        *module == target
+                    } else {
+                        module.ends_with(&format!(".{target}"))
+                            && get_org_repo(&root.join(loader), root) == project
+                    };
+                    if matches && *loader != file {
+                        graph.nodes.entry(file.clone()).or_insert(None);
+                        graph.edges.insert((file.clone(), loader.to_string()));
+                    }
+                }
+            }
+        }
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::directory::scan_directory;
+
+    #[test]
+    fn test_python_imports() {
+        let code = "import os, pkg.loader as loader\n\
+                    from pkg import models, helpers as h\n\
+                    from . import sibling\n\
+                    from ..common.load import (get_model,\n    other)\n\
+                    from typing import *\n";
+        assert_eq!(
+            python_imports(code),
+            vec![
+                "os",
+                "pkg.loader",
+                "pkg",
+                "pkg.models",
+                "pkg.helpers",
+                ".sibling",
+                "..common.load",
+                "..common.load.get_model",
+                "typing",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_import_graph_dot() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo = root.path().join("org").join("repo");
+        fs::create_dir_all(repo.join("pkg"))?;
+        fs::write(
+            repo.join("pkg/loader.py"),
+            "model = AutoModel.from_pretrained(\"gpt2\")\n",
+        )?;
+        fs::write(
+            repo.join("pkg/partial.py"),
+            "model = AutoModel.from_pretrained(\"gpt2\", revision=\"main\")\n",
+        )?;
+        fs::write(repo.join("pkg/__init__.py"), "")?;
+        fs::write(repo.join("pkg/user.py"), "from .partial import model\n")?;
+        fs::write(
+            repo.join("app.py"),
+            "import pkg.loader\nfrom pkg import partial\nimport os\n",
+        )?;
+        fs::write(repo.join("unrelated.py"), "import json\n")?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        let dot = build_import_graph(&[root.path()], &results).to_dot();
+        let expected = "digraph model_loads {\n\
+            \x20   node [shape=box, style=filled, fillcolor=white];\n\
+            \x20   \"org/repo/app.py\";\n\
+            \x20   \"org/repo/pkg/loader.py\" [fillcolor=red];\n\
+            \x20   \"org/repo/pkg/partial.py\" [fillcolor=yellow];\n\
+            \x20   \"org/repo/pkg/user.py\";\n\
+            \x20   \"org/repo/app.py\" -> \"org/repo/pkg/loader.py\";\n\
+            \x20   \"org/repo/app.py\" -> \"org/repo/pkg/partial.py\";\n\
+            \x20   \"org/repo/pkg/user.py\" -> \"org/repo/pkg/partial.py\";\n\
+            }\n";
+        assert_eq!(dot, expected);
+        assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_dot_escapes_ids() {
+        let mut graph = ImportGraph::default();
+        graph
+            .nodes
+            .insert("org/repo/say \"hi\".py".to_string(), Some(Status::Safe));
+        assert!(
+            graph
+                .to_dot()
+                .contains("    \"org/repo/say \\\"hi\\\".py\" [fillcolor=green];\n")
+        );
+    }
+}
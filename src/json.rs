@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use std::collections::BTreeMap;
+
+use crate::directory::{FileRow, ScanResults, ScanStats};
+use crate::models::{ModelLoad, group_by_model};
+use crate::scanner::{Finding, Status};
+
+/// Version of the JSON report layout written by default. Consumers should check the
+/// top-level `schema_version` key before parsing the rest of the document.
+pub const JSON_SCHEMA_VERSION: u8 = 1;
+
+#[derive(Serialize)]
+struct ProjectEntry<'a> {
+    org: &'a str,
+    repo: &'a str,
+    status: Status,
+    /// Files with unsafe usages, which drove an `unsafe` status.
+    unsafe_files: &'a [String],
+}
+
+/// Root object of schema version 1. Field order is the key order in the output, so
+/// `schema_version` always comes first.
+#[derive(Serialize)]
+struct JsonReportV1<'a> {
+    schema_version: u8,
+    summary: &'a ScanStats,
+    risk_score: f64,
+    projects: Vec<ProjectEntry<'a>>,
+    files: &'a [FileRow],
+    findings: &'a [Finding],
+}
+
+/// Schema version 1 grouped by model, with `models` in place of `files`.
+#[derive(Serialize)]
+struct JsonModelsReportV1<'a> {
+    schema_version: u8,
+    summary: &'a ScanStats,
+    risk_score: f64,
+    projects: Vec<ProjectEntry<'a>>,
+    models: BTreeMap<String, Vec<ModelLoad>>,
+    findings: &'a [Finding],
+}
+
+fn check_schema_version(schema_version: u8) -> std::io::Result<()> {
+    if schema_version != 1 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("unsupported JSON schema version {schema_version}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Project entries sorted by org and repo.
+fn project_entries(results: &ScanResults) -> Vec<ProjectEntry<'_>> {
+    let mut projects: Vec<ProjectEntry> = results
+        .project_statuses
+        .iter()
+        .map(|(key, status)| ProjectEntry {
+            org: &key.0,
+            repo: &key.1,
+            status: *status,
+            unsafe_files: results
+                .project_unsafe_files
+                .get(key)
+                .map_or(&[], Vec::as_slice),
+        })
+        .collect();
+    projects.sort_by(|a, b| (a.org, a.repo).cmp(&(b.org, b.repo)));
+    projects
+}
+
+fn write_report<T: Serialize>(output_path: &str, report: &T) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(output_path)?);
+    serde_json::to_writer_pretty(&mut writer, report)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Write the full scan results as a JSON document using the given schema version.
+pub fn write_file_json(
+    output_path: &str,
+    results: &ScanResults,
+    schema_version: u8,
+) -> std::io::Result<()> {
+    check_schema_version(schema_version)?;
+    let report = JsonReportV1 {
+        schema_version,
+        summary: &results.stats,
+        risk_score: results.risk_score,
+        projects: project_entries(results),
+        files: &results.file_rows,
+        findings: &results.findings,
+    };
+    write_report(output_path, &report)
+}
+
+/// [`write_file_json`] with the files grouped by model, under a top-level `models` key
+/// mapping each model name to the files loading it; see [`group_by_model`].
+pub fn write_models_json(
+    output_path: &str,
+    results: &ScanResults,
+    schema_version: u8,
+) -> std::io::Result<()> {
+    check_schema_version(schema_version)?;
+    let report = JsonModelsReportV1 {
+        schema_version,
+        summary: &results.stats,
+        risk_score: results.risk_score,
+        projects: project_entries(results),
+        models: group_by_model(results),
+        findings: &results.findings,
+    };
+    write_report(output_path, &report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::directory::scan_directory;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_write_file_json() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("load.py"),
+            "model = AutoModel.from_pretrained(\"gpt2\")\n",
+        )?;
+        let results = scan_directory(root.path(), &ScanConfig::default());
+
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+        write_file_json(temp_path, &results, JSON_SCHEMA_VERSION)?;
+
+        let contents = fs::read_to_string(temp_path)?;
+        assert!(contents.starts_with("{\n  \"schema_version\": 1,"));
+        let data: serde_json::Value = serde_json::from_str(&contents)?;
+        assert_eq!(data["schema_version"], 1);
+        assert_eq!(data["summary"]["unsafe_usages"], 1);
+        assert_eq!(data["risk_score"], 10.0);
+        assert_eq!(data["projects"][0]["status"], "unsafe");
+        assert_eq!(
+            data["projects"][0]["unsafe_files"],
+            serde_json::json!(["org/repo/load.py"])
+        );
+        assert_eq!(data["files"][0]["file"], "org/repo/load.py");
+        assert_eq!(data["findings"][0]["line"], 1);
+        assert_eq!(data["findings"][0]["model_name"]["kind"], "literal");
+        assert_eq!(data["findings"][0]["model_name"]["value"], "gpt2");
+        assert!(data["findings"][0].get("context").is_none());
+
+        assert!(write_file_json(temp_path, &results, 2).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_models_json() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        for repo in ["repo-a", "repo-b"] {
+            let repo_dir = root.path().join("org").join(repo);
+            fs::create_dir_all(&repo_dir)?;
+            fs::write(
+                repo_dir.join("load.py"),
+                "model = AutoModel.from_pretrained(\"gpt2\")\n",
+            )?;
+        }
+        let results = scan_directory(root.path(), &ScanConfig::default());
+
+        let temp_file = NamedTempFile::new()?;
+        let temp_path = temp_file.path().to_str().unwrap();
+        write_models_json(temp_path, &results, JSON_SCHEMA_VERSION)?;
+
+        let data: serde_json::Value = serde_json::from_str(&fs::read_to_string(temp_path)?)?;
+        assert_eq!(data["schema_version"], 1);
+        assert!(data.get("files").is_none());
+        assert_eq!(
+            data["models"]["gpt2"],
+            serde_json::json!([
+                {"org": "org", "repo": "repo-a", "file": "org/repo-a/load.py", "status": "unsafe",
+                 "safe_usages": 0, "partial_usages": 0, "unsafe_usages": 1},
+                {"org": "org", "repo": "repo-b", "file": "org/repo-b/load.py", "status": "unsafe",
+                 "safe_usages": 0, "partial_usages": 0, "unsafe_usages": 1},
+            ])
+        );
+
+        Ok(())
+    }
+}
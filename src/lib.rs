@@ -0,0 +1,85 @@
+//! Scan Python codebases for Hugging Face model, dataset and file downloads that are not
+//! pinned to an immutable commit SHA.
+//!
+//! [`scan_directory`] walks a tree of `org/repo` checkouts and aggregates the results,
+//! while [`scan_code_for_usage`] and [`scan_file`] classify a single source file.
+
+pub mod aliases;
+pub mod annotations;
+pub mod bench;
+pub mod cache;
+pub mod config;
+pub mod config_file;
+pub mod csv;
+pub mod cwe;
+pub mod cyclonedx;
+pub mod directory;
+pub mod format;
+pub mod git_index;
+pub mod github;
+pub mod gitignore;
+pub mod graph;
+pub mod json;
+pub mod metrics;
+pub mod models;
+pub mod org_report;
+pub mod pattern_db;
+pub mod remediation;
+pub mod requirements;
+pub mod risk;
+pub mod scanner;
+pub mod streaming;
+pub mod vex;
+
+pub use aliases::{ImportAliasMap, ResolvedCode};
+pub use annotations::{format_annotation, github_annotations};
+pub use cache::{CACHE_FILE_NAME, ScanCache, content_hash};
+pub use config::{ConcurrencyMode, DEFAULT_MAX_FILE_SIZE, EncodingMode, ScanConfig};
+pub use config_file::{
+    CONFIG_FILE_NAME, ConfigFileError, ConfigSettings, ConfigValue, DISCOVERED_OPTIONS,
+    PYPROJECT_TABLE, load_config_files, parse_config_table,
+};
+pub use csv::{
+    CURRENT_FORMAT_VERSION, CsvColumnSpec, CsvError, CsvOptions, DedupStrategy, HeaderMode,
+    MODELS_CSV_HEADER, csv_header, deduplicate_csv, format_csv_field,
+    format_csv_field_with_separator, migrate_csv, parse_csv_columns, parse_csv_records, verify_csv,
+    write_file_csv, write_models_csv,
+};
+pub use cwe::{CweId, FindingModifier, annotate_cwe, cwe_for_status};
+pub use cyclonedx::{CYCLONEDX_SPEC_VERSION, CycloneDxBom, cyclonedx_bom, write_cyclonedx};
+pub use directory::{
+    EXCLUDED_DIRS, FileRow, IO_THREADS, PatternCounts, PhaseTimings, Project, ScanResults,
+    ScanStats, check_thresholds, get_org_repo, is_scanned_file, scan_directories, scan_directory,
+    scan_git_index,
+};
+pub use format::format_count;
+pub use git_index::{
+    StagedFile, read_blob, scan_file_from_git, staged_files, staged_files_matching,
+};
+pub use gitignore::Gitignore;
+pub use graph::{ImportGraph, build_import_graph, python_imports};
+pub use json::{JSON_SCHEMA_VERSION, write_file_json, write_models_json};
+pub use models::{
+    DuplicateModel, ModelLoad, ModelRegistry, ModelReuse, RegistryEntry, anonymise_model_names,
+    detect_reuse, find_duplicates, group_by_model,
+};
+pub use org_report::{TOP_RISKIEST, letter_grade, render_org_risk_report, riskiest_projects};
+pub use pattern_db::{
+    PatternDbError, format_pattern_table, load_pattern_db, parse_extra_pattern, patterns_to_json,
+};
+pub use remediation::{COMMIT_SHA_PLACEHOLDER, FIX_PR_TITLE, remediation_hint, render_fix_pr_body};
+pub use requirements::{
+    HF_PACKAGES, RequirementFinding, scan_requirements_file, scan_requirements_txt,
+};
+pub use risk::{RiskWeights, risk_score};
+pub use scanner::{
+    DEFAULT_GENERATED_MARKERS, FileScan, Finding, JOBLIB_LOAD, LOCAL_FILE_LOAD_PATTERNS,
+    METADATA_LOAD, ModelName, Pattern, Status, SuppressionReason, TORCH_LOAD, active_patterns,
+    builtin_patterns, count_usages, decode_source, excludes_weight_files, extract_model_name,
+    extract_revision, has_suspicious_sha, is_abbreviated_sha, is_commit_sha,
+    is_plausible_commit_sha, parse_version, patterns_added_since, read_source, safe_revision_regex,
+    scan_code, scan_code_for_usage, scan_content, scan_file, scan_shell_script,
+    uses_shared_cache_dir, uses_traversal_subfolder, uses_trust_remote_code,
+};
+pub use streaming::{DEFAULT_WINDOW_LINES, StreamingScan, scan_code_streaming};
+pub use vex::{OPENVEX_CONTEXT, VEX_VULNERABILITY, VexDocument, openvex_document};
@@ -1,500 +1,1309 @@
-use rayon::prelude::*;
-use regex::Regex;
-use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::{BufWriter, Write};
+use hfscanner::config_file::{ConfigSettings, ConfigValue, load_config_files};
+use hfscanner::github::{self, PullRequest};
+use hfscanner::metrics::{self, MetricsSnapshot};
+use hfscanner::{
+    CURRENT_FORMAT_VERSION, ConcurrencyMode, CsvColumnSpec, CsvOptions, DEFAULT_MAX_FILE_SIZE,
+    DedupStrategy, EXCLUDED_DIRS, EncodingMode, HeaderMode, JSON_SCHEMA_VERSION, ModelRegistry,
+    RiskWeights, ScanCache, ScanConfig, ScanResults, Status, active_patterns, annotate_cwe,
+    anonymise_model_names, build_import_graph, check_thresholds, csv_header, deduplicate_csv,
+    detect_reuse, find_duplicates, format_count, format_pattern_table, github_annotations,
+    group_by_model, has_suspicious_sha, load_pattern_db, openvex_document, parse_csv_columns,
+    parse_extra_pattern, patterns_added_since, patterns_to_json, remediation_hint,
+    render_fix_pr_body, render_org_risk_report, safe_revision_regex, scan_directories,
+    scan_directory, scan_git_index, verify_csv, write_cyclonedx, write_file_csv, write_file_json,
+    write_models_csv, write_models_json,
+};
+use std::io::IsTerminal;
+use std::net::TcpListener;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::{Arc, Mutex};
-use walkdir::WalkDir;
-
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-enum Status {
-    Safe,
-    PartiallySafe,
-    Unsafe,
-}
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{Level, debug, error};
+
+/// Flags followed by a value, which must not be taken for a root directory.
+const VALUE_FLAGS: &[&str] = &[
+    "--csv",
+    "--append-csv",
+    "--format-version",
+    "--csv-columns",
+    "--csv-separator",
+    "--json",
+    "--export-graph",
+    "--schema-version",
+    "--include-context",
+    "--pattern-db",
+    "--extra-pattern",
+    "--revision-pattern",
+    "--generated-marker",
+    "--scan-ext",
+    "--dup-threshold",
+    "--fail-on",
+    "--fail-on-score",
+    "--severity-threshold",
+    "--fail-on-new-patterns-since",
+    "--cache-dir",
+    "--emit-registry",
+    "--emit-fix-pr-body",
+    "--emit-attestation",
+    "--cyclonedx",
+    "--organisation-risk-report",
+    "--perf-profile-json",
+    "--output-format",
+    "--slow-file-threshold",
+    "--concurrency-mode",
+    "--encoding-errors",
+    "--min-file-size",
+    "--max-file-size",
+    "--github-repo",
+    "--github-pr-number",
+    "--github-token",
+    "--github-api-url",
+    "--report-title",
+    "--baseline",
+    "--metrics-server",
+    "--metrics-interval",
+    "--suppress-if-private-repo",
+    "--config",
+];
 
-const EXCLUDED_DIRS: &[&str] = &[
-    ".git",
-    "node_modules",
-    "__pycache__",
-    ".mypy_cache",
-    ".venv",
-    "venv",
-    ".env",
+/// Flags without a value, which a config file sets with `true`.
+const BOOLEAN_FLAGS: &[&str] = &[
+    "--accept-short-sha",
+    "--anonymise-models",
+    "--ci-mode",
+    "--csv-header-only",
+    "--csv-no-header",
+    "--detailed",
+    "--detect-reuse",
+    "--emit-cwe",
+    "--emit-line-annotations",
+    "--find-duplicates",
+    "--flag-suspicious-sha",
+    "--format-numbers",
+    "--github-pr-comment",
+    "--group-by-model",
+    "--include-safe-in-csv",
+    "--lazy-walk",
+    "--no-builtin-patterns",
+    "--no-ci-mode",
+    "--no-line-annotations",
+    "--parallel-io",
+    "--per-file-timing",
+    "--print-patterns",
+    "--quiet",
+    "--remediation-hint",
+    "--respect-gitignore",
+    "--scan-depth-report",
+    "--scan-git-index",
+    "--skip-generated",
+    "--summary",
+    "--verbose",
 ];
 
-fn is_commit_sha(s: &str) -> bool {
-    let sha_re = Regex::new(r"^[a-f0-9]{40}$").unwrap();
-    sha_re.is_match(s)
+/// Positional arguments, i.e. everything that is neither a flag nor a flag's value.
+fn root_dirs(args: &[String]) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with("--") {
+            roots.push(PathBuf::from(arg));
+        }
+    }
+    roots
 }
 
-fn scan_code_for_usage(code: &str) -> (usize, usize, usize) {
-    let use_auth_or_local_re =
-        Regex::new(r#"use_auth_token\s*=\s*True|from_pretrained\(["'](\./|/)"#).unwrap();
-    let revision_capture_re = Regex::new(r#"revision\s*=\s*["']([^"']+)["']"#).unwrap();
-
-    let patterns = vec![
-        Regex::new(r#"AutoModel\.from_pretrained\s*\((?s:.*?)\)"#).unwrap(),
-        Regex::new(r#"AutoTokenizer\.from_pretrained\s*\((?s:.*?)\)"#).unwrap(),
-        Regex::new(r#"load_dataset\s*\((?s:.*?)\)"#).unwrap(),
-        Regex::new(r#"hf_hub_download\s*\((?s:.*?)\)"#).unwrap(),
-        Regex::new(r#"snapshot_download\s*\((?s:.*?)\)"#).unwrap(),
-    ];
-
-    let mut safe_count = 0;
-    let mut partial_count = 0;
-    let mut unsafe_count = 0;
-
-    for pattern in &patterns {
-        for caps in pattern.captures_iter(code) {
-            let full_call = caps.get(0).map_or("", |m| m.as_str());
-
-            if use_auth_or_local_re.is_match(full_call) {
-                safe_count += 1;
-                continue;
-            }
+/// Flags that set the same option in different ways. A config file option is dropped when
+/// any flag of its group is given on the command line.
+const EXCLUSIVE_FLAGS: &[&[&str]] = &[
+    &["--summary", "--detailed"],
+    &["--ci-mode", "--no-ci-mode"],
+    &["--emit-line-annotations", "--no-line-annotations"],
+    &["--csv", "--append-csv"],
+    &["--csv-no-header", "--csv-header-only"],
+    &["--fail-on-score", "--severity-threshold"],
+];
 
-            if let Some(rev_caps) = revision_capture_re.captures(full_call) {
-                let val = &rev_caps[1];
-                if is_commit_sha(val) {
-                    safe_count += 1;
-                } else {
-                    partial_count += 1;
+/// `args` with the options from `settings` appended, except those also given on the
+/// command line, directly or through another flag of their [`EXCLUSIVE_FLAGS`] group,
+/// which take precedence. `true` becomes a bare flag and `false` is dropped; other values
+/// go to flags taking a value, once per element for arrays. Keys naming no option are
+/// rejected, so a misspelt option is not silently ignored.
+fn with_config_args(
+    mut args: Vec<String>,
+    settings: &ConfigSettings,
+) -> Result<Vec<String>, String> {
+    let on_command_line = |flag: &str| args.iter().any(|arg| arg == flag);
+    let mut extra = Vec::new();
+    for (key, value) in settings {
+        let flag = format!("--{key}");
+        if flag == "--config"
+            || !(VALUE_FLAGS.contains(&flag.as_str()) || BOOLEAN_FLAGS.contains(&flag.as_str()))
+        {
+            return Err(format!("unknown option '{key}'"));
+        }
+        let overridden = on_command_line(&flag)
+            || EXCLUSIVE_FLAGS
+                .iter()
+                .filter(|group| group.contains(&flag.as_str()))
+                .any(|group| group.iter().any(|other| on_command_line(other)));
+        if overridden {
+            continue;
+        }
+        let takes_value = VALUE_FLAGS.contains(&flag.as_str());
+        let values = match value {
+            ConfigValue::Array(values) => values.clone(),
+            value => vec![value.clone()],
+        };
+        for value in values {
+            match value {
+                ConfigValue::Bool(true) if !takes_value => extra.push(flag.clone()),
+                ConfigValue::Bool(false) if !takes_value => {}
+                ConfigValue::String(_) | ConfigValue::Integer(_) | ConfigValue::Float(_)
+                    if takes_value =>
+                {
+                    extra.push(flag.clone());
+                    extra.push(value.to_string());
                 }
-            } else {
-                unsafe_count += 1;
+                _ if takes_value => return Err(format!("{key} expects a string or number")),
+                _ => return Err(format!("{key} expects true or false")),
             }
         }
     }
-
-    (safe_count, partial_count, unsafe_count)
+    args.extend(extra);
+    Ok(args)
 }
 
-fn scan_file(path: &Path) -> (usize, usize, usize) {
-    let content = match fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(_) => return (0, 0, 0),
+/// `org/repo` names for `--suppress-if-private-repo <source>`: the lines of a file, or with
+/// `github` the private repositories of every org directory under `roots`, from the API.
+fn private_repo_list(
+    source: &str,
+    roots: &[PathBuf],
+    args: &[String],
+) -> Result<Vec<String>, String> {
+    if source != "github" {
+        let list = std::fs::read_to_string(source).map_err(|e| format!("{source}: {e}"))?;
+        return Ok(list
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect());
+    }
+    let flag_value = |flag: &str| {
+        let index = args.iter().position(|x| x == flag);
+        index.and_then(|i| args.get(i + 1)).cloned()
     };
-    scan_code_for_usage(&content)
+    let token = flag_value("--github-token")
+        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+        .ok_or("the GitHub lookup requires --github-token or GITHUB_TOKEN")?;
+    let api_url = flag_value("--github-api-url").unwrap_or(github::DEFAULT_API_URL.to_string());
+    let mut repos = Vec::new();
+    for root in roots {
+        let entries = std::fs::read_dir(root).map_err(|e| format!("{}: {e}", root.display()))?;
+        for entry in entries.flatten() {
+            let org = entry.file_name().to_string_lossy().to_string();
+            if !entry.path().is_dir()
+                || org.starts_with('.')
+                || EXCLUDED_DIRS.contains(&org.as_str())
+            {
+                continue;
+            }
+            repos.extend(github::private_repos(&api_url, &token, &org).map_err(|e| e.to_string())?);
+        }
+    }
+    Ok(repos)
 }
 
-fn is_excluded(entry: &walkdir::DirEntry) -> bool {
-    entry.file_type().is_dir()
-        && EXCLUDED_DIRS
-            .iter()
-            .any(|&e| entry.file_name().to_string_lossy().contains(e))
-}
+/// `hfscanner dedup <input.csv> [--out <output.csv>] [--strategy <strategy>]`
+fn run_dedup(args: &[String]) -> ExitCode {
+    let Some(input_path) = args.get(2).filter(|arg| !arg.starts_with("--")) else {
+        eprintln!(
+            "Usage: {} dedup <input.csv> [--out <output.csv>] [--strategy keep-first|keep-last|sum|max]",
+            args[0]
+        );
+        return ExitCode::FAILURE;
+    };
+    let strategy_index = args.iter().position(|x| x == "--strategy");
+    let strategy = match strategy_index.map(|i| args.get(i + 1).map(String::as_str)) {
+        None | Some(Some("keep-last")) => DedupStrategy::KeepLast,
+        Some(Some("keep-first")) => DedupStrategy::KeepFirst,
+        Some(Some("sum")) => DedupStrategy::SumCounts,
+        Some(Some("max")) => DedupStrategy::MaxCounts,
+        Some(other) => {
+            error!(
+                "Invalid --strategy {:?} (expected keep-first, keep-last, sum or max)",
+                other.unwrap_or_default()
+            );
+            return ExitCode::FAILURE;
+        }
+    };
 
-fn format_csv_field(field: &str) -> String {
-    // Quote the field if it contains commas, quotes, or newlines
-    if field.contains(',') || field.contains('"') || field.contains('\n') {
-        format!("\"{}\"", field.replace('"', "\"\""))
-    } else {
-        field.to_string()
+    let input = match std::fs::read_to_string(input_path) {
+        Ok(input) => input,
+        Err(e) => {
+            error!("Failed to read {input_path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let output = deduplicate_csv(&input, strategy);
+
+    let out_index = args.iter().position(|x| x == "--out");
+    match out_index.and_then(|i| args.get(i + 1)) {
+        Some(out_path) => {
+            if let Err(e) = std::fs::write(out_path, output) {
+                error!("Failed to write {out_path}: {e}");
+                return ExitCode::FAILURE;
+            }
+            println!("Deduplicated CSV written to: {out_path}");
+        }
+        None => print!("{output}"),
     }
+    ExitCode::SUCCESS
 }
 
-/// Extract (org, repo) from a path like `root/org/repo/file.py`
-fn get_org_repo(path: &Path, root: &Path) -> (String, String) {
-    let rel_components = match path.strip_prefix(root) {
-        Ok(rel) => rel
-            .components()
-            .map(|c| c.as_os_str().to_string_lossy().to_string())
-            .collect::<Vec<_>>(),
-        Err(_) => return ("unknown".to_string(), "unknown".to_string()),
+/// `hfscanner --verify-csv <file> [--format-version <n>]`
+fn run_verify_csv(args: &[String], index: usize) -> ExitCode {
+    let Some(path) = args.get(index + 1) else {
+        eprintln!(
+            "Usage: {} --verify-csv <file> [--format-version <n>]",
+            args[0]
+        );
+        return ExitCode::FAILURE;
+    };
+    let format_version_index = args.iter().position(|x| x == "--format-version");
+    let format_version = match format_version_index.map(|i| args.get(i + 1)) {
+        None => CURRENT_FORMAT_VERSION,
+        Some(value) => match value.and_then(|v| v.parse::<u8>().ok()) {
+            Some(version) => version,
+            None => {
+                error!(
+                    "Invalid --format-version {:?}",
+                    value.map(String::as_str).unwrap_or_default()
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("Failed to read {path}: {e}");
+            return ExitCode::FAILURE;
+        }
     };
 
-    if rel_components.len() < 3 {
-        return ("unknown".to_string(), "unknown".to_string());
+    let errors = verify_csv(&content, format_version);
+    if errors.is_empty() {
+        println!("{path}: valid CSV format version {format_version}");
+        return ExitCode::SUCCESS;
     }
-
-    (rel_components[0].clone(), rel_components[1].clone())
+    for (i, error) in errors.iter().enumerate() {
+        println!("{}. {error}", i + 1);
+    }
+    println!("{path}: {} issue(s) found", errors.len());
+    ExitCode::FAILURE
 }
 
-fn write_file_csv(
-    output_path: &str,
-    file_data: &[(String, String, String, usize, usize, usize)],
-) -> std::io::Result<()> {
-    let file = File::create(output_path)?;
-    let mut writer = BufWriter::new(file);
-    writeln!(
-        writer,
-        "org,repo,file,safe_usages,partial_usages,unsafe_usages"
-    )?;
-    for (org, repo, file_path, safe, partial, unsafe_) in file_data {
-        let formatted_org = format_csv_field(org);
-        let formatted_repo = format_csv_field(repo);
-        let formatted_file = format_csv_field(file_path);
-        writeln!(
-            writer,
-            "{formatted_org},{formatted_repo},{formatted_file},{safe},{partial},{unsafe_}",
-        )?;
-    }
-    Ok(())
+/// Log to stderr, at debug level with `--verbose` and only errors otherwise.
+fn init_tracing(args: &[String]) {
+    let log_level = if args.contains(&"--verbose".to_string()) {
+        Level::DEBUG
+    } else {
+        Level::ERROR
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(log_level)
+        .with_writer(std::io::stderr)
+        .init();
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() < 3 {
+    if args
+        .iter()
+        .skip(1)
+        .any(|arg| arg == "--version" || arg == "-V")
+    {
+        println!("hfscanner {}", env!("CARGO_PKG_VERSION"));
+        return ExitCode::SUCCESS;
+    }
+    if args.get(1).is_some_and(|arg| arg == "dedup") {
+        init_tracing(&args);
+        return run_dedup(&args);
+    }
+    if let Some(index) = args.iter().position(|x| x == "--verify-csv") {
+        init_tracing(&args);
+        return run_verify_csv(&args, index);
+    }
+
+    // `.silentinjection.toml` and `pyproject.toml` are looked up in the working directory,
+    // never in the scanned tree, which may be untrusted. Logging starts once they are
+    // merged, so they can set `verbose`.
+    let config_index = args.iter().position(|x| x == "--config");
+    let explicit_config = config_index
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from);
+    let merged = load_config_files(Path::new("."), explicit_config.as_deref())
+        .map_err(|e| e.to_string())
+        .and_then(|settings| with_config_args(args.clone(), &settings));
+    init_tracing(merged.as_ref().unwrap_or(&args));
+    let args = match merged {
+        Ok(args) => args,
+        Err(e) => {
+            error!("Invalid configuration: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let detailed = args.contains(&"--detailed".to_string());
+    // On by default in GitHub Actions. Flags given alongside still take precedence.
+    let ci_mode = !args.contains(&"--no-ci-mode".to_string())
+        && (args.contains(&"--ci-mode".to_string())
+            || std::env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true"));
+    let quiet = args.contains(&"--quiet".to_string())
+        || (ci_mode && !detailed && !args.contains(&"--summary".to_string()));
+    let pattern_db_index = args.iter().position(|x| x == "--pattern-db");
+    let mut extra_patterns = match pattern_db_index.and_then(|i| args.get(i + 1)) {
+        Some(db_path) => match load_pattern_db(Path::new(db_path)) {
+            Ok(patterns) => patterns,
+            Err(e) => {
+                error!("Failed to load pattern database {db_path}: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Vec::new(),
+    };
+    for spec in args
+        .windows(2)
+        .filter(|pair| pair[0] == "--extra-pattern")
+        .map(|pair| &pair[1])
+    {
+        match parse_extra_pattern(spec) {
+            Ok(pattern) => extra_patterns.push(pattern),
+            Err(e) => {
+                error!("Invalid --extra-pattern: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if args.contains(&"--print-patterns".to_string()) {
+        let config = ScanConfig {
+            extra_patterns,
+            no_builtin_patterns: args.contains(&"--no-builtin-patterns".to_string()),
+            ..Default::default()
+        };
+        let patterns = active_patterns(&config);
+        let output_format_index = args.iter().position(|x| x == "--output-format");
+        match output_format_index.map(|i| args.get(i + 1).map(String::as_str)) {
+            None | Some(Some("text")) => print!("{}", format_pattern_table(&patterns)),
+            Some(Some("json")) => println!("{}", patterns_to_json(&patterns)),
+            Some(other) => {
+                error!(
+                    "Invalid --output-format {:?} (expected text or json)",
+                    other.unwrap_or_default()
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let root_dirs = root_dirs(&args);
+    if root_dirs.is_empty() {
         eprintln!(
-            "Usage: {} <root_dir> [--summary | --detailed] [--csv <file>]",
+            "Usage: {} <root_dir>... [--config <toml-file>] [--summary | --detailed] [--quiet] [--ci-mode | --no-ci-mode] [--csv <file> | --append-csv <file>] [--csv-no-header | --csv-header-only] [--format-version <n>] [--csv-columns <name,...>] [--csv-separator <char>] [--json <file> [--schema-version <n>]] [--export-graph <dot-file>] [--emit-registry <json-file>] [--emit-fix-pr-body <md-file>] [--emit-attestation <vex-file>] [--cyclonedx <file>] [--organisation-risk-report <md-file> [--baseline <dir>]] [--group-by-model] [--include-safe-in-csv] [--include-context <n>] [--find-duplicates [--dup-threshold <n>]] [--detect-reuse] [--scan-ext <ext,...>] [--accept-short-sha] [--flag-suspicious-sha] [--suppress-if-private-repo <file|github>] [--revision-pattern <regex>]... [--pattern-db <file>] [--extra-pattern <name>=<regex>]... [--no-builtin-patterns] [--print-patterns [--output-format <text|json>]] [--emit-line-annotations | --no-line-annotations] [--fail-on <partial|unsafe>] [--fail-on-score <n> | --severity-threshold <n>] [--fail-on-new-patterns-since <version>] [--per-file-timing [--slow-file-threshold <secs>]] [--perf-profile-json <file>] [--concurrency-mode <rayon|tokio>] [--encoding-errors <strict|replace>] [--lazy-walk] [--parallel-io] [--scan-git-index] [--cache-dir <dir>] [--scan-depth-report] [--skip-generated [--generated-marker <regex>]...] [--respect-gitignore] [--min-file-size <bytes>] [--max-file-size <bytes>] [--github-pr-comment --github-repo <owner/repo> --github-pr-number <n> [--github-token <token>] [--baseline <dir>] [--report-title <title>]] [--metrics-server <addr> [--metrics-interval <secs>]] [--format-numbers] [--anonymise-models] [--emit-cwe] [--remediation-hint] [--verbose] [--version]",
             args[0]
         );
-        return;
+        return ExitCode::FAILURE;
     }
 
-    let root_dir = PathBuf::from(&args[1]);
-    let detailed = args.contains(&"--detailed".to_string());
+    let context_index = args.iter().position(|x| x == "--include-context");
+    let context_lines = match context_index.map(|i| args.get(i + 1)) {
+        None => None,
+        Some(value) => match value.and_then(|v| v.parse::<usize>().ok()) {
+            Some(n) => Some(n),
+            None => {
+                error!(
+                    "Invalid --include-context {:?} (expected a number of lines)",
+                    value.map(String::as_str).unwrap_or_default()
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let mut safe_revision_patterns = Vec::new();
+    for pattern in args
+        .windows(2)
+        .filter(|pair| pair[0] == "--revision-pattern")
+        .map(|pair| &pair[1])
+    {
+        match safe_revision_regex(pattern) {
+            Ok(regex) => safe_revision_patterns.push(regex),
+            Err(e) => {
+                error!("Invalid --revision-pattern {pattern:?}: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let mut generated_markers = Vec::new();
+    for marker in args
+        .windows(2)
+        .filter(|pair| pair[0] == "--generated-marker")
+        .map(|pair| &pair[1])
+    {
+        match regex::Regex::new(marker) {
+            Ok(regex) => generated_markers.push(regex),
+            Err(e) => {
+                error!("Invalid --generated-marker {marker:?}: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let scan_extensions: Vec<String> = args
+        .iter()
+        .position(|x| x == "--scan-ext")
+        .and_then(|i| args.get(i + 1))
+        .map(|exts| {
+            exts.split(',')
+                .map(|ext| ext.trim().trim_start_matches('.').to_string())
+                .filter(|ext| !ext.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let concurrency_index = args.iter().position(|x| x == "--concurrency-mode");
+    let concurrency_mode = match concurrency_index.map(|i| args.get(i + 1).map(String::as_str)) {
+        None | Some(Some("rayon")) => ConcurrencyMode::Rayon,
+        Some(Some("tokio")) => ConcurrencyMode::Tokio,
+        Some(other) => {
+            error!(
+                "Invalid --concurrency-mode {:?} (expected rayon or tokio)",
+                other.unwrap_or_default()
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let encoding_index = args.iter().position(|x| x == "--encoding-errors");
+    let encoding_errors = match encoding_index.map(|i| args.get(i + 1).map(String::as_str)) {
+        None | Some(Some("strict")) => EncodingMode::Strict,
+        Some(Some("replace")) => EncodingMode::Replace,
+        Some(other) => {
+            error!(
+                "Invalid --encoding-errors {:?} (expected strict or replace)",
+                other.unwrap_or_default()
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let size_flag = |flag: &str, default: u64| {
+        let index = args.iter().position(|x| x == flag);
+        match index.map(|i| args.get(i + 1)) {
+            None => Some(default),
+            Some(value) => value.and_then(|v| v.parse::<u64>().ok()),
+        }
+    };
+    let Some(min_file_size) = size_flag("--min-file-size", 0) else {
+        error!("Invalid --min-file-size (expected a number of bytes)");
+        return ExitCode::FAILURE;
+    };
+    let Some(max_file_size) = size_flag("--max-file-size", DEFAULT_MAX_FILE_SIZE) else {
+        error!("Invalid --max-file-size (expected a number of bytes)");
+        return ExitCode::FAILURE;
+    };
+    let fail_on_index = args.iter().position(|x| x == "--fail-on");
+    let fail_on = match fail_on_index.map(|i| args.get(i + 1).map(String::as_str)) {
+        None => ci_mode.then_some(Status::Unsafe),
+        Some(Some("partial")) => Some(Status::PartiallySafe),
+        Some(Some("unsafe")) => Some(Status::Unsafe),
+        Some(other) => {
+            error!(
+                "Invalid --fail-on level {:?} (expected partial or unsafe)",
+                other.unwrap_or_default()
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    // `--severity-threshold` is another name for `--fail-on-score`; the lower one wins.
+    let mut fail_on_score: Option<f64> = None;
+    for flag in ["--fail-on-score", "--severity-threshold"] {
+        let index = args.iter().position(|x| x == flag);
+        if let Some(value) = index.map(|i| args.get(i + 1)) {
+            let Some(threshold) = value.and_then(|v| v.parse::<f64>().ok()) else {
+                error!(
+                    "Invalid {flag} {:?} (expected a number)",
+                    value.map(String::as_str).unwrap_or_default()
+                );
+                return ExitCode::FAILURE;
+            };
+            fail_on_score = Some(fail_on_score.map_or(threshold, |t| t.min(threshold)));
+        }
+    }
+    let new_patterns_index = args
+        .iter()
+        .position(|x| x == "--fail-on-new-patterns-since");
+    let fail_on_new_patterns_since = match new_patterns_index.map(|i| args.get(i + 1)) {
+        None => None,
+        Some(Some(version)) if patterns_added_since(version).is_some() => Some(version.clone()),
+        Some(value) => {
+            error!(
+                "Invalid --fail-on-new-patterns-since {:?} (expected a version such as 0.1.0)",
+                value.map(String::as_str).unwrap_or_default()
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let private_index = args.iter().position(|x| x == "--suppress-if-private-repo");
+    let private_repos = match private_index.map(|i| args.get(i + 1)) {
+        None => Vec::new(),
+        Some(Some(source)) => match private_repo_list(source, &root_dirs, &args) {
+            Ok(repos) => repos,
+            Err(e) => {
+                error!("Failed to read --suppress-if-private-repo list: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        Some(None) => {
+            error!("--suppress-if-private-repo requires a file of org/repo names or github");
+            return ExitCode::FAILURE;
+        }
+    };
+    let perf_profile_index = args.iter().position(|x| x == "--perf-profile-json");
+    let perf_profile_file = perf_profile_index.and_then(|i| args.get(i + 1));
+    let mut config = ScanConfig {
+        include_safe_in_csv: args.contains(&"--include-safe-in-csv".to_string()),
+        accept_short_sha: args.contains(&"--accept-short-sha".to_string()),
+        flag_suspicious_shas: args.contains(&"--flag-suspicious-sha".to_string()),
+        scan_extensions,
+        safe_revision_patterns,
+        extra_patterns,
+        no_builtin_patterns: args.contains(&"--no-builtin-patterns".to_string()),
+        context_lines,
+        per_file_timing: args.contains(&"--per-file-timing".to_string()),
+        perf_profile: perf_profile_file.is_some(),
+        concurrency_mode,
+        encoding_errors,
+        lazy_walk: args.contains(&"--lazy-walk".to_string()),
+        parallel_io: args.contains(&"--parallel-io".to_string()),
+        #[cfg(feature = "bench")]
+        read_latency: None,
+        skip_generated: args.contains(&"--skip-generated".to_string()),
+        generated_markers,
+        respect_gitignore: args.contains(&"--respect-gitignore".to_string()),
+        min_file_size,
+        max_file_size: Some(max_file_size),
+        risk_weights: RiskWeights::default(),
+        private_repos,
+        cache: None,
+        fail_on,
+        fail_on_score,
+        fail_on_new_patterns_since,
+    };
+    let cache_dir_index = args.iter().position(|x| x == "--cache-dir");
+    if let Some(cache_dir) = cache_dir_index.and_then(|i| args.get(i + 1)) {
+        match ScanCache::open(Path::new(cache_dir), &config) {
+            Ok(cache) => config.cache = Some(Arc::new(cache)),
+            Err(e) => {
+                error!("Failed to open --cache-dir {cache_dir}: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
     let csv_index = args.iter().position(|x| x == "--csv");
     let csv_output = csv_index.and_then(|i| args.get(i + 1));
-
-    let file_paths: Vec<_> = WalkDir::new(&root_dir)
-        .into_iter()
-        .filter_entry(|e| !is_excluded(e))
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "py"))
-        .collect();
-
-    let total_safe = Arc::new(Mutex::new(0));
-    let total_partial = Arc::new(Mutex::new(0));
-    let total_unsafe = Arc::new(Mutex::new(0));
-    let project_statuses = Arc::new(Mutex::new(HashMap::<(String, String), Status>::new()));
-    let file_rows = Arc::new(Mutex::new(Vec::<(
-        String,
-        String,
-        String,
-        usize,
-        usize,
-        usize,
-    )>::new()));
-
-    file_paths.par_iter().for_each(|entry| {
-        let path = entry.path();
-        let (safe, partial, unsafe_) = scan_file(path);
-
-        if safe == 0 && partial == 0 && unsafe_ == 0 {
-            return;
-        }
-
-        let (org, repo) = get_org_repo(path, &root_dir);
-        let file_rel = path
-            .strip_prefix(&root_dir)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .to_string();
-
-        file_rows.lock().unwrap().push((
-            org.clone(),
-            repo.clone(),
-            file_rel,
-            safe,
-            partial,
-            unsafe_,
-        ));
-
-        *total_safe.lock().unwrap() += safe;
-        *total_partial.lock().unwrap() += partial;
-        *total_unsafe.lock().unwrap() += unsafe_;
-
-        let mut statuses = project_statuses.lock().unwrap();
-        let key = (org.clone(), repo.clone());
-        let current = statuses.get(&key).cloned();
-
-        let new_status = if unsafe_ > 0 {
-            Status::Unsafe
-        } else if partial > 0 {
-            Status::PartiallySafe
+    let append_csv_index = args.iter().position(|x| x == "--append-csv");
+    let append_csv_output = append_csv_index.and_then(|i| args.get(i + 1));
+    let csv_no_header = args.contains(&"--csv-no-header".to_string());
+    let csv_header_only = args.contains(&"--csv-header-only".to_string());
+    if csv_no_header && csv_header_only {
+        error!("--csv-no-header and --csv-header-only are mutually exclusive");
+        return ExitCode::FAILURE;
+    }
+    let format_version_index = args.iter().position(|x| x == "--format-version");
+    let format_version = match format_version_index.map(|i| args.get(i + 1)) {
+        None => CURRENT_FORMAT_VERSION,
+        Some(value) => match value.and_then(|v| v.parse::<u8>().ok()) {
+            Some(version) if csv_header(version).is_some() => version,
+            _ => {
+                error!(
+                    "Invalid --format-version {:?} (expected 1 to {CURRENT_FORMAT_VERSION})",
+                    value.map(String::as_str).unwrap_or_default()
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let csv_columns_index = args.iter().position(|x| x == "--csv-columns");
+    let csv_columns = match csv_columns_index.map(|i| args.get(i + 1)) {
+        None => None,
+        Some(value) => match value.map(|v| parse_csv_columns(v)) {
+            Some(Ok(columns)) => Some(columns),
+            Some(Err(name)) => {
+                let valid: Vec<&str> = CsvColumnSpec::ALL.iter().map(|c| c.name()).collect();
+                error!(
+                    "Unknown --csv-columns column {name:?} (expected {})",
+                    valid.join(", ")
+                );
+                return ExitCode::FAILURE;
+            }
+            None => {
+                error!("--csv-columns requires a comma-separated list of columns");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let csv_separator = match args
+        .iter()
+        .position(|x| x == "--csv-separator")
+        .map(|i| args.get(i + 1))
+    {
+        None => ',',
+        Some(value) => {
+            let value = value.map(String::as_str).unwrap_or_default();
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c != '"' && c != '\n' && c != '\r' => c,
+                _ => {
+                    error!(
+                        "Invalid --csv-separator {value:?} (expected a single character other than a quote or newline)"
+                    );
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    };
+    let csv_options = CsvOptions {
+        header: if csv_no_header {
+            HeaderMode::Omit
+        } else if csv_header_only {
+            HeaderMode::Only
         } else {
-            Status::Safe
+            HeaderMode::Include
+        },
+        append: false,
+        format_version,
+        columns: csv_columns,
+        separator: csv_separator,
+    };
+    let json_index = args.iter().position(|x| x == "--json");
+    let json_output = json_index.and_then(|i| args.get(i + 1));
+    let schema_version_index = args.iter().position(|x| x == "--schema-version");
+    let schema_version = match schema_version_index.map(|i| args.get(i + 1)) {
+        None => JSON_SCHEMA_VERSION,
+        Some(value) => match value.and_then(|v| v.parse::<u8>().ok()) {
+            Some(version) if version == JSON_SCHEMA_VERSION => version,
+            _ => {
+                error!(
+                    "Invalid --schema-version {:?} (expected {JSON_SCHEMA_VERSION})",
+                    value.map(String::as_str).unwrap_or_default()
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    // `ci_mode` already covers GITHUB_ACTIONS, so `--no-ci-mode` turns the default off too.
+    let emit_annotations = !args.contains(&"--no-line-annotations".to_string())
+        && (args.contains(&"--emit-line-annotations".to_string()) || ci_mode);
+    let find_dups = args.contains(&"--find-duplicates".to_string());
+    let dup_threshold_index = args.iter().position(|x| x == "--dup-threshold");
+    let dup_threshold = match dup_threshold_index.map(|i| args.get(i + 1)) {
+        None => 3,
+        Some(value) => match value.and_then(|v| v.parse::<usize>().ok()) {
+            Some(n) => n,
+            None => {
+                error!(
+                    "Invalid --dup-threshold {:?} (expected a number of files)",
+                    value.map(String::as_str).unwrap_or_default()
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    let github_pr = if args.contains(&"--github-pr-comment".to_string()) {
+        let flag_value = |flag: &str| {
+            let index = args.iter().position(|x| x == flag);
+            index.and_then(|i| args.get(i + 1)).cloned()
         };
-
-        let final_status = match (current, new_status) {
-            (Some(Status::Unsafe), _) => Status::Unsafe,
-            (_, Status::Unsafe) => Status::Unsafe,
-            (Some(Status::PartiallySafe), _) => Status::PartiallySafe,
-            (_, Status::PartiallySafe) => Status::PartiallySafe,
-            _ => Status::Safe,
+        let pr = flag_value("--github-repo")
+            .zip(flag_value("--github-pr-number"))
+            .and_then(|(repo, number)| PullRequest::parse(&repo, &number));
+        let Some(pr) = pr else {
+            error!(
+                "--github-pr-comment requires --github-repo <owner/repo> and --github-pr-number <n>"
+            );
+            return ExitCode::FAILURE;
         };
+        let Some(token) =
+            flag_value("--github-token").or_else(|| std::env::var("GITHUB_TOKEN").ok())
+        else {
+            error!("--github-pr-comment requires --github-token or GITHUB_TOKEN");
+            return ExitCode::FAILURE;
+        };
+        let api_url = flag_value("--github-api-url").unwrap_or(github::DEFAULT_API_URL.to_string());
+        let baseline = flag_value("--baseline").map(PathBuf::from);
+        Some((pr, token, api_url, baseline))
+    } else {
+        None
+    };
+    let slow_threshold_index = args.iter().position(|x| x == "--slow-file-threshold");
+    let slow_threshold = match slow_threshold_index.map(|i| args.get(i + 1)) {
+        None => Duration::from_secs(1),
+        Some(value) => {
+            match value.and_then(|v| Duration::try_from_secs_f64(v.parse().ok()?).ok()) {
+                Some(threshold) => threshold,
+                None => {
+                    error!(
+                        "Invalid --slow-file-threshold {:?} (expected a number of seconds)",
+                        value.map(String::as_str).unwrap_or_default()
+                    );
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    };
+    let metrics_interval_index = args.iter().position(|x| x == "--metrics-interval");
+    let metrics_interval = match metrics_interval_index.map(|i| args.get(i + 1)) {
+        None => DEFAULT_METRICS_INTERVAL,
+        Some(value) => match value.and_then(|v| v.parse().ok()).filter(|&secs| secs > 0) {
+            Some(secs) => Duration::from_secs(secs),
+            None => {
+                error!(
+                    "Invalid --metrics-interval {:?} (expected a positive number of seconds)",
+                    value.map(String::as_str).unwrap_or_default()
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    // Bound before scanning, so an address in use fails fast, and served from its own
+    // thread while the scans run.
+    let metrics_index = args.iter().position(|x| x == "--metrics-server");
+    let metrics = match metrics_index.and_then(|i| args.get(i + 1)) {
+        Some(addr) => match TcpListener::bind(addr) {
+            Ok(listener) => {
+                let snapshot = Arc::new(Mutex::new(MetricsSnapshot::default()));
+                let served = Arc::clone(&snapshot);
+                std::thread::spawn(move || {
+                    metrics::serve_metrics(listener, served, metrics::REQUEST_TIMEOUT)
+                });
+                Some((addr, snapshot))
+            }
+            Err(e) => {
+                error!("Failed to start metrics server on {addr}: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    let scan_git_index_flag = args.contains(&"--scan-git-index".to_string());
+    let run_scan = || {
+        if scan_git_index_flag {
+            scan_git_index(&root_dirs, &config)
+                .map_err(|e| format!("Failed to read the git index: {e}"))
+        } else {
+            Ok(scan_directories(&root_dirs, &config))
+        }
+    };
+    let update_metrics = |results: &ScanResults, scan_duration: Duration| {
+        if let Some((_, snapshot)) = &metrics {
+            *snapshot.lock().unwrap() = MetricsSnapshot {
+                risk_score: results.risk_score,
+                ..MetricsSnapshot::from_rows(&results.file_rows, scan_duration)
+            };
+        }
+    };
 
-        statuses.insert(key, final_status);
-    });
-
-    let total_safe_usages = *total_safe.lock().unwrap();
-    let total_partial_usages = *total_partial.lock().unwrap();
-    let total_unsafe_usages = *total_unsafe.lock().unwrap();
-    let project_statuses = project_statuses.lock().unwrap();
-
-    let safe_projects = project_statuses
-        .values()
-        .filter(|&&s| s == Status::Safe)
-        .count();
-    let partial_projects = project_statuses
-        .values()
-        .filter(|&&s| s == Status::PartiallySafe)
-        .count();
-    let unsafe_projects = project_statuses
-        .values()
-        .filter(|&&s| s == Status::Unsafe)
-        .count();
-
-    println!("====== Scan Summary ======");
-    println!("Safe usages (with commit SHA): {total_safe_usages}");
-    println!("Partially safe usages (with tag/branch): {total_partial_usages}");
-    println!("Unsafe usages (no revision): {total_unsafe_usages}");
-    println!("Safe projects: {safe_projects}");
-    println!("Partially safe projects: {partial_projects}");
-    println!("Unsafe projects: {unsafe_projects}");
+    let scan_start = Instant::now();
+    let mut results = match run_scan() {
+        Ok(results) => results,
+        Err(e) => {
+            error!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    update_metrics(&results, scan_start.elapsed());
+    if let Some(cache) = &config.cache
+        && let Err(e) = cache.save()
+    {
+        error!("Failed to write scan cache: {e}");
+    }
+    if args.contains(&"--anonymise-models".to_string()) {
+        anonymise_model_names(&mut results);
+    }
+    let emit_cwe = args.contains(&"--emit-cwe".to_string());
+    if emit_cwe {
+        annotate_cwe(&mut results.findings);
+    }
+    let scan_duration = scan_start.elapsed();
+    debug!(?scan_duration, "scan finished");
+    let output_start = Instant::now();
+    let stats = &results.stats;
+
+    let format_numbers = args.contains(&"--format-numbers".to_string());
+    let count = |n: &dyn std::fmt::Display| {
+        if format_numbers {
+            format_count(n)
+        } else {
+            n.to_string()
+        }
+    };
+    if !quiet {
+        println!("====== Scan Summary ======");
+        println!(
+            "Safe usages (with commit SHA): {}",
+            count(&stats.safe_usages)
+        );
+        println!(
+            "Partially safe usages (with tag/branch): {}",
+            count(&stats.partial_usages)
+        );
+        if stats.metadata_load_usages > 0 {
+            println!(
+                "  of which model card/metadata loads: {}",
+                count(&stats.metadata_load_usages)
+            );
+        }
+        if stats.local_file_load_usages > 0 {
+            println!(
+                "  of which XGBoost/LightGBM model file loads: {}",
+                count(&stats.local_file_load_usages)
+            );
+        }
+        println!(
+            "Unsafe usages (no revision): {}",
+            count(&stats.unsafe_usages)
+        );
+        if stats.joblib_unsafe_usages > 0 {
+            println!(
+                "  of which joblib.load: {}",
+                count(&stats.joblib_unsafe_usages)
+            );
+        }
+        if stats.test_fixture_unsafe_usages > 0 {
+            println!(
+                "  of which in test fixtures: {}",
+                count(&stats.test_fixture_unsafe_usages)
+            );
+        }
+        println!(
+            "Ignored usages (# noscan): {}",
+            count(&stats.ignored_usages)
+        );
+        if stats.files_skipped_generated > 0 {
+            println!(
+                "Generated files skipped: {}",
+                count(&stats.files_skipped_generated)
+            );
+        }
+        if stats.files_skipped_too_large > 0 {
+            println!(
+                "Files skipped (over --max-file-size): {}",
+                count(&stats.files_skipped_too_large)
+            );
+        }
+        println!("Safe projects: {}", count(&stats.safe_projects));
+        println!(
+            "Partially safe projects: {}",
+            count(&stats.partial_projects)
+        );
+        println!("Unsafe projects: {}", count(&stats.unsafe_projects));
+        println!("Risk score: {}", results.risk_score);
+        println!(
+            "Lines scanned: {} ({} bytes)",
+            count(&stats.total_lines_scanned),
+            count(&stats.total_bytes_scanned)
+        );
+    }
+
+    if args.contains(&"--scan-depth-report".to_string()) {
+        println!("\n====== Files by Depth ======");
+        let widest = results.files_by_depth.values().max().copied().unwrap_or(0);
+        for (depth, files) in &results.files_by_depth {
+            // Scale the bars to at most 50 characters.
+            let bar = "#".repeat((files * 50).div_ceil(widest.max(1)));
+            println!("{depth:>3} | {bar} {files}");
+        }
+    }
+
+    let slowest_files = results.slowest_files();
+    if config.per_file_timing {
+        println!("\n====== Slowest Files ======");
+        for row in slowest_files.iter().take(10) {
+            println!("{:>10.3?} {}", row.scan_time.unwrap_or_default(), row.file);
+        }
+    }
 
     if detailed {
         println!("\n====== Project Status ======");
-        for ((org, repo), status) in project_statuses.iter() {
-            let status_str = match status {
-                Status::Safe => "safe",
-                Status::PartiallySafe => "partially_safe",
-                Status::Unsafe => "unsafe",
+        for project in &results.projects {
+            println!(
+                "{:<20}/{:<20} {}",
+                project.org, project.repo, project.status
+            );
+            let key = (project.org.clone(), project.repo.clone());
+            for file in results.project_unsafe_files.get(&key).into_iter().flatten() {
+                println!("    {file}");
+            }
+        }
+
+        println!("\n====== Files ======");
+        for row in &results.file_rows {
+            println!(
+                "{} safe={} partial={} unsafe={} ({} lines)",
+                row.file, row.safe_usages, row.partial_usages, row.unsafe_usages, row.lines
+            );
+        }
+
+        if config.per_file_timing {
+            println!("\n====== Files Slower Than {slow_threshold:?} ======");
+            for row in slowest_files
+                .iter()
+                .take_while(|row| row.scan_time.is_some_and(|t| t >= slow_threshold))
+            {
+                println!("{:>10.3?} {}", row.scan_time.unwrap_or_default(), row.file);
+            }
+        }
+
+        if stats.ignored_usages > 0 {
+            println!("\n====== Ignored Findings ======");
+            // Dimmed on a terminal, so they stand apart from the findings that count.
+            let (dim, reset) = if std::io::stdout().is_terminal() {
+                ("\x1b[2m", "\x1b[0m")
+            } else {
+                ("", "")
             };
-            println!("{org:<20}/{repo:<20} {status_str}");
+            for finding in results
+                .findings
+                .iter()
+                .filter(|f| f.status == Status::Ignored)
+            {
+                println!(
+                    "{dim}{}:{} {} ignored{reset}",
+                    finding.file, finding.line, finding.pattern
+                );
+            }
+        }
+
+        println!("\n====== Findings ======");
+        for finding in &results.findings {
+            println!(
+                "{}:{} {} {}{}{}{}{}{}{}{}{}",
+                finding.file,
+                finding.line,
+                finding.pattern,
+                finding.status,
+                match &finding.enclosing_function {
+                    Some(function) => format!(" in {function}()"),
+                    None => String::new(),
+                },
+                if finding.in_loop { " (in loop)" } else { "" },
+                if finding.in_init_empty_weights {
+                    " (in init_empty_weights)"
+                } else {
+                    ""
+                },
+                if finding.in_test_fixture {
+                    " (in test fixture)"
+                } else {
+                    ""
+                },
+                if finding.shared_cache_dir {
+                    " (shared cache_dir)"
+                } else {
+                    ""
+                },
+                if has_suspicious_sha(&finding.call) {
+                    " (suspicious SHA)"
+                } else {
+                    ""
+                },
+                if finding.incomplete_download {
+                    " (incomplete download)"
+                } else {
+                    ""
+                },
+                if finding.private_repo {
+                    " (private repo)"
+                } else {
+                    ""
+                }
+            );
+            for cwe in &finding.cwe {
+                println!("    {cwe}: {}", cwe.url());
+            }
+            for line in finding.context.lines() {
+                println!("    {line}");
+            }
+        }
+    }
+
+    let group_models = args.contains(&"--group-by-model".to_string());
+    if group_models && !quiet {
+        println!("\n====== Models ======");
+        for (model_name, loads) in group_by_model(&results) {
+            println!("{model_name}");
+            for load in loads {
+                println!("  {}/{} {} {}", load.org, load.repo, load.file, load.status);
+            }
+        }
+    }
+
+    let remediation_hints = args.contains(&"--remediation-hint".to_string());
+    if remediation_hints {
+        println!("\n====== Remediation Hints ======");
+        for finding in &results.findings {
+            if let Some(hint) = remediation_hint(finding) {
+                println!("{}:{} try: {hint}", finding.file, finding.line);
+            }
+        }
+    }
+
+    if emit_annotations {
+        for annotation in github_annotations(&results.findings, remediation_hints) {
+            println!("{annotation}");
+        }
+    }
+
+    if find_dups {
+        println!("\n====== Duplicate Model Loads ======");
+        for dup in find_duplicates(&results, dup_threshold) {
+            println!(
+                "{}/{}: {} seen in {} files",
+                dup.org, dup.repo, dup.model_name, dup.files
+            );
+        }
+    }
+
+    if args.contains(&"--detect-reuse".to_string()) {
+        println!("\n====== Model Reuse Across Repos ======");
+        for reuse in detect_reuse(&results) {
+            let projects: Vec<String> = reuse
+                .projects
+                .iter()
+                .map(|(org, repo)| format!("{org}/{repo}"))
+                .collect();
+            match &reuse.revision {
+                Some(revision) => print!("{}/{revision}", reuse.model_name),
+                None => print!("{} (no revision)", reuse.model_name),
+            }
+            println!(": seen in {}", projects.join(", "));
         }
     }
 
+    let mut exit_code = ExitCode::SUCCESS;
+
     if let Some(csv_file) = csv_output {
-        if let Err(e) = write_file_csv(csv_file, &file_rows.lock().unwrap()) {
-            eprintln!("Failed to write CSV: {e}");
+        let written = if group_models {
+            write_models_csv(csv_file, &group_by_model(&results), &csv_options)
         } else {
+            write_file_csv(csv_file, &results.file_rows, &csv_options)
+        };
+        if let Err(e) = written {
+            error!("Failed to write CSV: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
             println!("CSV written to: {csv_file}");
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Read;
-    use tempfile::NamedTempFile;
+    if let Some(csv_file) = append_csv_output {
+        let options = CsvOptions {
+            append: true,
+            ..csv_options
+        };
+        let written = if group_models {
+            write_models_csv(csv_file, &group_by_model(&results), &options)
+        } else {
+            write_file_csv(csv_file, &results.file_rows, &options)
+        };
+        if let Err(e) = written {
+            error!("Failed to append CSV: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
+            println!("CSV appended to: {csv_file}");
+        }
+    }
 
-    #[test]
-    fn test_format_csv_field_normal() {
-        assert_eq!(format_csv_field("normal_project"), "normal_project");
-        assert_eq!(format_csv_field("project-name"), "project-name");
-        assert_eq!(format_csv_field("project_123"), "project_123");
+    if let Some(json_file) = json_output {
+        let written = if group_models {
+            write_models_json(json_file, &results, schema_version)
+        } else {
+            write_file_json(json_file, &results, schema_version)
+        };
+        if let Err(e) = written {
+            error!("Failed to write JSON: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
+            println!("JSON written to: {json_file}");
+        }
     }
 
-    #[test]
-    fn test_format_csv_field_with_comma() {
-        assert_eq!(
-            format_csv_field("project, with comma"),
-            "\"project, with comma\""
-        );
-        assert_eq!(format_csv_field("a,b,c"), "\"a,b,c\"");
-        assert_eq!(
-            format_csv_field("comma, at, multiple, places"),
-            "\"comma, at, multiple, places\""
-        );
+    let graph_index = args.iter().position(|x| x == "--export-graph");
+    if let Some(graph_file) = graph_index.and_then(|i| args.get(i + 1)) {
+        let dot = build_import_graph(&root_dirs, &results).to_dot();
+        if let Err(e) = std::fs::write(graph_file, dot) {
+            error!("Failed to write import graph: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
+            println!("Import graph written to: {graph_file}");
+        }
     }
 
-    #[test]
-    fn test_format_csv_field_with_quotes() {
-        assert_eq!(
-            format_csv_field("project \"quoted\""),
-            "\"project \"\"quoted\"\"\""
-        );
-        assert_eq!(format_csv_field("\"start quote"), "\"\"\"start quote\"");
-        assert_eq!(format_csv_field("end quote\""), "\"end quote\"\"\"");
-        assert_eq!(
-            format_csv_field("multiple \"quotes\" here"),
-            "\"multiple \"\"quotes\"\" here\""
-        );
+    let registry_index = args.iter().position(|x| x == "--emit-registry");
+    if let Some(registry_file) = registry_index.and_then(|i| args.get(i + 1)) {
+        let registry = ModelRegistry::from_findings(&results.findings);
+        if let Err(e) = std::fs::write(registry_file, registry.to_json()) {
+            error!("Failed to write model registry: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
+            println!("Model registry written to: {registry_file}");
+        }
     }
 
-    #[test]
-    fn test_format_csv_field_with_newline() {
-        assert_eq!(
-            format_csv_field("project\nwith\nnewlines"),
-            "\"project\nwith\nnewlines\""
-        );
-        assert_eq!(format_csv_field("line1\nline2"), "\"line1\nline2\"");
+    let fix_pr_body_index = args.iter().position(|x| x == "--emit-fix-pr-body");
+    if let Some(body_file) = fix_pr_body_index.and_then(|i| args.get(i + 1)) {
+        if let Err(e) = std::fs::write(body_file, render_fix_pr_body(&results.findings)) {
+            error!("Failed to write fix PR body: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
+            println!("Fix PR body written to: {body_file}");
+        }
     }
 
-    #[test]
-    fn test_format_csv_field_combined_special_chars() {
-        assert_eq!(
-            format_csv_field("project, \"with\" both"),
-            "\"project, \"\"with\"\" both\""
+    let attestation_index = args.iter().position(|x| x == "--emit-attestation");
+    if let Some(vex_file) = attestation_index.and_then(|i| args.get(i + 1)) {
+        let document = openvex_document(&results, SystemTime::now());
+        if let Err(e) = std::fs::write(vex_file, document.to_json()) {
+            error!("Failed to write attestation: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
+            println!("Attestation written to: {vex_file}");
+        }
+    }
+
+    let cyclonedx_index = args.iter().position(|x| x == "--cyclonedx");
+    if let Some(sbom_file) = cyclonedx_index.and_then(|i| args.get(i + 1)) {
+        if let Err(e) = write_cyclonedx(sbom_file, &results) {
+            error!("Failed to write CycloneDX SBOM: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
+            println!("CycloneDX SBOM written to: {sbom_file}");
+        }
+    }
+
+    let org_report_index = args.iter().position(|x| x == "--organisation-risk-report");
+    if let Some(report_file) = org_report_index.and_then(|i| args.get(i + 1)) {
+        let baseline_index = args.iter().position(|x| x == "--baseline");
+        let baseline = baseline_index
+            .and_then(|i| args.get(i + 1))
+            .map(|dir| scan_directory(Path::new(dir), &config));
+        let report = render_org_risk_report(&results, baseline.as_ref(), &config.risk_weights);
+        if let Err(e) = std::fs::write(report_file, report) {
+            error!("Failed to write organisation risk report: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
+            println!("Organisation risk report written to: {report_file}");
+        }
+    }
+
+    if !check_thresholds(&results, &config) {
+        exit_code = ExitCode::FAILURE;
+    }
+
+    if let Some(profile_file) = perf_profile_file
+        && let Some(mut timings) = results.phase_timings
+    {
+        timings.output = output_start.elapsed();
+        if let Err(e) = std::fs::write(profile_file, timings.to_json()) {
+            error!("Failed to write performance profile: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else if !quiet {
+            println!("Performance profile written to: {profile_file}");
+        }
+    }
+
+    if let Some((pr, token, api_url, baseline)) = github_pr {
+        let baseline_stats = baseline.map(|dir| scan_directory(&dir, &config).stats);
+        let report_title = args
+            .iter()
+            .position(|x| x == "--report-title")
+            .and_then(|i| args.get(i + 1));
+        let comment = github::render_pr_comment(
+            &results,
+            baseline_stats.as_ref(),
+            report_title.map(String::as_str),
         );
-        assert_eq!(
-            format_csv_field("all: comma, \"quote\", and\nnewline"),
-            "\"all: comma, \"\"quote\"\", and\nnewline\""
+        if let Err(e) = github::post_pr_review(&api_url, &token, &pr, &comment) {
+            error!("Failed to post PR comment: {e}");
+            exit_code = ExitCode::FAILURE;
+        } else {
+            println!(
+                "Review comment posted to {}/{}#{}",
+                pr.owner, pr.repo, pr.number
+            );
+        }
+    }
+
+    if let Some((addr, _)) = &metrics {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                error!("Failed to start Tokio runtime: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        println!(
+            "Serving metrics on http://{addr}/metrics, rescanning every {}s until interrupted",
+            metrics_interval.as_secs()
         );
+        // Polled across iterations, so a signal that arrives during a rescan is not lost.
+        let mut shutdown = Box::pin(shutdown_signal());
+        // Rescans run outside `block_on`, as the Tokio concurrency mode starts a runtime
+        // of its own.
+        while !runtime.block_on(async {
+            tokio::select! {
+                () = &mut shutdown => true,
+                () = tokio::time::sleep(metrics_interval) => false,
+            }
+        }) {
+            let scan_start = Instant::now();
+            match run_scan() {
+                Ok(results) => update_metrics(&results, scan_start.elapsed()),
+                Err(e) => error!("{e}"),
+            }
+        }
     }
 
-    #[test]
-    fn test_write_file_csv_basic() -> std::io::Result<()> {
-        let file_data = vec![
-            (
-                "org1".to_string(),
-                "repo1".to_string(),
-                "file1.py".to_string(),
-                1,
-                2,
-                3,
-            ),
-            (
-                "org2".to_string(),
-                "repo2".to_string(),
-                "file2.py".to_string(),
-                0,
-                1,
-                0,
-            ),
-        ];
-
-        let temp_file = NamedTempFile::new()?;
-        let temp_path = temp_file.path().to_str().unwrap();
-
-        write_file_csv(temp_path, &file_data)?;
-
-        let mut contents = String::new();
-        let mut file = File::open(temp_path)?;
-        file.read_to_string(&mut contents)?;
-
-        assert!(contents.contains("org,repo,file,safe_usages,partial_usages,unsafe_usages"));
-        assert!(contents.contains("org1,repo1,file1.py,1,2,3"));
-        assert!(contents.contains("org2,repo2,file2.py,0,1,0"));
-
-        Ok(())
-    }
-
-    #[test]
-    fn test_write_file_csv_with_special_chars() -> std::io::Result<()> {
-        let file_data = vec![
-            (
-                "normal_org".to_string(),
-                "normal_repo".to_string(),
-                "normal.py".to_string(),
-                1,
-                0,
-                0,
-            ),
-            (
-                "org, with comma".to_string(),
-                "repo".to_string(),
-                "file.py".to_string(),
-                0,
-                1,
-                0,
-            ),
-            (
-                "org".to_string(),
-                "repo \"quoted\"".to_string(),
-                "file.py".to_string(),
-                0,
-                0,
-                1,
-            ),
-            (
-                "org".to_string(),
-                "repo".to_string(),
-                "path/with, comma/file.py".to_string(),
-                1,
-                1,
-                1,
-            ),
-        ];
-
-        let temp_file = NamedTempFile::new()?;
-        let temp_path = temp_file.path().to_str().unwrap();
-
-        write_file_csv(temp_path, &file_data)?;
-
-        let mut contents = String::new();
-        let mut file = File::open(temp_path)?;
-        file.read_to_string(&mut contents)?;
-
-        // Check header
-        assert!(contents.contains("org,repo,file,safe_usages,partial_usages,unsafe_usages"));
-
-        // Check each entry is properly formatted
-        assert!(contents.contains("normal_org,normal_repo,normal.py,1,0,0"));
-        assert!(contents.contains("\"org, with comma\",repo,file.py,0,1,0"));
-        assert!(contents.contains("org,\"repo \"\"quoted\"\"\",file.py,0,0,1"));
-        assert!(contents.contains("org,repo,\"path/with, comma/file.py\",1,1,1"));
-
-        Ok(())
-    }
-
-    #[test]
-    fn test_is_commit_sha() {
-        // Valid SHA
-        assert!(is_commit_sha("5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d"));
-        assert!(is_commit_sha("abcdef1234567890abcdef1234567890abcdef12"));
-
-        // Invalid SHA
-        assert!(!is_commit_sha("main"));
-        assert!(!is_commit_sha("v1.0"));
-        assert!(!is_commit_sha("5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5")); // 39 chars
-        assert!(!is_commit_sha("5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d1")); // 41 chars
-        assert!(!is_commit_sha("5D0F2E8A7F1B2C3D4E5F6A7B8C9D0E1F2A3B4C5D")); // uppercase
-        assert!(!is_commit_sha("5g0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")); // contains 'g'
-    }
-
-    #[test]
-    fn test_scan_code_for_usage_basic() {
-        let code = r#"
-from transformers import AutoModel
-model = AutoModel.from_pretrained("model")
-"#;
-        let (safe, partial, unsafe_) = scan_code_for_usage(code);
-        assert_eq!(safe, 0);
-        assert_eq!(partial, 0);
-        assert_eq!(unsafe_, 1);
-    }
-
-    #[test]
-    fn test_scan_code_for_usage_with_sha() {
-        let code = r#"
-from transformers import AutoModel
-model = AutoModel.from_pretrained("model", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
-"#;
-        let (safe, partial, unsafe_) = scan_code_for_usage(code);
-        assert_eq!(safe, 1);
-        assert_eq!(partial, 0);
-        assert_eq!(unsafe_, 0);
-    }
-
-    #[test]
-    fn test_scan_code_for_usage_with_tag() {
-        let code = r#"
-from transformers import AutoModel
-model = AutoModel.from_pretrained("model", revision="main")
-"#;
-        let (safe, partial, unsafe_) = scan_code_for_usage(code);
-        assert_eq!(safe, 0);
-        assert_eq!(partial, 1);
-        assert_eq!(unsafe_, 0);
-    }
-
-    #[test]
-    fn test_get_org_repo() {
-        use std::path::PathBuf;
-
-        let root = PathBuf::from("/home/user/repos");
-        let path = PathBuf::from("/home/user/repos/microsoft/DialoGPT/src/model.py");
-
-        let (org, repo) = get_org_repo(&path, &root);
-        assert_eq!(org, "microsoft");
-        assert_eq!(repo, "DialoGPT");
-    }
-
-    #[test]
-    fn test_get_org_repo_with_comma() {
-        use std::path::PathBuf;
-
-        let root = PathBuf::from("/repos");
-        let path = PathBuf::from("/repos/org, with comma/repo-name/file.py");
-
-        let (org, repo) = get_org_repo(&path, &root);
-        assert_eq!(org, "org, with comma");
-        assert_eq!(repo, "repo-name");
+    exit_code
+}
+
+/// Seconds between rescans with `--metrics-server`.
+const DEFAULT_METRICS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Resolves once the process is asked to stop: Ctrl-C, or SIGTERM on Unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
     }
 }
@@ -0,0 +1,213 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::directory::FileRow;
+
+/// Gauges exposed on the `/metrics` endpoint, replaced after every completed scan.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    pub unsafe_files: usize,
+    pub partial_files: usize,
+    pub safe_files: usize,
+    pub last_scan_duration: Duration,
+    /// `ScanResults::risk_score` of the last scan; not derived from the rows.
+    pub risk_score: f64,
+}
+
+impl MetricsSnapshot {
+    /// Classify every file by its worst finding. Files without findings are not counted.
+    pub fn from_rows(rows: &[FileRow], last_scan_duration: Duration) -> Self {
+        let mut snapshot = MetricsSnapshot {
+            last_scan_duration,
+            ..Default::default()
+        };
+        for row in rows {
+            if row.unsafe_usages > 0 {
+                snapshot.unsafe_files += 1;
+            } else if row.partial_usages > 0 {
+                snapshot.partial_files += 1;
+            } else if row.safe_usages > 0 {
+                snapshot.safe_files += 1;
+            }
+        }
+        snapshot
+    }
+}
+
+/// Render a snapshot in the Prometheus text exposition format.
+pub fn render_metrics(snapshot: &MetricsSnapshot) -> String {
+    let gauges = [
+        (
+            "silentinjection_unsafe_files_total",
+            "Files with at least one unsafe usage in the last scan.",
+            snapshot.unsafe_files.to_string(),
+        ),
+        (
+            "silentinjection_partial_files_total",
+            "Files whose worst usage is partially safe in the last scan.",
+            snapshot.partial_files.to_string(),
+        ),
+        (
+            "silentinjection_safe_files_total",
+            "Files with only safe usages in the last scan.",
+            snapshot.safe_files.to_string(),
+        ),
+        (
+            "silentinjection_risk_score",
+            "Composite risk score of the last scan.",
+            snapshot.risk_score.to_string(),
+        ),
+        (
+            "silentinjection_last_scan_duration_seconds",
+            "Wall-clock duration of the last scan.",
+            snapshot.last_scan_duration.as_secs_f64().to_string(),
+        ),
+    ];
+
+    let mut out = String::new();
+    for (name, help, value) in gauges {
+        out.push_str(&format!("# HELP {name} {help}\n"));
+        out.push_str(&format!("# TYPE {name} gauge\n"));
+        out.push_str(&format!("{name} {value}\n"));
+    }
+    out
+}
+
+/// How long a client may take to send its request line before the connection is dropped.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve `/metrics` on `listener` until the process exits.
+///
+/// The snapshot is shared so a long-running caller can swap in new values between scans.
+/// Requests are handled one at a time, so a connection that sends nothing is dropped
+/// after `request_timeout` rather than holding up the others.
+pub fn serve_metrics(
+    listener: TcpListener,
+    snapshot: Arc<Mutex<MetricsSnapshot>>,
+    request_timeout: Duration,
+) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let handled = stream
+                    .set_read_timeout(Some(request_timeout))
+                    .and_then(|()| stream.set_write_timeout(Some(request_timeout)))
+                    .and_then(|()| handle_connection(stream, &snapshot));
+                if let Err(e) = handled {
+                    tracing::warn!("Metrics request failed: {e}");
+                }
+            }
+            Err(e) => tracing::warn!("Metrics connection failed: {e}"),
+        }
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    snapshot: &Mutex<MetricsSnapshot>,
+) -> std::io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+
+    let (status, content_type, body) = if path == "/metrics" {
+        (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            render_metrics(&snapshot.lock().unwrap()),
+        )
+    } else {
+        ("404 Not Found", "text/plain", "not found\n".to_string())
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_metrics_snapshot_from_rows() {
+        let row = |file: &str, safe, partial, unsafe_| FileRow {
+            org: "o".to_string(),
+            repo: "r".to_string(),
+            file: file.to_string(),
+            safe_usages: safe,
+            partial_usages: partial,
+            unsafe_usages: unsafe_,
+            ..Default::default()
+        };
+        let rows = vec![
+            row("a.py", 1, 0, 2),
+            row("b.py", 1, 1, 0),
+            row("c.py", 3, 0, 0),
+            row("d.py", 0, 0, 0),
+        ];
+        let snapshot = MetricsSnapshot::from_rows(&rows, Duration::from_millis(1500));
+        assert_eq!(snapshot.unsafe_files, 1);
+        assert_eq!(snapshot.partial_files, 1);
+        assert_eq!(snapshot.safe_files, 1);
+        assert!(
+            render_metrics(&snapshot).contains("silentinjection_last_scan_duration_seconds 1.5")
+        );
+    }
+
+    #[test]
+    fn test_metrics_endpoint() -> std::io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let snapshot = Arc::new(Mutex::new(MetricsSnapshot {
+            unsafe_files: 2,
+            ..Default::default()
+        }));
+        std::thread::spawn(move || serve_metrics(listener, snapshot, REQUEST_TIMEOUT));
+
+        let mut stream = TcpStream::connect(addr)?;
+        stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("silentinjection_unsafe_files_total 2"));
+        assert!(response.contains("silentinjection_partial_files_total"));
+        assert!(response.contains("silentinjection_safe_files_total"));
+        assert!(response.contains("silentinjection_risk_score 0"));
+        assert!(response.contains("silentinjection_last_scan_duration_seconds"));
+
+        let mut stream = TcpStream::connect(addr)?;
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics_idle_connection() -> std::io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let snapshot = Arc::new(Mutex::new(MetricsSnapshot::default()));
+        let timeout = Duration::from_millis(100);
+        std::thread::spawn(move || serve_metrics(listener, snapshot, timeout));
+
+        // Connects but never sends a request.
+        let _idle = TcpStream::connect(addr)?;
+        let mut stream = TcpStream::connect(addr)?;
+        stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+        Ok(())
+    }
+}
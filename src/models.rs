@@ -0,0 +1,569 @@
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::LazyLock;
+
+use crate::directory::ScanResults;
+use crate::scanner::{Finding, ModelName, Status, count_usages, extract_revision};
+
+/// A model name loaded from several files of the same project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateModel {
+    pub org: String,
+    pub repo: String,
+    pub model_name: String,
+    /// Number of distinct files loading `model_name`.
+    pub files: usize,
+}
+
+/// Literal model names loaded from more than `threshold` files within one `(org, repo)`,
+/// sorted by project and name. Dynamic (f-string) names are ignored; anonymised names are
+/// reported by their placeholder.
+pub fn find_duplicates(results: &ScanResults, threshold: usize) -> Vec<DuplicateModel> {
+    let projects: HashMap<&str, (&str, &str)> = results
+        .file_rows
+        .iter()
+        .map(|row| (row.file.as_str(), (row.org.as_str(), row.repo.as_str())))
+        .collect();
+
+    let mut files_by_model: BTreeMap<(&str, &str, &str), BTreeSet<&str>> = BTreeMap::new();
+    for finding in &results.findings {
+        let Some(ModelName::Literal(name) | ModelName::Anonymised(name)) = &finding.model_name
+        else {
+            continue;
+        };
+        let Some(&(org, repo)) = projects.get(finding.file.as_str()) else {
+            continue;
+        };
+        files_by_model
+            .entry((org, repo, name.as_str()))
+            .or_default()
+            .insert(finding.file.as_str());
+    }
+
+    files_by_model
+        .into_iter()
+        .filter(|(_, files)| files.len() > threshold)
+        .map(|((org, repo, model_name), files)| DuplicateModel {
+            org: org.to_string(),
+            repo: repo.to_string(),
+            model_name: model_name.to_string(),
+            files: files.len(),
+        })
+        .collect()
+}
+
+/// A model name and revision loaded by more than one project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelReuse {
+    pub model_name: String,
+    /// `None` for loads without a `revision` argument.
+    pub revision: Option<String>,
+    /// The `(org, repo)` pairs loading it, sorted.
+    pub projects: Vec<(String, String)>,
+}
+
+/// `(model name, revision)` combinations loaded from more than one `(org, repo)`, for
+/// spotting code copied between projects, sorted by name and revision. Dynamic (f-string)
+/// names are ignored.
+pub fn detect_reuse(results: &ScanResults) -> Vec<ModelReuse> {
+    let projects: HashMap<&str, (&str, &str)> = results
+        .file_rows
+        .iter()
+        .map(|row| (row.file.as_str(), (row.org.as_str(), row.repo.as_str())))
+        .collect();
+
+    // Keyed by model name and revision.
+    type Key<'a> = (&'a str, Option<String>);
+    let mut projects_by_model: BTreeMap<Key, BTreeSet<(&str, &str)>> = BTreeMap::new();
+    for finding in &results.findings {
+        let Some(ModelName::Literal(name) | ModelName::Anonymised(name)) = &finding.model_name
+        else {
+            continue;
+        };
+        let Some(&project) = projects.get(finding.file.as_str()) else {
+            continue;
+        };
+        projects_by_model
+            .entry((name.as_str(), extract_revision(&finding.call)))
+            .or_default()
+            .insert(project);
+    }
+
+    projects_by_model
+        .into_iter()
+        .filter(|(_, projects)| projects.len() > 1)
+        .map(|((model_name, revision), projects)| ModelReuse {
+            model_name: model_name.to_string(),
+            revision,
+            projects: projects
+                .into_iter()
+                .map(|(org, repo)| (org.to_string(), repo.to_string()))
+                .collect(),
+        })
+        .collect()
+}
+
+/// One model name and revision in a [`ModelRegistry`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RegistryEntry {
+    pub model_name: String,
+    /// `None` for loads without a `revision` argument.
+    pub revision: Option<String>,
+    pub load_count: usize,
+    /// Distinct files loading the model, sorted.
+    pub files: Vec<String>,
+    /// `(file, line)` of every load, in scan order.
+    pub locations: Vec<Location>,
+}
+
+/// Inventory of every model loaded in a scan, keyed by model name and revision: a bill of
+/// materials for the models a codebase depends on. Dynamic (f-string) names are left out,
+/// ignored findings are not.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelRegistry {
+    loads: BTreeMap<(String, Option<String>), Vec<Location>>,
+}
+
+/// `(file, line)` of a load.
+type Location = (String, usize);
+
+impl ModelRegistry {
+    pub fn from_findings(findings: &[Finding]) -> ModelRegistry {
+        let mut registry = ModelRegistry::default();
+        for finding in findings {
+            registry.add(finding);
+        }
+        registry
+    }
+
+    /// Record the load in `finding`, if it names a model.
+    pub fn add(&mut self, finding: &Finding) {
+        let Some(ModelName::Literal(name) | ModelName::Anonymised(name)) = &finding.model_name
+        else {
+            return;
+        };
+        self.loads
+            .entry((name.clone(), extract_revision(&finding.call)))
+            .or_default()
+            .push((finding.file.clone(), finding.line));
+    }
+
+    /// The entries, sorted by model name and revision.
+    pub fn entries(&self) -> Vec<RegistryEntry> {
+        self.loads
+            .iter()
+            .map(|((model_name, revision), locations)| {
+                let files: BTreeSet<&String> = locations.iter().map(|(file, _)| file).collect();
+                RegistryEntry {
+                    model_name: model_name.clone(),
+                    revision: revision.clone(),
+                    load_count: locations.len(),
+                    files: files.into_iter().cloned().collect(),
+                    locations: locations.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// The entries as a pretty-printed JSON array.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.entries()).unwrap()
+    }
+}
+
+/// One file loading a model, as grouped by [`group_by_model`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelLoad {
+    pub org: String,
+    pub repo: String,
+    pub file: String,
+    /// Most severe status of the model's loads in this file.
+    pub status: Status,
+    pub safe_usages: usize,
+    pub partial_usages: usize,
+    pub unsafe_usages: usize,
+}
+
+/// The files loading each model, for model-centric rather than file-centric reviews:
+/// model name to its loads, sorted by org, repo and file. Dynamic (f-string) names are
+/// left out; anonymised names are grouped by their placeholder.
+pub fn group_by_model(results: &ScanResults) -> BTreeMap<String, Vec<ModelLoad>> {
+    let projects: HashMap<&str, (&str, &str)> = results
+        .file_rows
+        .iter()
+        .map(|row| (row.file.as_str(), (row.org.as_str(), row.repo.as_str())))
+        .collect();
+
+    let mut loads: BTreeMap<(&str, &str, &str, &str), Vec<Finding>> = BTreeMap::new();
+    for finding in &results.findings {
+        let Some(ModelName::Literal(name) | ModelName::Anonymised(name)) = &finding.model_name
+        else {
+            continue;
+        };
+        let Some(&(org, repo)) = projects.get(finding.file.as_str()) else {
+            continue;
+        };
+        loads
+            .entry((name.as_str(), org, repo, finding.file.as_str()))
+            .or_default()
+            .push(finding.clone());
+    }
+
+    let mut models: BTreeMap<String, Vec<ModelLoad>> = BTreeMap::new();
+    for ((name, org, repo, file), findings) in loads {
+        let (safe_usages, partial_usages, unsafe_usages) = count_usages(&findings);
+        models.entry(name.to_string()).or_default().push(ModelLoad {
+            org: org.to_string(),
+            repo: repo.to_string(),
+            file: file.to_string(),
+            status: findings
+                .iter()
+                .map(|f| f.status)
+                .max()
+                .unwrap_or(Status::Safe),
+            safe_usages,
+            partial_usages,
+            unsafe_usages,
+        });
+    }
+    models
+}
+
+/// Replace every model name with a `<model_N>` placeholder, numbered in order of first
+/// appearance so that the same name gets the same placeholder in [`ScanResults::findings`]
+/// and every [`Project::findings`](crate::directory::Project::findings).
+///
+/// Every string literal in a finding's `call` and `context` whose contents is one of the
+/// names is redacted too, so names of neighbouring loads and of variables assigned a name
+/// on an earlier line do not leak. Only whole literals are replaced: `"t5"` becomes
+/// `"<model_3>"` but `"t5-large"` and `t5_tokenizer` are left alone.
+pub fn anonymise_model_names(results: &mut ScanResults) {
+    let mut labels: HashMap<String, String> = HashMap::new();
+    let all_findings = results.findings.iter().chain(
+        results
+            .projects
+            .iter()
+            .flat_map(|project| &project.findings),
+    );
+    for finding in all_findings {
+        let names: Vec<&str> = match &finding.model_name {
+            Some(name) => vec![name.as_str()],
+            None => variable_bound_names(finding),
+        };
+        for name in names {
+            if !labels.contains_key(name) {
+                let label = format!("<model_{}>", labels.len() + 1);
+                labels.insert(name.to_string(), label);
+            }
+        }
+    }
+
+    let all_findings = results.findings.iter_mut().chain(
+        results
+            .projects
+            .iter_mut()
+            .flat_map(|project| &mut project.findings),
+    );
+    for finding in all_findings {
+        if let Some(name) = &finding.model_name {
+            finding.model_name = Some(ModelName::Anonymised(labels[name.as_str()].clone()));
+        }
+        finding.call = redact_string_literals(&finding.call, &labels);
+        finding.context = redact_string_literals(&finding.context, &labels);
+    }
+}
+
+/// For a call passed a variable, as in `from_pretrained(model_id)`, the string literals
+/// assigned to that variable within the finding's context.
+fn variable_bound_names(finding: &Finding) -> Vec<&str> {
+    let start = finding.call.find("from_pretrained").unwrap_or(0);
+    let Some(variable) = NAME_VARIABLE_RE.captures(&finding.call[start..]) else {
+        return Vec::new();
+    };
+    ASSIGNMENT_RE
+        .captures_iter(&finding.context)
+        .filter(|caps| caps[1] == variable[1])
+        .filter_map(|caps| caps.get(2).or_else(|| caps.get(3)))
+        .map(|name| name.as_str())
+        .collect()
+}
+
+static NAME_VARIABLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[^(]*\(\s*([A-Za-z_]\w*)\s*[,)]").unwrap());
+
+static ASSIGNMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?m)^\s*([A-Za-z_]\w*)\s*=\s*(?:"([^"\n]*)"|'([^'\n]*)')"#).unwrap()
+});
+
+/// `code` with each non-empty string literal whose contents is a key of `labels` replaced
+/// by its label, keeping the quotes and any prefix.
+fn redact_string_literals(code: &str, labels: &HashMap<String, String>) -> String {
+    STRING_LITERAL_RE
+        .replace_all(code, |caps: &Captures| {
+            let (quote, contents) = match (caps.get(1), caps.get(2)) {
+                (Some(contents), _) => ('"', contents.as_str()),
+                (_, Some(contents)) => ('\'', contents.as_str()),
+                _ => unreachable!("one alternative always matches"),
+            };
+            match labels.get(contents) {
+                Some(label) if !contents.is_empty() => format!("{quote}{label}{quote}"),
+                _ => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+static STRING_LITERAL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""((?:[^"\\\n]|\\.)*)"|'((?:[^'\\\n]|\\.)*)'"#).unwrap());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::directory::scan_directory;
+    use std::fs;
+
+    #[test]
+    fn test_find_duplicates() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        for i in 0..5 {
+            fs::write(
+                repo_dir.join(format!("train_{i}.py")),
+                "model = AutoModel.from_pretrained(\"bert-base-uncased\")\n\
+                 tok = AutoTokenizer.from_pretrained(\"bert-base-uncased\")\n",
+            )?;
+        }
+        fs::write(
+            repo_dir.join("other.py"),
+            "model = AutoModel.from_pretrained(\"gpt2\")\n",
+        )?;
+        let other_repo = root.path().join("org").join("other-repo");
+        fs::create_dir_all(&other_repo)?;
+        fs::write(
+            other_repo.join("load.py"),
+            "model = AutoModel.from_pretrained(\"bert-base-uncased\")\n",
+        )?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        assert_eq!(
+            find_duplicates(&results, 3),
+            vec![DuplicateModel {
+                org: "org".to_string(),
+                repo: "repo".to_string(),
+                model_name: "bert-base-uncased".to_string(),
+                files: 5,
+            }]
+        );
+        assert!(find_duplicates(&results, 5).is_empty());
+        assert_eq!(find_duplicates(&results, 0).len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_model() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_a = root.path().join("org").join("repo-a");
+        let repo_b = root.path().join("org").join("repo-b");
+        fs::create_dir_all(&repo_a)?;
+        fs::create_dir_all(&repo_b)?;
+        fs::write(
+            repo_a.join("load.py"),
+            "model = AutoModel.from_pretrained(\"bert-base-uncased\")\n\
+             tok = AutoTokenizer.from_pretrained(\"bert-base-uncased\", revision=\"main\")\n\
+             other = AutoModel.from_pretrained(\"gpt2\", revision=\"5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d\")\n",
+        )?;
+        fs::write(
+            repo_b.join("train.py"),
+            "model = AutoModel.from_pretrained(\"bert-base-uncased\", revision=\"5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d\")\n\
+             dynamic = AutoModel.from_pretrained(f\"org/{name}\")\n",
+        )?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        let models = group_by_model(&results);
+        assert_eq!(
+            models.keys().collect::<Vec<_>>(),
+            vec!["bert-base-uncased", "gpt2"]
+        );
+        assert_eq!(
+            models["bert-base-uncased"],
+            vec![
+                ModelLoad {
+                    org: "org".to_string(),
+                    repo: "repo-a".to_string(),
+                    file: "org/repo-a/load.py".to_string(),
+                    status: Status::Unsafe,
+                    safe_usages: 0,
+                    partial_usages: 1,
+                    unsafe_usages: 1,
+                },
+                ModelLoad {
+                    org: "org".to_string(),
+                    repo: "repo-b".to_string(),
+                    file: "org/repo-b/train.py".to_string(),
+                    status: Status::Safe,
+                    safe_usages: 1,
+                    partial_usages: 0,
+                    unsafe_usages: 0,
+                },
+            ]
+        );
+        assert_eq!(models["gpt2"].len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_anonymise_model_names() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("load.py"),
+            "a = AutoModel.from_pretrained(\"org/secret\")\n\
+             b = AutoTokenizer.from_pretrained(\"t5\")\n\
+             c = AutoModel.from_pretrained(\"org/secret\", revision=\"main\")\n\
+             model_id = \"org/hidden\"\n\
+             d = AutoModel.from_pretrained(model_id)\n\
+             e = AutoModel.from_pretrained(\"t5-large\")  # t5_tokenizer\n",
+        )?;
+        let config = ScanConfig {
+            context_lines: Some(1),
+            ..ScanConfig::default()
+        };
+        let mut results = scan_directory(root.path(), &config);
+        anonymise_model_names(&mut results);
+
+        let names: Vec<Option<&str>> = results
+            .findings
+            .iter()
+            .map(|f| f.model_name.as_ref().map(ModelName::as_str))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                Some("<model_1>"),
+                Some("<model_2>"),
+                Some("<model_1>"),
+                None,
+                Some("<model_4>"),
+            ]
+        );
+        assert_eq!(
+            results.findings[0].model_name,
+            Some(ModelName::Anonymised("<model_1>".to_string()))
+        );
+        assert_eq!(
+            results.findings[0].call,
+            "AutoModel.from_pretrained(\"<model_1>\")"
+        );
+        // Names of neighbouring loads and names assigned to the variable passed to a
+        // load are redacted from the context, and only whole literals are replaced.
+        assert_eq!(
+            results.findings[1].context,
+            "a = AutoModel.from_pretrained(\"<model_1>\")\n\
+             b = AutoTokenizer.from_pretrained(\"<model_2>\")\n\
+             c = AutoModel.from_pretrained(\"<model_1>\", revision=\"main\")"
+        );
+        assert_eq!(
+            results.findings[3].context,
+            "model_id = \"<model_3>\"\n\
+             d = AutoModel.from_pretrained(model_id)\n\
+             e = AutoModel.from_pretrained(\"<model_4>\")  # t5_tokenizer"
+        );
+        assert_eq!(results.projects[0].findings, results.findings);
+        for finding in &results.projects[0].findings {
+            for secret in ["secret", "hidden"] {
+                assert!(!finding.call.contains(secret) && !finding.context.contains(secret));
+            }
+            assert!(!finding.context.contains("\"t5\""));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_registry() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("a.py"),
+            "model = AutoModel.from_pretrained(\"gpt2\")\n\
+             tok = AutoTokenizer.from_pretrained(\"gpt2\")\n\
+             ds = load_dataset(\"squad\", revision=\"v1\")\n\
+             dyn = AutoModel.from_pretrained(f\"org/{name}\")\n",
+        )?;
+        fs::write(
+            repo_dir.join("b.py"),
+            "model = AutoModel.from_pretrained(\"gpt2\")  # noscan\n",
+        )?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        let registry = ModelRegistry::from_findings(&results.findings);
+        let entries = registry.entries();
+        let names: Vec<(&str, Option<&str>, usize)> = entries
+            .iter()
+            .map(|e| (e.model_name.as_str(), e.revision.as_deref(), e.load_count))
+            .collect();
+        assert_eq!(names, vec![("gpt2", None, 3), ("squad", Some("v1"), 1)]);
+        assert_eq!(entries[0].files, vec!["org/repo/a.py", "org/repo/b.py"]);
+        assert!(
+            entries[0]
+                .locations
+                .contains(&("org/repo/a.py".to_string(), 2))
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&registry.to_json()).unwrap();
+        assert_eq!(json[1]["model_name"], "squad");
+        assert_eq!(json[1]["locations"][0][1], 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_reuse() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        for (org, repo) in [("org-a", "repo"), ("org-b", "fork")] {
+            let repo_dir = root.path().join(org).join(repo);
+            fs::create_dir_all(&repo_dir)?;
+            fs::write(
+                repo_dir.join("load.py"),
+                "model = AutoModel.from_pretrained(\"org/model\", revision=\"v1\")\n\
+                 tok = AutoTokenizer.from_pretrained(\"org/model\")\n",
+            )?;
+        }
+        let other = root.path().join("org-c").join("repo");
+        fs::create_dir_all(&other)?;
+        fs::write(
+            other.join("load.py"),
+            "model = AutoModel.from_pretrained(\"org/model\", revision=\"v2\")\n",
+        )?;
+
+        let results = scan_directory(root.path(), &ScanConfig::default());
+        let projects = vec![
+            ("org-a".to_string(), "repo".to_string()),
+            ("org-b".to_string(), "fork".to_string()),
+        ];
+        assert_eq!(
+            detect_reuse(&results),
+            vec![
+                ModelReuse {
+                    model_name: "org/model".to_string(),
+                    revision: None,
+                    projects: projects.clone(),
+                },
+                ModelReuse {
+                    model_name: "org/model".to_string(),
+                    revision: Some("v1".to_string()),
+                    projects,
+                },
+            ]
+        );
+
+        Ok(())
+    }
+}
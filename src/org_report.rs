@@ -0,0 +1,324 @@
+use crate::directory::ScanResults;
+use crate::risk::{RiskWeights, risk_score};
+use crate::scanner::Status;
+
+/// Repositories listed under "Top riskiest repositories".
+pub const TOP_RISKIEST: usize = 5;
+
+/// Width in characters of the bars in the status chart.
+const BAR_WIDTH: usize = 20;
+
+/// Letter grade for an average risk of `score / findings` per finding: A below 0.5, B
+/// below 1.5, C below 3, D below 5 and F from 5, where an unpinned load weighs 10 and a
+/// branch-pinned one 1 with the default weights. `score` must be the [`risk_score`] of
+/// the same findings, so the pickle, path traversal and `trust_remote_code` weights of a
+/// finding are averaged over the findings that carry them. A scan without findings is an
+/// A.
+pub fn letter_grade(score: f64, findings: usize) -> char {
+    if findings == 0 {
+        return 'A';
+    }
+    let average = score / findings as f64;
+    match average {
+        a if a < 0.5 => 'A',
+        a if a < 1.5 => 'B',
+        a if a < 3.0 => 'C',
+        a if a < 5.0 => 'D',
+        _ => 'F',
+    }
+}
+
+/// The `n` projects with the highest risk score as `(org/repo, score)`, riskiest first.
+/// Projects scoring 0 are left out and ties are broken by name.
+pub fn riskiest_projects(
+    results: &ScanResults,
+    weights: &RiskWeights,
+    n: usize,
+) -> Vec<(String, f64)> {
+    let mut scored: Vec<(String, f64)> = results
+        .projects
+        .iter()
+        .map(|project| {
+            (
+                format!("{}/{}", project.org, project.repo),
+                risk_score(&project.findings, weights),
+            )
+        })
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    scored.truncate(n);
+    scored
+}
+
+/// Findings that count towards the [`risk_score`] of `results`, i.e. all but the ignored
+/// ones.
+fn scored_findings(results: &ScanResults) -> usize {
+    results
+        .findings
+        .iter()
+        .filter(|f| f.status != Status::Ignored)
+        .count()
+}
+
+/// What a model load with `status` does, completing "N% of the model loads ...".
+fn status_description(status: Status) -> &'static str {
+    match status {
+        Status::Ignored => "are suppressed",
+        Status::Safe => "are pinned to a commit SHA or a local path",
+        Status::PartiallySafe => "are pinned to a branch, tag or other mutable reference",
+        Status::MetadataLoad => "load Hub metadata such as a model card without a pin",
+        Status::LocalFileLoad => "load a serialised model file from a local path",
+        Status::ApiVersionUnpinned => "call an API model through an undated alias",
+        Status::RemoteCall => "call a remotely served model without a pin",
+        Status::Unsafe => "are not pinned or deserialise untrusted files",
+        Status::TrainingWithUnsafeBase => "fine-tune an unpinned base model",
+        Status::PathTraversal => "read files outside the model directory",
+    }
+}
+
+/// One sentence breaking the non-ignored findings of `results` down by status, most
+/// common first.
+fn status_breakdown(results: &ScanResults) -> String {
+    let total = scored_findings(results);
+    if total == 0 {
+        return "No model loads were found.".to_string();
+    }
+    let mut counts: Vec<(Status, usize)> = Status::ALL
+        .iter()
+        .filter(|&&status| status != Status::Ignored)
+        .map(|&status| {
+            let count = results
+                .findings
+                .iter()
+                .filter(|f| f.status == status)
+                .count();
+            (status, count)
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    let parts: Vec<String> = counts
+        .iter()
+        .map(|&(status, count)| {
+            format!(
+                "{:.1}% ({count}) {}",
+                percent(count, total),
+                status_description(status)
+            )
+        })
+        .collect();
+    let list = match parts.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {last}", rest.join(", ")),
+        _ => parts.join(""),
+    };
+    format!("Of {total} model loads, {list}.")
+}
+
+/// `count` of `total` as a bar of [`BAR_WIDTH`] characters.
+fn bar(count: usize, total: usize) -> String {
+    let filled = (count * BAR_WIDTH + total / 2)
+        .checked_div(total)
+        .unwrap_or(0);
+    format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+fn signed(delta: f64) -> String {
+    if delta > 0.0 {
+        format!("+{delta}")
+    } else {
+        delta.to_string()
+    }
+}
+
+/// A one-page Markdown summary of the organisation's risk posture for `--organisation-risk-report`:
+/// the risk score and its [`letter_grade`], model loads by status as an ASCII chart, the
+/// [`TOP_RISKIEST`] riskiest repositories and, if a baseline scan is given, the change
+/// since then.
+pub fn render_org_risk_report(
+    results: &ScanResults,
+    baseline: Option<&ScanResults>,
+    weights: &RiskWeights,
+) -> String {
+    let stats = &results.stats;
+    let loads = scored_findings(results);
+    let grade = letter_grade(results.risk_score, loads);
+    let mut out = String::from("# Organisation ML supply chain risk report\n\n");
+    out.push_str(&format!(
+        "**Risk score: {} (grade {grade})** across {loads} model loads in {} repositories.\n\n",
+        results.risk_score,
+        results.projects.len()
+    ));
+
+    out.push_str("## Model loads by status\n\n");
+    out.push_str(&status_breakdown(results));
+    out.push_str("\n\n");
+    out.push_str("```text\n");
+    for (label, count) in [
+        ("Safe", stats.safe_usages),
+        ("Partially safe", stats.partial_usages),
+        ("Unsafe", stats.unsafe_usages),
+    ] {
+        out.push_str(&format!(
+            "{label:<15} {} {:>5.1}% ({count})\n",
+            bar(count, loads),
+            percent(count, loads)
+        ));
+    }
+    out.push_str("```\n\n");
+
+    out.push_str(&format!("## Top {TOP_RISKIEST} riskiest repositories\n\n"));
+    let riskiest = riskiest_projects(results, weights, TOP_RISKIEST);
+    if riskiest.is_empty() {
+        out.push_str("No repository has any risk.\n");
+    } else {
+        out.push_str("| # | Repository | Risk score |\n");
+        out.push_str("|---:|---|---:|\n");
+        for (rank, (name, score)) in riskiest.iter().enumerate() {
+            out.push_str(&format!("| {} | `{name}` | {score} |\n", rank + 1));
+        }
+    }
+
+    if let Some(base) = baseline {
+        let base_loads = scored_findings(base);
+        out.push_str("\n## Trend since baseline\n\n");
+        out.push_str("| | Baseline | Current | Change |\n");
+        out.push_str("|---|---:|---:|---:|\n");
+        out.push_str(&format!(
+            "| Risk score | {} | {} | {} |\n",
+            base.risk_score,
+            results.risk_score,
+            signed(results.risk_score - base.risk_score)
+        ));
+        out.push_str(&format!(
+            "| Grade | {} | {grade} | |\n",
+            letter_grade(base.risk_score, base_loads)
+        ));
+        for (label, base_count, count) in [
+            ("Safe", base.stats.safe_usages, stats.safe_usages),
+            (
+                "Partially safe",
+                base.stats.partial_usages,
+                stats.partial_usages,
+            ),
+            ("Unsafe", base.stats.unsafe_usages, stats.unsafe_usages),
+        ] {
+            out.push_str(&format!(
+                "| {label} loads | {base_count} | {count} | {} |\n",
+                signed(count as f64 - base_count as f64)
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::directory::scan_directory;
+    use std::fs;
+
+    const PINNED: &str = "m = AutoModel.from_pretrained(\"gpt2\", revision=\"5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d\")\n";
+    const UNPINNED: &str = "m = AutoModel.from_pretrained(\"gpt2\")\n";
+
+    #[test]
+    fn test_letter_grade() {
+        assert_eq!(letter_grade(0.0, 0), 'A');
+        assert_eq!(letter_grade(4.0, 10), 'A');
+        assert_eq!(letter_grade(10.0, 10), 'B');
+        assert_eq!(letter_grade(29.0, 10), 'C');
+        assert_eq!(letter_grade(30.0, 10), 'D');
+        assert_eq!(letter_grade(100.0, 10), 'F');
+    }
+
+    #[test]
+    fn test_render_org_risk_report() -> std::io::Result<()> {
+        // Six repositories with 6 to 1 unpinned loads, and one with 54 pinned loads: 210
+        // over 75 loads is 2.8 per load, a C.
+        let root = tempfile::tempdir()?;
+        for (repo, unpinned) in [("a", 1), ("b", 2), ("c", 3), ("d", 4), ("e", 5), ("f", 6)] {
+            let repo_dir = root.path().join("org").join(repo);
+            fs::create_dir_all(&repo_dir)?;
+            fs::write(repo_dir.join("load.py"), UNPINNED.repeat(unpinned))?;
+        }
+        let pinned_dir = root.path().join("org").join("pinned");
+        fs::create_dir_all(&pinned_dir)?;
+        fs::write(pinned_dir.join("load.py"), PINNED.repeat(54))?;
+        let config = ScanConfig::default();
+        let results = scan_directory(root.path(), &config);
+        assert_eq!(results.risk_score, 210.0);
+
+        let weights = RiskWeights::default();
+        assert_eq!(
+            riskiest_projects(&results, &weights, TOP_RISKIEST),
+            vec![
+                ("org/f".to_string(), 60.0),
+                ("org/e".to_string(), 50.0),
+                ("org/d".to_string(), 40.0),
+                ("org/c".to_string(), 30.0),
+                ("org/b".to_string(), 20.0),
+            ]
+        );
+
+        let report = render_org_risk_report(&results, None, &weights);
+        assert!(report.contains("**Risk score: 210 (grade C)** across 75 model loads"));
+        assert!(report.contains("| 1 | `org/f` | 60 |\n"));
+        assert!(report.contains("| 5 | `org/b` | 20 |\n"));
+        assert!(!report.contains("`org/a`"));
+        assert!(report.contains(&format!(
+            "Safe            {}{}  72.0% (54)\n",
+            "█".repeat(14),
+            "░".repeat(6)
+        )));
+        assert!(!report.contains("Trend since baseline"));
+
+        let baseline_root = tempfile::tempdir()?;
+        let baseline_dir = baseline_root.path().join("org").join("a");
+        fs::create_dir_all(&baseline_dir)?;
+        fs::write(baseline_dir.join("load.py"), UNPINNED.repeat(30))?;
+        let baseline = scan_directory(baseline_root.path(), &config);
+        let report = render_org_risk_report(&results, Some(&baseline), &weights);
+        assert!(report.contains("| Risk score | 300 | 210 | -90 |\n"));
+        assert!(report.contains("| Grade | F | C | |\n"));
+        assert!(report.contains("| Safe loads | 0 | 54 | +54 |\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_org_risk_report_statuses() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("load.py"),
+            format!(
+                "{}clf = joblib.load(\"model.pkl\")\n\
+                 m = AutoModel.from_pretrained(\"gpt2\", revision=\"main\", subfolder=\"../x\")\n\
+                 n = AutoModel.from_pretrained(\"gpt2\")  # noscan\n",
+                PINNED.repeat(2)
+            ),
+        )?;
+        let results = scan_directory(root.path(), &ScanConfig::default());
+
+        // 20 for the pickle load and 50 for the path traversal over 4 findings: an F.
+        let report = render_org_risk_report(&results, None, &RiskWeights::default());
+        assert!(report.contains("**Risk score: 70 (grade F)** across 4 model loads"));
+        assert!(report.contains(
+            "Of 4 model loads, 50.0% (2) are pinned to a commit SHA or a local path, \
+             25.0% (1) are not pinned or deserialise untrusted files and 25.0% (1) read \
+             files outside the model directory.\n"
+        ));
+
+        Ok(())
+    }
+}
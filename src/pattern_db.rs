@@ -0,0 +1,268 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::scanner::{Pattern, Status};
+
+/// One entry of a pattern database file.
+#[derive(Debug, Serialize, Deserialize)]
+struct PatternEntry {
+    name: String,
+    regex: String,
+    severity: String,
+    /// Only written by [`patterns_to_json`]; ignored when loading.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    since_version: String,
+}
+
+#[derive(Debug)]
+pub enum PatternDbError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    InvalidRegex {
+        name: String,
+        source: regex::Error,
+    },
+    InvalidSeverity {
+        name: String,
+        severity: String,
+    },
+    /// An `--extra-pattern` value that is not `<name>=<regex>`.
+    InvalidSpec(String),
+}
+
+impl fmt::Display for PatternDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternDbError::Io(e) => write!(f, "failed to read pattern database: {e}"),
+            PatternDbError::Json(e) => write!(f, "invalid pattern database: {e}"),
+            PatternDbError::InvalidRegex { name, source } => {
+                write!(f, "invalid regex for pattern '{name}': {source}")
+            }
+            PatternDbError::InvalidSeverity { name, severity } => write!(
+                f,
+                "invalid severity '{severity}' for pattern '{name}' (expected safe, partial, metadata_load, api_version_unpinned, remote_call or unsafe)"
+            ),
+            PatternDbError::InvalidSpec(spec) => {
+                write!(f, "invalid pattern '{spec}' (expected <name>=<regex>)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternDbError {}
+
+fn parse_severity(name: &str, severity: &str) -> Result<Status, PatternDbError> {
+    match severity {
+        "safe" => Ok(Status::Safe),
+        "partial" | "partially_safe" => Ok(Status::PartiallySafe),
+        "metadata_load" => Ok(Status::MetadataLoad),
+        "local_file_load" => Ok(Status::LocalFileLoad),
+        "api_version_unpinned" => Ok(Status::ApiVersionUnpinned),
+        "remote_call" => Ok(Status::RemoteCall),
+        "unsafe" => Ok(Status::Unsafe),
+        _ => Err(PatternDbError::InvalidSeverity {
+            name: name.to_string(),
+            severity: severity.to_string(),
+        }),
+    }
+}
+
+/// Parse a JSON array of `{"name": ..., "regex": ..., "severity": ...}` entries.
+pub fn parse_pattern_db(json: &str) -> Result<Vec<Pattern>, PatternDbError> {
+    let entries: Vec<PatternEntry> = serde_json::from_str(json).map_err(PatternDbError::Json)?;
+    entries
+        .into_iter()
+        .map(|entry| {
+            let severity = parse_severity(&entry.name, &entry.severity)?;
+            let regex =
+                Regex::new(&entry.regex).map_err(|source| PatternDbError::InvalidRegex {
+                    name: entry.name.clone(),
+                    source,
+                })?;
+            Ok(Pattern {
+                name: entry.name,
+                regex,
+                severity,
+                since_version: "",
+            })
+        })
+        .collect()
+}
+
+/// Parse an ad-hoc `<name>=<regex>` pattern as given to `--extra-pattern`. It is unsafe
+/// when unpinned, like the built-in patterns.
+pub fn parse_extra_pattern(spec: &str) -> Result<Pattern, PatternDbError> {
+    let Some((name, regex)) = spec
+        .split_once('=')
+        .filter(|(name, regex)| !name.trim().is_empty() && !regex.is_empty())
+    else {
+        return Err(PatternDbError::InvalidSpec(spec.to_string()));
+    };
+    let name = name.trim().to_string();
+    let regex = Regex::new(regex).map_err(|source| PatternDbError::InvalidRegex {
+        name: name.clone(),
+        source,
+    })?;
+    Ok(Pattern {
+        name,
+        regex,
+        severity: Status::Unsafe,
+        since_version: "",
+    })
+}
+
+pub fn load_pattern_db(path: &Path) -> Result<Vec<Pattern>, PatternDbError> {
+    let json = fs::read_to_string(path).map_err(PatternDbError::Io)?;
+    parse_pattern_db(&json)
+}
+
+/// `name | regex | severity | since_version` lines for `--print-patterns`, after a header
+/// line. Patterns without a `since_version` show `-`.
+pub fn format_pattern_table(patterns: &[Pattern]) -> String {
+    let mut table = String::from("name | regex | severity | since_version\n");
+    for pattern in patterns {
+        let since = match pattern.since_version {
+            "" => "-",
+            version => version,
+        };
+        table.push_str(&format!(
+            "{} | {} | {} | {since}\n",
+            pattern.name,
+            pattern.regex.as_str(),
+            pattern.severity
+        ));
+    }
+    table
+}
+
+/// The patterns as a pattern database JSON array, with their `since_version` where set.
+/// The output can be loaded again with `--pattern-db`.
+pub fn patterns_to_json(patterns: &[Pattern]) -> String {
+    let entries: Vec<PatternEntry> = patterns
+        .iter()
+        .map(|pattern| PatternEntry {
+            name: pattern.name.clone(),
+            regex: pattern.regex.as_str().to_string(),
+            severity: pattern.severity.to_string(),
+            since_version: pattern.since_version.to_string(),
+        })
+        .collect();
+    serde_json::to_string_pretty(&entries).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{active_patterns, builtin_patterns};
+
+    #[test]
+    fn test_parse_pattern_db() {
+        let json = r#"[
+            {"name": "AutoModelForCausalLM", "regex": "AutoModelForCausalLM\\.from_pretrained\\s*\\((?s:.*?)\\)", "severity": "unsafe"},
+            {"name": "torchaudio", "regex": "torchaudio\\.load\\s*\\(", "severity": "partial"}
+        ]"#;
+        let patterns = parse_pattern_db(json).unwrap();
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].name, "AutoModelForCausalLM");
+        assert_eq!(patterns[0].severity, Status::Unsafe);
+        assert!(
+            patterns[0]
+                .regex
+                .is_match(r#"AutoModelForCausalLM.from_pretrained("gpt2")"#)
+        );
+        assert_eq!(patterns[1].severity, Status::PartiallySafe);
+    }
+
+    #[test]
+    fn test_parse_pattern_db_invalid_regex() {
+        let json = r#"[{"name": "broken", "regex": "from_pretrained(", "severity": "unsafe"}]"#;
+        let err = parse_pattern_db(json).unwrap_err();
+        assert!(matches!(err, PatternDbError::InvalidRegex { ref name, .. } if name == "broken"));
+        assert!(err.to_string().contains("'broken'"));
+    }
+
+    #[test]
+    fn test_parse_pattern_db_invalid_severity() {
+        let json = r#"[{"name": "odd", "regex": "x", "severity": "critical"}]"#;
+        assert!(matches!(
+            parse_pattern_db(json),
+            Err(PatternDbError::InvalidSeverity { .. })
+        ));
+    }
+
+    #[test]
+    fn test_print_patterns() {
+        let config = crate::ScanConfig {
+            extra_patterns: vec![parse_extra_pattern(r"mylib=mylib\.load\(").unwrap()],
+            ..Default::default()
+        };
+        let patterns = active_patterns(&config);
+
+        let table = format_pattern_table(&patterns);
+        let mut lines = table.lines();
+        assert_eq!(
+            lines.next(),
+            Some("name | regex | severity | since_version")
+        );
+        for builtin in builtin_patterns() {
+            assert!(
+                lines.any(|line| line.starts_with(&format!("{} | ", builtin.name))),
+                "{} missing",
+                builtin.name
+            );
+        }
+        assert!(table.ends_with("mylib | mylib\\.load\\( | unsafe | -\n"));
+        assert!(table.contains("llama_cpp | "));
+
+        let reloaded = parse_pattern_db(&patterns_to_json(&patterns)).unwrap();
+        assert_eq!(reloaded.len(), builtin_patterns().len() + 1);
+        assert_eq!(reloaded.last().unwrap().name, "mylib");
+        assert!(patterns_to_json(&patterns).contains(r#""since_version": "0.1.0""#));
+    }
+
+    #[test]
+    fn test_parse_extra_pattern() {
+        let pattern = parse_extra_pattern(r"mylib=mylib\.load_model\s*\((?s:.*?)\)").unwrap();
+        assert_eq!(pattern.name, "mylib");
+        assert_eq!(pattern.severity, Status::Unsafe);
+
+        let config = crate::ScanConfig {
+            extra_patterns: vec![pattern],
+            ..Default::default()
+        };
+        let code = "a = mylib.load_model(\"org/model\")\nb = mylib.load_model(\"org/model\", revision=\"v1\")\n";
+        let findings = crate::scan_code(code, &config);
+        let summary: Vec<(&str, Status)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("mylib", Status::Unsafe), ("mylib", Status::PartiallySafe)]
+        );
+
+        assert!(matches!(
+            parse_extra_pattern("no-separator"),
+            Err(PatternDbError::InvalidSpec(_))
+        ));
+        assert!(matches!(
+            parse_extra_pattern("=regex"),
+            Err(PatternDbError::InvalidSpec(_))
+        ));
+        assert!(matches!(
+            parse_extra_pattern("broken=load("),
+            Err(PatternDbError::InvalidRegex { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_pattern_db_invalid_json() {
+        assert!(matches!(
+            parse_pattern_db("{not json"),
+            Err(PatternDbError::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,287 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+use crate::scanner::{Finding, JOBLIB_LOAD, METADATA_LOAD, Status, TORCH_LOAD};
+
+/// Placeholder for the commit SHA in a [`remediation_hint`]; the scanner cannot know it.
+pub const COMMIT_SHA_PLACEHOLDER: &str = "<commit-sha>";
+
+/// Patterns whose calls are not fixed by a `revision` argument: `joblib.load`,
+/// `torch.load`, `ModelCard.load` and XGBoost and LightGBM model files have none, MLflow
+/// pins through the model URI and OpenAI through a dated model name.
+const UNPINNABLE_PATTERNS: &[&str] = &[
+    JOBLIB_LOAD,
+    TORCH_LOAD,
+    METADATA_LOAD,
+    "xgboost_load",
+    "lightgbm_load",
+    "mlflow_load_model",
+    "openai_create",
+];
+
+/// The finding's call pinned to [`COMMIT_SHA_PLACEHOLDER`]: an existing `revision` value is
+/// replaced, otherwise `revision="<commit-sha>"` is added as the last argument
+/// (`--revision <commit-sha>` for `huggingface-cli download`).
+///
+/// `None` for ignored and safe findings and calls that cannot be pinned with a revision.
+pub fn remediation_hint(finding: &Finding) -> Option<String> {
+    if finding.status <= Status::Safe || UNPINNABLE_PATTERNS.contains(&finding.pattern.as_str()) {
+        return None;
+    }
+    let call = finding.call.as_str();
+
+    if finding.pattern == "huggingface_cli" {
+        return Some(if CLI_REVISION_RE.is_match(call) {
+            CLI_REVISION_RE
+                .replace(call, format!("--revision {COMMIT_SHA_PLACEHOLDER}"))
+                .into_owned()
+        } else {
+            format!("{call} --revision {COMMIT_SHA_PLACEHOLDER}")
+        });
+    }
+
+    if REVISION_ARG_RE.is_match(call) {
+        return Some(
+            REVISION_ARG_RE
+                .replace(call, format!("${{1}}${{2}}{COMMIT_SHA_PLACEHOLDER}${{2}}"))
+                .into_owned(),
+        );
+    }
+
+    let close = call.rfind(')')?;
+    let args = call[..close].trim_end();
+    let separator = if args.ends_with('(') {
+        ""
+    } else if args.ends_with(',') {
+        " "
+    } else {
+        ", "
+    };
+    Some(format!(
+        "{args}{separator}revision=\"{COMMIT_SHA_PLACEHOLDER}\"{}",
+        &call[close..]
+    ))
+}
+
+static CLI_REVISION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"--revision(?:\s+|=)\S+").unwrap());
+
+static REVISION_ARG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(revision\s*=\s*|["']revision["']\s*:\s*)(["'])[^"']*["']"#).unwrap()
+});
+
+/// What to do about an unsafe finding, for SBOM and VEX documents: pin it with its
+/// [`remediation_hint`], or load a reviewed local copy if it cannot be pinned.
+pub(crate) fn remediation_action(finding: &Finding) -> String {
+    remediation_hint(finding).map_or_else(
+        || {
+            format!(
+                "The {} call cannot be pinned with a revision; load a reviewed local copy \
+                 instead.",
+                finding.pattern
+            )
+        },
+        |hint| format!("Pin the download to a commit SHA: {hint}"),
+    )
+}
+
+/// Title of the pull request body written by [`render_fix_pr_body`].
+pub const FIX_PR_TITLE: &str = "Pin Hugging Face downloads to commit SHAs";
+
+/// `text` for a Markdown table cell, with pipes escaped and line breaks collapsed.
+fn table_cell(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
+/// Markdown body for a pull request fixing the unsafe `findings`: a summary table, a
+/// ```` ```diff ```` block per call with its [`remediation_hint`], and a checklist of the
+/// remaining manual steps.
+pub fn render_fix_pr_body(findings: &[Finding]) -> String {
+    let unsafe_findings: Vec<&Finding> = findings
+        .iter()
+        .filter(|f| f.status >= Status::Unsafe)
+        .collect();
+    let mut out = format!("## {FIX_PR_TITLE}\n\n");
+    if unsafe_findings.is_empty() {
+        out.push_str("No unsafe downloads were found.\n");
+        return out;
+    }
+    out.push_str(&format!(
+        "This change pins {} unsafe download{} to an immutable commit, so a compromised or \
+         renamed repository on the Hub cannot change what is loaded.\n\n",
+        unsafe_findings.len(),
+        if unsafe_findings.len() == 1 { "" } else { "s" }
+    ));
+
+    out.push_str("### Summary\n\n");
+    out.push_str("| File | Line | Pattern | Model | Status |\n");
+    out.push_str("|---|---:|---|---|---|\n");
+    for finding in &unsafe_findings {
+        let model = finding.model_name.as_ref().map_or("-".to_string(), |name| {
+            format!("`{}`", table_cell(name.as_str()))
+        });
+        out.push_str(&format!(
+            "| `{}` | {} | `{}` | {model} | {} |\n",
+            table_cell(&finding.file),
+            finding.line,
+            finding.pattern,
+            finding.status
+        ));
+    }
+
+    out.push_str("\n### Suggested fixes\n");
+    let mut unpinnable = Vec::new();
+    for finding in &unsafe_findings {
+        let Some(hint) = remediation_hint(finding) else {
+            unpinnable.push(*finding);
+            continue;
+        };
+        out.push_str(&format!(
+            "\n`{}:{}`\n\n```diff\n",
+            finding.file, finding.line
+        ));
+        for line in finding.call.lines() {
+            out.push_str(&format!("-{line}\n"));
+        }
+        for line in hint.lines() {
+            out.push_str(&format!("+{line}\n"));
+        }
+        out.push_str("```\n");
+    }
+    if !unpinnable.is_empty() {
+        out.push_str("\nThese calls have no `revision` to pin and need another fix:\n\n");
+        for finding in &unpinnable {
+            out.push_str(&format!(
+                "- `{}:{}` (`{}`)\n",
+                finding.file, finding.line, finding.pattern
+            ));
+        }
+    }
+
+    out.push_str("\n### Checklist\n\n");
+    out.push_str(
+        "- [ ] Look up the commit SHA of each model on the Hub (the repository's commit history)\n",
+    );
+    out.push_str(&format!(
+        "- [ ] Replace every `{COMMIT_SHA_PLACEHOLDER}` with the full 40 character SHA\n"
+    ));
+    if !unpinnable.is_empty() {
+        out.push_str("- [ ] Fix or suppress the calls that cannot be pinned with a revision\n");
+    }
+    out.push_str("- [ ] Check that the pinned models still load and behave as before\n");
+    out.push_str("- [ ] Re-run hfscanner and confirm no unsafe findings remain\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::scanner::{scan_code, scan_shell_script};
+
+    fn hints(code: &str) -> Vec<Option<String>> {
+        scan_code(code, &ScanConfig::default())
+            .iter()
+            .map(remediation_hint)
+            .collect()
+    }
+
+    #[test]
+    fn test_remediation_hint() {
+        let code = r#"
+a = AutoModel.from_pretrained("bert-base-uncased")
+b = AutoTokenizer.from_pretrained('gpt2', revision='main')
+c = AutoModel.from_pretrained(
+    "org/model",
+    trust_remote_code=True,
+)
+d = AutoModel.from_pretrained("gpt2", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+e = joblib.load(path)
+"#;
+        assert_eq!(
+            hints(code),
+            vec![
+                Some(r#"AutoModel.from_pretrained("bert-base-uncased", revision="<commit-sha>")"#.to_string()),
+                Some(r#"AutoTokenizer.from_pretrained('gpt2', revision='<commit-sha>')"#.to_string()),
+                Some("AutoModel.from_pretrained(\n    \"org/model\",\n    trust_remote_code=True, revision=\"<commit-sha>\")".to_string()),
+                None,
+                None,
+            ]
+        );
+
+        let kwargs = r#"llm = HuggingFacePipeline.from_model_id(model_id="gpt2", model_kwargs={"revision": "v1"})"#;
+        assert_eq!(
+            hints(kwargs),
+            vec![Some(
+                r#"HuggingFacePipeline.from_model_id(model_id="gpt2", model_kwargs={"revision": "<commit-sha>"})"#
+                    .to_string()
+            )]
+        );
+
+        let findings = scan_shell_script("huggingface-cli download org/model --revision main\n");
+        assert_eq!(
+            remediation_hint(&findings[0]).as_deref(),
+            Some("huggingface-cli download org/model --revision <commit-sha>")
+        );
+    }
+
+    #[test]
+    fn test_render_fix_pr_body() {
+        let code = r#"
+a = AutoModel.from_pretrained("bert-base-uncased")
+b = AutoModel.from_pretrained(
+    "org/model",
+    trust_remote_code=True,
+)
+c = AutoModel.from_pretrained("gpt2", revision="main")
+d = joblib.load(path)
+"#;
+        let mut findings = scan_code(code, &ScanConfig::default());
+        for finding in &mut findings {
+            finding.file = "org/repo/load.py".to_string();
+        }
+        let body = render_fix_pr_body(&findings);
+
+        assert!(body.starts_with("## Pin Hugging Face downloads to commit SHAs\n"));
+        assert!(
+            body.contains(
+                "| `org/repo/load.py` | 2 | `AutoModel` | `bert-base-uncased` | unsafe |"
+            )
+        );
+        assert!(body.contains("| `org/repo/load.py` | 8 | `joblib_load` | - | unsafe |"));
+        // Partially safe calls are left out.
+        assert!(!body.contains("| 7 |"));
+        assert!(body.contains(
+            "`org/repo/load.py:2`\n\n```diff\n\
+             -AutoModel.from_pretrained(\"bert-base-uncased\")\n\
+             +AutoModel.from_pretrained(\"bert-base-uncased\", revision=\"<commit-sha>\")\n\
+             ```\n"
+        ));
+        assert!(body.contains(
+            "-    trust_remote_code=True,\n-)\n+AutoModel.from_pretrained(\n+    \"org/model\",\n+    trust_remote_code=True, revision=\"<commit-sha>\")\n"
+        ));
+        assert!(body.contains("- `org/repo/load.py:8` (`joblib_load`)"));
+        assert!(body.contains("- [ ] Replace every `<commit-sha>`"));
+
+        // Every fence is closed, and every line inside a diff block is a removal or addition.
+        let mut in_block = false;
+        for line in body.lines() {
+            if line.starts_with("```") {
+                assert_eq!(line == "```diff", !in_block, "unexpected fence {line:?}");
+                in_block = !in_block;
+            } else if in_block {
+                assert!(
+                    line.starts_with(['-', '+']),
+                    "unexpected diff line {line:?}"
+                );
+            }
+        }
+        assert!(!in_block);
+        assert_eq!(body.matches("```diff").count(), 2);
+
+        assert!(render_fix_pr_body(&[]).contains("No unsafe downloads were found."));
+    }
+}
@@ -0,0 +1,230 @@
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+use crate::scanner::{Status, is_commit_sha};
+
+/// Hugging Face packages checked by [`scan_requirements_file`], in normalised form.
+pub const HF_PACKAGES: &[&str] = &[
+    "transformers",
+    "huggingface-hub",
+    "datasets",
+    "diffusers",
+    "peft",
+    "timm",
+    "sentence-transformers",
+];
+
+/// A Hugging Face package listed in a requirements file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RequirementFinding {
+    /// 1-based line on which the requirement starts.
+    pub line: usize,
+    /// Package name as written in the file.
+    pub package: String,
+    /// Version specifier, e.g. `==4.35.0` or `>=4.0`; empty if there is none.
+    pub specifier: String,
+    /// Safe when pinned with `==` and a `--hash`, partially safe when only pinned with
+    /// `==`, unsafe otherwise.
+    pub status: Status,
+}
+
+/// Normalise a package name as pip does: case-insensitive, with runs of `-`, `_` and `.`
+/// equivalent.
+fn normalize_package(name: &str) -> String {
+    PACKAGE_SEPARATOR_RE
+        .replace_all(&name.to_lowercase(), "-")
+        .into_owned()
+}
+
+static PACKAGE_SEPARATOR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[-_.]+").unwrap());
+
+/// The requirements in `content` as `(1-based start line, requirement)`, with
+/// backslash-continued lines joined and trailing comments stripped.
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut logical = String::new();
+    let mut start_line = 0;
+    for (i, raw) in content.lines().enumerate() {
+        if logical.is_empty() {
+            start_line = i + 1;
+        }
+        // Backslash-continued lines form one requirement, typically with `--hash` options.
+        if let Some(continued) = raw.strip_suffix('\\') {
+            logical.push_str(continued);
+            logical.push(' ');
+            continue;
+        }
+        logical.push_str(raw);
+        let line = std::mem::take(&mut logical);
+        let line = line.split(" #").next().unwrap_or_default().trim();
+        lines.push((start_line, line.to_string()));
+    }
+    lines
+}
+
+/// Find the Hugging Face packages in a `requirements.txt` and classify how well their
+/// version is pinned. Other packages, options and `-r` includes are ignored.
+pub fn scan_requirements_file(content: &str) -> Vec<RequirementFinding> {
+    let mut findings = Vec::new();
+    for (start_line, line) in logical_lines(content) {
+        if line.starts_with('#') || line.starts_with('-') {
+            continue;
+        }
+        let Some(caps) = REQUIREMENT_RE.captures(&line) else {
+            continue;
+        };
+        if !HF_PACKAGES.contains(&normalize_package(&caps[1]).as_str()) {
+            continue;
+        }
+        // Drop environment markers (`; python_version < "3.8"`) and per-requirement options.
+        let specifier: String = caps[2]
+            .split(';')
+            .next()
+            .and_then(|spec| spec.split(" --").next())
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect();
+        let status = if !PINNED_RE.is_match(&specifier) {
+            Status::Unsafe
+        } else if line.contains("--hash=") {
+            Status::Safe
+        } else {
+            Status::PartiallySafe
+        };
+        findings.push(RequirementFinding {
+            line: start_line,
+            package: caps[1].to_string(),
+            specifier,
+            status,
+        });
+    }
+    findings
+}
+
+static REQUIREMENT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z0-9][A-Za-z0-9._-]*)\s*(?:\[[^\]]*\])?(.*)$").unwrap());
+
+static PINNED_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^===?\s*[^*,]+$").unwrap());
+
+/// Find the `git+https://` dependencies in a `requirements.txt`, e.g.
+/// `transformers @ git+https://github.com/huggingface/transformers.git@main` or
+/// `-e git+https://github.com/org/repo.git@v1.0#egg=repo`, whatever the package. A
+/// dependency pinned to a full commit SHA is safe, one on a branch or tag partially safe
+/// and one without an `@<ref>` unsafe. `--hash` options do not count: pip cannot check
+/// them for VCS checkouts.
+///
+/// The package is the name before `@`, else the `#egg=` fragment, else the repository
+/// name; the specifier is the `@<ref>` suffix, or empty.
+pub fn scan_requirements_txt(content: &str) -> Vec<RequirementFinding> {
+    let mut findings = Vec::new();
+    for (start_line, line) in logical_lines(content) {
+        let Some(caps) = GIT_REQUIREMENT_RE.captures(&line) else {
+            continue;
+        };
+        let package = caps
+            .get(1)
+            .map(|name| name.as_str().to_string())
+            .or_else(|| {
+                let fragment = caps.get(4)?.as_str();
+                Some(EGG_RE.captures(fragment)?[1].to_string())
+            })
+            .unwrap_or_else(|| caps[2].rsplit('/').next().unwrap_or_default().to_string());
+        let (specifier, status) = match caps.get(3).map(|r| r.as_str()) {
+            Some(reference) if is_commit_sha(reference) => (format!("@{reference}"), Status::Safe),
+            Some(reference) => (format!("@{reference}"), Status::PartiallySafe),
+            None => (String::new(), Status::Unsafe),
+        };
+        findings.push(RequirementFinding {
+            line: start_line,
+            package,
+            specifier,
+            status,
+        });
+    }
+    findings
+}
+
+static GIT_REQUIREMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:(?:-e|--editable)\s+)?(?:([A-Za-z0-9][A-Za-z0-9._-]*)\s*(?:\[[^\]]*\])?\s*@\s*)?git\+https://([^\s@#;]+?)(?:\.git)?(?:@([^\s#;]+))?(?:#(\S*))?(?:\s|;|$)",
+    )
+    .unwrap()
+});
+
+static EGG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|&)egg=([^&]+)").unwrap());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_requirements_file() {
+        let content = "\
+# model stack
+-r base.txt
+numpy
+transformers
+huggingface_hub>=0.20
+datasets==2.16.1
+Sentence.Transformers[train] == 2.2.2  # pinned
+diffusers==0.25.*
+transformers==4.35.0 \\
+    --hash=sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef
+peft>=0.7,<0.8
+timm==0.9.12 --hash=sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef
+";
+        let findings = scan_requirements_file(content);
+        let summary: Vec<(usize, &str, &str, Status)> = findings
+            .iter()
+            .map(|f| (f.line, f.package.as_str(), f.specifier.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (4, "transformers", "", Status::Unsafe),
+                (5, "huggingface_hub", ">=0.20", Status::Unsafe),
+                (6, "datasets", "==2.16.1", Status::PartiallySafe),
+                (7, "Sentence.Transformers", "==2.2.2", Status::PartiallySafe),
+                (8, "diffusers", "==0.25.*", Status::Unsafe),
+                (9, "transformers", "==4.35.0", Status::Safe),
+                (11, "peft", ">=0.7,<0.8", Status::Unsafe),
+                (12, "timm", "==0.9.12", Status::Safe),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_requirements_txt() {
+        let content = "\
+numpy==1.26.0
+transformers @ git+https://github.com/huggingface/transformers.git@main
+peft @ git+https://github.com/huggingface/peft.git@v0.7.1 ; python_version >= \"3.8\"
+accelerate @ git+https://github.com/huggingface/accelerate
+-e git+https://github.com/org/tools.git@5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d#egg=org-tools
+git+https://github.com/org/models.git
+diffusers @ git+https://github.com/huggingface/diffusers.git@main \\
+    --hash=sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef
+timm @ git+https://github.com/huggingface/pytorch-image-models.git@5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d --hash=sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef
+# datasets @ git+https://github.com/huggingface/datasets
+";
+        let findings = scan_requirements_txt(content);
+        let summary: Vec<(usize, &str, &str, Status)> = findings
+            .iter()
+            .map(|f| (f.line, f.package.as_str(), f.specifier.as_str(), f.status))
+            .collect();
+        let sha = "@5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d";
+        assert_eq!(
+            summary,
+            vec![
+                (2, "transformers", "@main", Status::PartiallySafe),
+                (3, "peft", "@v0.7.1", Status::PartiallySafe),
+                (4, "accelerate", "", Status::Unsafe),
+                (5, "org-tools", sha, Status::Safe),
+                (6, "models", "", Status::Unsafe),
+                (7, "diffusers", "@main", Status::PartiallySafe),
+                (9, "timm", sha, Status::Safe),
+            ]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use crate::scanner::{Finding, Status, uses_trust_remote_code};
+
+/// Patterns whose calls unpickle the downloaded file, which is worse than loading weights
+/// from an unpinned revision. `joblib_load` is built in; pattern databases can use the
+/// other names.
+pub const PICKLE_PATTERNS: &[&str] = &["pickle_load", "torch_load", "joblib_load"];
+
+/// Weights for [`risk_score`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskWeights {
+    pub safe: f64,
+    pub partial: f64,
+    pub metadata_load: f64,
+    pub local_file_load: f64,
+    pub api_version_unpinned: f64,
+    pub remote_call: f64,
+    pub unsafe_: f64,
+    /// Replaces `unsafe_` for unsafe findings of a [`PICKLE_PATTERNS`] pattern.
+    pub pickle_unsafe: f64,
+    /// Replaces `unsafe_` for findings with [`Status::PathTraversal`].
+    pub path_traversal: f64,
+    /// Added for every call passing `trust_remote_code=True`, whatever its status.
+    pub trust_remote_code: f64,
+    /// Multiplies the weight of unsafe findings inside a loop.
+    pub loop_multiplier: f64,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        RiskWeights {
+            safe: 0.0,
+            partial: 1.0,
+            metadata_load: 1.0,
+            local_file_load: 1.0,
+            api_version_unpinned: 1.0,
+            remote_call: 5.0,
+            unsafe_: 10.0,
+            pickle_unsafe: 20.0,
+            path_traversal: 50.0,
+            trust_remote_code: 50.0,
+            loop_multiplier: 10.0,
+        }
+    }
+}
+
+/// Composite risk of a set of findings, for dashboards that want a single number.
+pub fn risk_score(findings: &[Finding], weights: &RiskWeights) -> f64 {
+    findings
+        .iter()
+        .map(|finding| {
+            let mut score = match finding.status {
+                Status::Ignored => return 0.0,
+                Status::Safe => weights.safe,
+                Status::PartiallySafe => weights.partial,
+                Status::MetadataLoad => weights.metadata_load,
+                Status::LocalFileLoad => weights.local_file_load,
+                Status::ApiVersionUnpinned => weights.api_version_unpinned,
+                Status::RemoteCall => weights.remote_call,
+                Status::Unsafe if PICKLE_PATTERNS.contains(&finding.pattern.as_str()) => {
+                    weights.pickle_unsafe
+                }
+                Status::Unsafe | Status::TrainingWithUnsafeBase => weights.unsafe_,
+                Status::PathTraversal => weights.path_traversal,
+            };
+            if finding.status >= Status::Unsafe && finding.in_loop {
+                score *= weights.loop_multiplier;
+            }
+            if uses_trust_remote_code(&finding.call) {
+                score += weights.trust_remote_code;
+            }
+            score
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::scanner::scan_code;
+
+    fn finding(pattern: &str, status: Status, call: &str) -> Finding {
+        Finding {
+            file: String::new(),
+            line: 1,
+            pattern: pattern.to_string(),
+            status,
+            call: call.to_string(),
+            model_name: None,
+            in_loop: false,
+            in_init_empty_weights: false,
+            in_test_fixture: false,
+            enclosing_function: None,
+            shared_cache_dir: false,
+            incomplete_download: false,
+            private_repo: false,
+            context: String::new(),
+            cwe: Vec::new(),
+            suppression_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_risk_score_weights() {
+        let findings = vec![
+            finding("AutoModel", Status::Safe, "AutoModel.from_pretrained(x)"),
+            finding(
+                "AutoModel",
+                Status::PartiallySafe,
+                "AutoModel.from_pretrained(x)",
+            ),
+            finding("AutoModel", Status::Unsafe, "AutoModel.from_pretrained(x)"),
+            finding("torch_load", Status::Unsafe, "torch.load(x)"),
+            finding(
+                "AutoModel",
+                Status::Unsafe,
+                "AutoModel.from_pretrained(x, trust_remote_code=True)",
+            ),
+        ];
+        // 0 + 1 + 10 + 20 + (10 + 50)
+        assert_eq!(risk_score(&findings, &RiskWeights::default()), 91.0);
+
+        let weights = RiskWeights {
+            partial: 0.0,
+            trust_remote_code: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(risk_score(&findings, &weights), 40.0);
+        assert_eq!(risk_score(&[], &weights), 0.0);
+    }
+
+    #[test]
+    fn test_risk_score_in_loop() {
+        let direct = scan_code(
+            "model = AutoModel.from_pretrained(name)\n",
+            &ScanConfig::default(),
+        );
+        let looped = scan_code(
+            "for name in tqdm(names):\n    model = AutoModel.from_pretrained(name)\n",
+            &ScanConfig::default(),
+        );
+        let weights = RiskWeights::default();
+        assert_eq!(risk_score(&direct, &weights), 10.0);
+        assert_eq!(risk_score(&looped, &weights), 100.0);
+    }
+}
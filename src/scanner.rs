@@ -0,0 +1,2516 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::aliases::ImportAliasMap;
+use crate::cache::content_hash;
+use crate::config::{EncodingMode, ScanConfig};
+use crate::cwe::CweId;
+
+/// Ordered from least to most severe.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Detected but deliberately suppressed; see [`Finding::suppression_reason`]. Not
+    /// counted as safe, partial or unsafe.
+    Ignored,
+    Safe,
+    PartiallySafe,
+    /// An unpinned load of Hub metadata such as a model card. No weights or code are
+    /// downloaded, but tools that parse the card's YAML can be fed poisoned metadata.
+    MetadataLoad,
+    /// A serialised model file loaded from a local path or a local URL by a library that
+    /// does not use the Hub, such as XGBoost or LightGBM. There is no revision to pin; the
+    /// risk is where the file came from.
+    LocalFileLoad,
+    /// An OpenAI API call whose `model` is an alias such as `gpt-4` rather than a dated
+    /// snapshot such as `gpt-4-0314`, so the model behind it can change between minor
+    /// versions. Nothing is downloaded, so this is not a supply-chain risk.
+    ApiVersionUnpinned,
+    /// An unpinned call to a model served remotely, e.g. through the Inference API. The
+    /// model name can still be hijacked, but no model code runs locally.
+    RemoteCall,
+    Unsafe,
+    /// An unsafe `from_pretrained` in a file that also fine-tunes with a `Trainer`, so the
+    /// unpinned weights end up in the newly trained model.
+    TrainingWithUnsafeBase,
+    /// A call whose `subfolder` has `..` components and so reads files outside the model
+    /// directory, whatever revision it is pinned to.
+    PathTraversal,
+}
+
+impl Status {
+    /// All statuses, from least to most severe.
+    pub const ALL: [Status; 10] = [
+        Status::Ignored,
+        Status::Safe,
+        Status::PartiallySafe,
+        Status::MetadataLoad,
+        Status::LocalFileLoad,
+        Status::ApiVersionUnpinned,
+        Status::RemoteCall,
+        Status::Unsafe,
+        Status::TrainingWithUnsafeBase,
+        Status::PathTraversal,
+    ];
+}
+
+/// The same snake_case name the status is serialised as.
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Ignored => "ignored",
+            Status::Safe => "safe",
+            Status::PartiallySafe => "partially_safe",
+            Status::MetadataLoad => "metadata_load",
+            Status::LocalFileLoad => "local_file_load",
+            Status::ApiVersionUnpinned => "api_version_unpinned",
+            Status::RemoteCall => "remote_call",
+            Status::Unsafe => "unsafe",
+            Status::TrainingWithUnsafeBase => "training_with_unsafe_base",
+            Status::PathTraversal => "path_traversal",
+        })
+    }
+}
+
+/// Why a finding has [`Status::Ignored`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuppressionReason {
+    /// A `# noscan` comment on one of the call's lines.
+    NoscanComment,
+    /// A path or pattern listed in an ignore file. Reserved for the JSON output: no ignore
+    /// file is read yet, so only [`SuppressionReason::NoscanComment`] is set today.
+    IgnoreFile,
+}
+
+/// A single model, dataset or file download call found in the scanned code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    /// Path relative to the scan root; empty when the code did not come from a file.
+    pub file: String,
+    /// 1-based line on which the call starts.
+    pub line: usize,
+    /// Name of the [`Pattern`] that matched.
+    pub pattern: String,
+    pub status: Status,
+    /// The matched call expression.
+    pub call: String,
+    /// First string argument of the call, if it has one.
+    pub model_name: Option<ModelName>,
+    /// The call is in the body of a `for` or `while` loop and may run many times.
+    pub in_loop: bool,
+    /// The call is inside a `with init_empty_weights():` block from Accelerate.
+    pub in_init_empty_weights: bool,
+    /// The call is inside a `@pytest.fixture` function or a unittest `setUp` method, so it
+    /// only runs in tests.
+    pub in_test_fixture: bool,
+    /// Name of the innermost `def` the call is in; `None` at module level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enclosing_function: Option<String>,
+    /// The call downloads into a world-writable `cache_dir` (`/tmp/`, `/var/tmp/`,
+    /// `/dev/shm/`), where another user can swap the files between download and load.
+    pub shared_cache_dir: bool,
+    /// A `snapshot_download` whose `ignore_patterns` skip weight files (`*.bin`, `*.pt`,
+    /// `*.safetensors`), so only part of the repository is fetched; see
+    /// [`excludes_weight_files`].
+    #[serde(default)]
+    pub incomplete_download: bool,
+    /// The call is in a repository listed in [`ScanConfig::private_repos`]; if it was
+    /// [`Status::Unsafe`] it is reported as partially safe.
+    #[serde(default)]
+    pub private_repo: bool,
+    /// Source lines around the call when [`ScanConfig::context_lines`] is set, otherwise empty.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub context: String,
+    /// Applicable weaknesses, filled in by [`annotate_cwe`]; empty otherwise.
+    ///
+    /// [`annotate_cwe`]: crate::cwe::annotate_cwe
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cwe: Vec<CweId>,
+    /// Set for [`Status::Ignored`] findings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suppression_reason: Option<SuppressionReason>,
+}
+
+/// The model, dataset or repository name passed to a call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum ModelName {
+    /// A plain, raw (`r"..."`) or byte (`b"..."`) string literal, without prefix or quotes.
+    Literal(String),
+    /// An f-string template such as `model-{version}`; the final name is only known at
+    /// runtime, so a pinned revision cannot be trusted to refer to the intended repository.
+    DynamicName(String),
+    /// A `<model_N>` placeholder set by [`anonymise_model_names`] in place of the real name.
+    ///
+    /// [`anonymise_model_names`]: crate::models::anonymise_model_names
+    Anonymised(String),
+}
+
+impl ModelName {
+    /// The name or template, without its kind.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ModelName::Literal(name)
+            | ModelName::DynamicName(name)
+            | ModelName::Anonymised(name) => name,
+        }
+    }
+}
+
+/// Extract the name passed as the first argument of `call`, or as its
+/// `pretrained_model_name_or_path=` or `model_name_or_path=` keyword argument.
+///
+/// For chained calls such as `importlib.import_module("transformers").AutoModel.from_pretrained(...)`
+/// the `from_pretrained` arguments are used, and when `from_pretrained` is passed as a
+/// callable, as in `asyncio.to_thread(AutoModel.from_pretrained, "x")`, the argument after
+/// it. Names that are not string literals yield `None`.
+pub fn extract_model_name(call: &str) -> Option<ModelName> {
+    let start = call.find("from_pretrained").unwrap_or(0);
+    let args = &call[start..];
+    let caps = CALLABLE_NAME_RE
+        .captures(args)
+        .or_else(|| POSITIONAL_NAME_RE.captures(args))
+        .or_else(|| KEYWORD_NAME_RE.captures(args))?;
+    let name = caps.get(2).or_else(|| caps.get(3))?.as_str().to_string();
+    if caps[1].contains(['f', 'F']) {
+        Some(ModelName::DynamicName(name))
+    } else {
+        Some(ModelName::Literal(name))
+    }
+}
+
+static CALLABLE_NAME_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^from_pretrained\s*,\s*([rRbBuUfF]{0,2})(?:"([^"]*)"|'([^']*)')"#).unwrap()
+});
+
+static POSITIONAL_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^[^(]*\(\s*([rRbBuUfF]{0,2})(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
+static KEYWORD_NAME_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\b(?:pretrained_model_name_or_path|model_name_or_path)\s*=\s*([rRbBuUfF]{0,2})(?:"([^"]*)"|'([^']*)')"#,
+    )
+    .unwrap()
+});
+
+/// Extract the `revision="..."` argument of `call`, also accepted as a `"revision": "..."`
+/// kwargs dict entry.
+pub fn extract_revision(call: &str) -> Option<String> {
+    let caps = REVISION_CAPTURE_RE.captures(call)?;
+    Some(caps.get(1).or_else(|| caps.get(2))?.as_str().to_string())
+}
+
+pub fn is_commit_sha(s: &str) -> bool {
+    COMMIT_SHA_RE.is_match(s)
+}
+
+static COMMIT_SHA_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-f0-9]{40}$").unwrap());
+
+/// Minimum Shannon entropy, in bits per character, of a plausible full commit SHA. Random
+/// 40 character hex strings average about 3.7.
+const MIN_SHA_ENTROPY: f64 = 3.0;
+
+/// Whether the hex digits of `sha` look random: no short repeating unit such as `0000...`
+/// or `abab...`, and for full SHAs enough entropy. Abbreviated SHAs are too short for a
+/// meaningful entropy check.
+fn looks_random(sha: &str) -> bool {
+    let bytes = sha.as_bytes();
+    let periodic = (1..=bytes.len() / 2)
+        .any(|period| (period..bytes.len()).all(|i| bytes[i] == bytes[i - period]));
+    if periodic {
+        return false;
+    }
+    if bytes.len() < 40 {
+        return true;
+    }
+    let mut counts = [0usize; 256];
+    for &b in bytes {
+        counts[b as usize] += 1;
+    }
+    let len = bytes.len() as f64;
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+    entropy >= MIN_SHA_ENTROPY
+}
+
+/// [`is_commit_sha`], but also rejecting SHAs that are unlikely to name a real commit,
+/// such as all zeros or a repeated pattern.
+pub fn is_plausible_commit_sha(s: &str) -> bool {
+    is_commit_sha(s) && looks_random(s)
+}
+
+/// Whether the call pins a revision that is shaped like a commit SHA but fails the
+/// randomness check of [`is_plausible_commit_sha`].
+pub fn has_suspicious_sha(call: &str) -> bool {
+    extract_revision(call).is_some_and(|rev| is_abbreviated_sha(&rev) && !looks_random(&rev))
+}
+
+/// Matches abbreviated SHAs as accepted by git (`5d0f2e8`) as well as full 40 character ones.
+pub fn is_abbreviated_sha(s: &str) -> bool {
+    ABBREVIATED_SHA_RE.is_match(s)
+}
+
+static ABBREVIATED_SHA_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-f0-9]{7,40}$").unwrap());
+
+/// Whether a matched call passes `trust_remote_code=True`, which lets the model repository
+/// run arbitrary code on load.
+pub fn uses_trust_remote_code(call: &str) -> bool {
+    TRUST_REMOTE_CODE_RE.is_match(call)
+}
+
+static TRUST_REMOTE_CODE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"trust_remote_code\s*=\s*True").unwrap());
+
+/// Whether a matched call passes `ignore_patterns` that skip weight files, such as
+/// `ignore_patterns=["*.bin"]`: a pattern ending in `.bin`, `.pt` or `.safetensors`.
+pub fn excludes_weight_files(call: &str) -> bool {
+    let Some(caps) = IGNORE_PATTERNS_RE.captures(call) else {
+        return false;
+    };
+    STRING_LITERAL_RE.captures_iter(&caps[1]).any(|pattern| {
+        [".bin", ".pt", ".safetensors"]
+            .iter()
+            .any(|ext| pattern[1].ends_with(ext))
+    })
+}
+
+static IGNORE_PATTERNS_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"ignore_patterns\s*=\s*(\[[^\]]*\]|\([^)]*\)|["'][^"']*["'])"#).unwrap()
+});
+
+static STRING_LITERAL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"["']([^"']*)["']"#).unwrap());
+
+/// Whether a matched call passes a `subfolder` with a `..` component, such as
+/// `subfolder="../../etc"`, which escapes the model directory.
+pub fn uses_traversal_subfolder(call: &str) -> bool {
+    SUBFOLDER_RE.captures_iter(call).any(|caps| {
+        caps[1]
+            .split(['/', '\\'])
+            .any(|component| component == "..")
+    })
+}
+
+static SUBFOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"subfolder\s*=\s*["']([^"']+)["']"#).unwrap());
+
+/// Whether a matched call passes a `cache_dir` in a world-writable location.
+pub fn uses_shared_cache_dir(call: &str) -> bool {
+    SHARED_CACHE_DIR_RE.is_match(call)
+}
+
+static SHARED_CACHE_DIR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"cache_dir\s*=\s*[rRfF]?["'](?:/tmp/|/var/tmp/|/dev/shm/)"#).unwrap()
+});
+
+/// A named call pattern. `severity` is the status given to a call without a pinned
+/// revision; a pinned call is never reported above it.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub name: String,
+    pub regex: Regex,
+    pub severity: Status,
+    /// Release that introduced a built-in pattern; empty for user-defined patterns.
+    pub since_version: &'static str,
+}
+
+/// Release of the built-in patterns that predate version tracking. Patterns added since
+/// carry the release they first ship in.
+const FIRST_RELEASE: &str = "0.1.0";
+
+/// Release that introduced the `huggingface-cli download` shell pattern.
+const HUGGINGFACE_CLI_SINCE: &str = "0.2.0";
+
+impl Pattern {
+    fn builtin(name: &str, regex: &str) -> Self {
+        Pattern {
+            name: name.to_string(),
+            regex: Regex::new(regex).unwrap(),
+            severity: Status::Unsafe,
+            since_version: FIRST_RELEASE,
+        }
+    }
+
+    fn with_severity(mut self, severity: Status) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    fn since(mut self, version: &'static str) -> Self {
+        self.since_version = version;
+        self
+    }
+}
+
+/// `major.minor.patch` as a comparable tuple, with an optional leading `v`. Missing
+/// minor and patch numbers are 0.
+pub fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.strip_prefix('v').unwrap_or(version).split('.');
+    let mut next = || parts.next().map_or(Some(0), |part| part.parse().ok());
+    let parsed = (next()?, next()?, next()?);
+    parts.next().is_none().then_some(parsed)
+}
+
+/// Names of the built-in patterns, including the `huggingface_cli` shell pattern,
+/// introduced after release `version`. `None` if `version` cannot be parsed.
+pub fn patterns_added_since(version: &str) -> Option<Vec<String>> {
+    let version = parse_version(version)?;
+    let newer = |since: &str| parse_version(since).is_some_and(|since| since > version);
+    let mut names: Vec<String> = builtin_patterns()
+        .into_iter()
+        .filter(|pattern| newer(pattern.since_version))
+        .map(|pattern| pattern.name)
+        .collect();
+    // `scan_shell_script` matches its command without a `Pattern`.
+    if newer(HUGGINGFACE_CLI_SINCE) {
+        names.push("huggingface_cli".to_string());
+    }
+    Some(names)
+}
+
+/// The patterns compiled into the scanner.
+pub fn builtin_patterns() -> Vec<Pattern> {
+    BUILTIN_PATTERNS.clone()
+}
+
+/// The built-in patterns, compiled on first use and shared by every scan.
+static BUILTIN_PATTERNS: LazyLock<Vec<Pattern>> = LazyLock::new(compile_builtin_patterns);
+
+/// Dynamic imports come first so the `AutoModel.from_pretrained` tail of
+/// `importlib.import_module("transformers").AutoModel.from_pretrained(...)` is not
+/// counted a second time by the plain patterns below.
+fn compile_builtin_patterns() -> Vec<Pattern> {
+    vec![
+        Pattern::builtin(
+            "dynamic_import",
+            r#"(?:importlib\.import_module|__import__)\s*\(\s*["']transformers["']\s*\)(?:\.\w+)*\.from_pretrained\s*\((?s:.*?)\)"#,
+        )
+        .since("0.2.0"),
+        Pattern::builtin(
+            "AutoModel",
+            r#"AutoModel\w*\.from_pretrained\s*\((?s:.*?)\)"#,
+        ),
+        Pattern::builtin(
+            "AutoTokenizer",
+            r#"AutoTokenizer\.from_pretrained\s*\((?s:.*?)\)"#,
+        ),
+        Pattern::builtin("AutoClass", r#"Auto\w+\.from_pretrained\s*\((?s:.*?)\)"#)
+            .since("0.2.0"),
+        // `from_pretrained` passed as a callable, as in `await asyncio.to_thread(
+        // AutoModel.from_pretrained, "x", revision=...)` or `functools.partial(...)`; the
+        // match runs to the end of the wrapping call so its keyword arguments are checked for
+        // a revision. A `partial` wrapper is part of the reported call.
+        Pattern::builtin(
+            "callable_from_pretrained",
+            r#"(?:\b(?:functools\.)?partial\s*\(\s*)?(?:\b[\w.]+\.)?\bfrom_pretrained\s*,\s*[rRbBuUfF]{0,2}["'][^"']+["'](?s:.*?)\)"#,
+        )
+        .since("0.2.0"),
+        Pattern::builtin(
+            "speech_model",
+            r#"\b(?:Wav2Vec2|Whisper|Speech|Hubert)\w*\.from_pretrained\s*\((?s:.*?)\)"#,
+        )
+        .since("0.2.0"),
+        // Concrete vision-language classes (BLIP-2, LLaVA, ...) that do not start with `Auto`.
+        Pattern::builtin(
+            "conditional_generation",
+            r#"\b\w+ForConditional\w+\.from_pretrained\s*\((?s:.*?)\)"#,
+        )
+        .since("0.2.0"),
+        // llama-cpp-python GGUF downloads, directly or through LangChain's `LlamaCpp`.
+        Pattern::builtin(
+            "llama_cpp",
+            r#"\b(?:Llama|LlamaCpp)\.from_pretrained\s*\((?s:.*?)\)"#,
+        )
+        .since("0.2.0"),
+        // Pipeline bundles are pinned by the installed torchaudio version, never by a
+        // revision, so they cannot be better than partially safe.
+        Pattern::builtin(
+            "torchaudio_pipeline",
+            r#"torchaudio\.pipelines\.[A-Z0-9_]+"#,
+        )
+        .with_severity(Status::PartiallySafe)
+        .since("0.2.0"),
+        Pattern::builtin(
+            "langchain",
+            r#"\b(?:HuggingFacePipeline\.from_model_id|HuggingFaceHub|HuggingFaceEndpoint)\s*\((?s:.*?)\)"#,
+        )
+        .since("0.2.0"),
+        Pattern::builtin(
+            "mlflow_load_model",
+            r#"mlflow\.\w+\.load_model\s*\((?s:.*?)\)"#,
+        )
+        .since("0.2.0"),
+        // Only reported in files importing safetensors, see `IMPORT_GATED_PATTERNS`.
+        Pattern::builtin(
+            SAFETENSORS_LOAD_FILE,
+            r#"\b(?:safetensors\.\w+\.)?load_file\s*\((?s:.*?)\)"#,
+        )
+        .with_severity(Status::PartiallySafe)
+        .since("0.2.0"),
+        Pattern::builtin("vllm", r#"\b(?:vllm\.)?LLM\s*\((?s:.*?)\)"#)
+            .since("0.2.0"),
+        Pattern::builtin(
+            "inference_api",
+            r#"\bInference(?:Client|Api)\s*\((?s:.*?)\)"#,
+        )
+        .with_severity(Status::RemoteCall)
+        .since("0.2.0"),
+        Pattern::builtin(OPENAI_CREATE, r#"\bopenai\.\w+\.create\s*\((?s:.*?)\)"#)
+            .with_severity(Status::ApiVersionUnpinned)
+            .since("0.2.0"),
+        Pattern::builtin(
+            METADATA_LOAD,
+            r#"\b(?:ModelCard\.load|DatasetInfo\.from_hf_datasets_cache)\s*\((?s:.*?)\)"#,
+        )
+        .with_severity(Status::MetadataLoad)
+        .since("0.2.0"),
+        // `xgb.Booster().load_model("model.json")`, `lgb.Booster(model_file="model.txt")`.
+        Pattern::builtin(
+            "xgboost_load",
+            r#"\b(?:xgb|xgboost)\.\w+\s*\((?:[^)]*\bmodel_file\s*=[^)]*|[^)]*\)\s*\.load_model\s*\([^)]*)\)"#,
+        )
+        .since("0.2.0"),
+        Pattern::builtin(
+            "lightgbm_load",
+            r#"\b(?:lgb|lightgbm)\.\w+\s*\((?:[^)]*\bmodel_file\s*=[^)]*|[^)]*\)\s*\.load_model\s*\([^)]*)\)"#,
+        )
+        .since("0.2.0"),
+        Pattern::builtin("load_dataset", r#"load_dataset\s*\((?s:.*?)\)"#),
+        // Reads a dataset saved with `save_to_disk` and never touches the network.
+        Pattern::builtin("load_from_disk", r#"\bload_from_disk\s*\((?s:.*?)\)"#)
+            .with_severity(Status::Safe)
+            .since("0.2.0"),
+        Pattern::builtin("evaluate_load", r#"\bevaluate\.load\s*\((?s:.*?)\)"#)
+            .since("0.2.0"),
+        // Unpickles its file and has no revision to pin, so it is always unsafe.
+        Pattern::builtin(JOBLIB_LOAD, r#"\bjoblib\.load\s*\((?s:.*?)\)"#)
+            .since("0.2.0"),
+        // Unpickles its file unless `weights_only=True`, and has no revision to pin.
+        Pattern::builtin(TORCH_LOAD, r#"\btorch\.load\s*\((?:[^()]|\([^()]*\))*\)"#)
+            .since("0.2.0"),
+        Pattern::builtin("hf_hub_download", r#"hf_hub_download\s*\((?s:.*?)\)"#),
+        Pattern::builtin("snapshot_download", r#"snapshot_download\s*\((?s:.*?)\)"#),
+    ]
+}
+
+/// The patterns [`scan_code`] checks with `config`: the built-in ones, unless
+/// [`ScanConfig::no_builtin_patterns`] is set, followed by [`ScanConfig::extra_patterns`].
+pub fn active_patterns(config: &ScanConfig) -> Vec<Pattern> {
+    active_pattern_refs(config).cloned().collect()
+}
+
+/// [`active_patterns`] without cloning the compiled regexes.
+fn active_pattern_refs(config: &ScanConfig) -> impl Iterator<Item = &Pattern> {
+    let builtins: &[Pattern] = if config.no_builtin_patterns {
+        &[]
+    } else {
+        &BUILTIN_PATTERNS
+    };
+    builtins.iter().chain(&config.extra_patterns)
+}
+
+fn is_loop_header(line: &str) -> bool {
+    let line = line.trim_start();
+    ["for ", "async for ", "while "]
+        .iter()
+        .any(|keyword| line.starts_with(keyword))
+}
+
+/// Headers of the blocks enclosing the code at `offset`, innermost first, judged by
+/// indentation: any preceding line indented less than the current block opens it. The
+/// text before `offset` on its own line comes first, so single-line blocks
+/// (`for name in names: AutoModel.from_pretrained(name)`) count as well.
+fn enclosing_block_headers(code: &str, offset: usize) -> Vec<&str> {
+    let line_start = code[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &code[line_start..];
+    let mut headers = vec![&code[line_start..offset]];
+
+    let indent_of = |l: &str| l.len() - l.trim_start().len();
+    let mut indent = indent_of(line);
+    for previous in code[..line_start].lines().rev() {
+        if indent == 0 {
+            break;
+        }
+        let trimmed = previous.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if indent_of(previous) < indent {
+            headers.push(previous);
+            indent = indent_of(previous);
+        }
+    }
+    headers
+}
+
+/// Whether the code at `offset` is inside a loop body.
+fn is_in_loop(code: &str, offset: usize) -> bool {
+    enclosing_block_headers(code, offset)
+        .into_iter()
+        .any(is_loop_header)
+}
+
+/// Whether the code at `offset` runs inside `with init_empty_weights():`, i.e. Accelerate
+/// builds the model skeleton without allocating weights, as done for big-model offloading.
+fn is_in_init_empty_weights(code: &str, offset: usize) -> bool {
+    enclosing_block_headers(code, offset)
+        .into_iter()
+        .any(|header| {
+            let header = header.trim_start();
+            (header.starts_with("with ") || header.starts_with("async with "))
+                && header.contains("init_empty_weights(")
+        })
+}
+
+/// Whether a line spanned by `code[start..end]` has a `# noscan` comment.
+fn has_noscan_comment(code: &str, start: usize, end: usize) -> bool {
+    let first = code[..start].rfind('\n').map_or(0, |i| i + 1);
+    let last = code[end..].find('\n').map_or(code.len(), |i| end + i);
+    NOSCAN_RE.is_match(&code[first..last])
+}
+
+static NOSCAN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"#\s*noscan\b").unwrap());
+
+/// Whether the code at `offset` is inside a test fixture: a function decorated with
+/// `@pytest.fixture` (or `@pytest_asyncio.fixture`, `@fixture(...)`) or a unittest
+/// `setUp`, `setUpClass` or `asyncSetUp` method.
+fn is_in_test_fixture(code: &str, offset: usize) -> bool {
+    let headers = enclosing_block_headers(code, offset);
+    let Some(&def) = headers.iter().find(|header| DEF_RE.is_match(header)) else {
+        return false;
+    };
+    if SETUP_RE.is_match(def) {
+        return true;
+    }
+    // Decorators are on the lines right above the `def`, which is a slice of `code`.
+    let def_start = def.as_ptr().addr() - code.as_ptr().addr();
+    code[..def_start]
+        .lines()
+        .rev()
+        .map(|line| line.trim_start())
+        .take_while(|line| line.starts_with('@'))
+        .any(|line| FIXTURE_RE.is_match(line))
+}
+
+static SETUP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:async\s+)?def\s+(?:setUp|setUpClass|asyncSetUp)\s*\(").unwrap()
+});
+
+static FIXTURE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^@(?:\w+\.)*fixture\b").unwrap());
+
+static DEF_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*(?:async\s+)?def\s").unwrap());
+
+/// Name of the innermost function or method whose body contains the code at `offset`.
+fn enclosing_function(code: &str, offset: usize) -> Option<String> {
+    enclosing_block_headers(code, offset)
+        .into_iter()
+        .find_map(|header| Some(DEF_NAME_RE.captures(header)?[1].to_string()))
+}
+
+static DEF_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:async\s+)?def\s+(\w+)\s*\(").unwrap());
+
+/// Classify an MLflow registry URI (`models:/<name>/<version>` or `models:/<name>@<alias>`)
+/// in `call`. Numbered versions are immutable, stages and aliases can be moved like a tag,
+/// and `latest` follows every new registration. Returns `None` if there is no such URI.
+fn mlflow_model_uri_status(call: &str) -> Option<Status> {
+    let version = MLFLOW_URI_RE.captures(call)?.get(1)?.as_str();
+    Some(if version.chars().all(|c| c.is_ascii_digit()) {
+        Status::Safe
+    } else if version.eq_ignore_ascii_case("latest") {
+        Status::Unsafe
+    } else {
+        Status::PartiallySafe
+    })
+}
+
+static MLFLOW_URI_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"["']models:/[^"'/@]+(?:/|@)([^"']+)["']"#).unwrap());
+
+/// Whether the `model` argument of an OpenAI call names a dated snapshot (`gpt-4-0314`,
+/// `gpt-4o-2024-05-13`) rather than an alias that moves to new versions.
+fn is_dated_openai_model(call: &str) -> bool {
+    DATED_OPENAI_MODEL_RE.is_match(call)
+}
+
+static DATED_OPENAI_MODEL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\bmodel\s*=\s*["'][^"']*-(?:\d{4}|\d{4}-\d{2}-\d{2})["']"#).unwrap()
+});
+
+/// Name of the built-in `openai.<Resource>.create` pattern.
+const OPENAI_CREATE: &str = "openai_create";
+
+/// Name of the built-in `safetensors` `load_file` pattern.
+const SAFETENSORS_LOAD_FILE: &str = "safetensors_load_file";
+
+/// Name of the built-in `joblib.load` pattern.
+pub const JOBLIB_LOAD: &str = "joblib_load";
+
+/// Name of the built-in `torch.load` pattern.
+pub const TORCH_LOAD: &str = "torch_load";
+
+/// Name of the built-in pattern for model card and dataset info loads, counted separately
+/// from weight downloads with [`Status::MetadataLoad`].
+pub const METADATA_LOAD: &str = "metadata_load";
+
+/// Names of the built-in XGBoost and LightGBM model file patterns, classified by the path
+/// of the model file rather than by revision.
+pub const LOCAL_FILE_LOAD_PATTERNS: &[&str] = &["xgboost_load", "lightgbm_load"];
+
+/// Status of a `torch.load` call, with import aliases resolved: unsafe if the file is an `http://` or `https://` URL,
+/// since the downloaded pickle is untrusted whatever `weights_only` says, safe with
+/// `weights_only=True`, which only unpickles tensors and primitive types, and unsafe
+/// otherwise.
+fn torch_load_status(call: &str) -> Status {
+    if !TORCH_LOAD_URL_RE.is_match(call) && WEIGHTS_ONLY_RE.is_match(call) {
+        Status::Safe
+    } else {
+        Status::Unsafe
+    }
+}
+
+static TORCH_LOAD_URL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^torch\.load\s*\(\s*(?:f\s*=\s*)?[rbf]?["']https?://"#).unwrap()
+});
+
+static WEIGHTS_ONLY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bweights_only\s*=\s*True\b").unwrap());
+
+/// Status of an XGBoost or LightGBM model file load, from the path passed as `model_file=`
+/// or to `load_model(...)`:
+///
+/// - relative paths, and paths that are not string literals, are [`Status::LocalFileLoad`]
+/// - absolute paths depend on the host: world-writable directories (`/tmp/`, `/var/tmp/`,
+///   `/dev/shm/`) are unsafe, anything else is a local file load
+/// - URLs depend on their host: `localhost` and loopback addresses are local file loads,
+///   any other host is unsafe, as the file is downloaded without an integrity check
+fn local_file_load_status(call: &str) -> Status {
+    let Some(path) = MODEL_FILE_PATH_RE
+        .captures(call)
+        .map(|caps| caps[1].to_string())
+    else {
+        return Status::LocalFileLoad;
+    };
+    if let Some(caps) = URL_HOST_RE.captures(&path)
+        && !path.starts_with("file://")
+    {
+        let host = caps[1].to_ascii_lowercase();
+        return match host.as_str() {
+            "localhost" | "[::1]" => Status::LocalFileLoad,
+            host if host.starts_with("127.") => Status::LocalFileLoad,
+            _ => Status::Unsafe,
+        };
+    }
+    let path = path.strip_prefix("file://").unwrap_or(&path);
+    if ["/tmp/", "/var/tmp/", "/dev/shm/"]
+        .iter()
+        .any(|dir| path.starts_with(dir))
+    {
+        Status::Unsafe
+    } else {
+        Status::LocalFileLoad
+    }
+}
+
+static MODEL_FILE_PATH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?:model_file\s*=\s*|load_model\s*\(\s*)[rRbBuUfF]{0,2}["']([^"']+)["']"#)
+        .unwrap()
+});
+
+static URL_HOST_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z][\w+.-]*://(\[[^\]]*\]|[^/:?#]*)").unwrap());
+
+/// `(pattern, module)` pairs for built-in patterns whose call names (`load_file`, `LLM`)
+/// are too generic to report in files that do not import `module`.
+const IMPORT_GATED_PATTERNS: &[(&str, &str)] =
+    &[(SAFETENSORS_LOAD_FILE, "safetensors"), ("vllm", "vllm")];
+
+/// [`IMPORT_GATED_PATTERNS`] with a regex for each module's `import` or `from` line.
+static IMPORT_GATED_RES: LazyLock<Vec<(&str, Regex)>> = LazyLock::new(|| {
+    IMPORT_GATED_PATTERNS
+        .iter()
+        .map(|&(pattern, module)| {
+            let import_re = Regex::new(&format!(
+                r"(?m)^\s*(?:from|import)\s+{}\b",
+                regex::escape(module)
+            ))
+            .unwrap();
+            (pattern, import_re)
+        })
+        .collect()
+});
+
+/// Whether the five lines before `offset` verify a file hash: a `hashlib` digest and a
+/// comparison, as in
+///
+/// ```python
+/// digest = hashlib.sha256(data).hexdigest()
+/// if digest != EXPECTED_SHA256:
+///     raise ValueError("tampered file")
+/// tensors = load_file(path)
+/// ```
+fn has_preceding_hash_check(code: &str, offset: usize) -> bool {
+    let line_start = code[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let window: Vec<&str> = code[..line_start].lines().rev().take(5).collect();
+    window.iter().any(|l| HASH_RE.is_match(l)) && window.iter().any(|l| COMPARE_RE.is_match(l))
+}
+
+static USE_AUTH_OR_LOCAL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"use_auth_token\s*=\s*True|local_files_only\s*=\s*True|from_pretrained(?:\(|\s*,\s*)["'](\./|/)"#,
+    )
+    .unwrap()
+});
+
+/// The second form is a `revision` key in a kwargs dict, as in LangChain's
+/// `model_kwargs={"revision": "..."}`.
+static REVISION_CAPTURE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"revision\s*=\s*["']([^"']+)["']|["']revision["']\s*:\s*["']([^"']+)["']"#)
+        .unwrap()
+});
+
+static IMPORT_HASHLIB_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*(?:import\s+hashlib|from\s+hashlib\s+import)\b").unwrap()
+});
+
+static HASH_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"hashlib\.\w+\(|\.hexdigest\(").unwrap());
+
+static COMPARE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"==|!=|compare_digest\(").unwrap());
+
+/// Byte offsets at which the lines of `code` start, for [`line_number`].
+fn line_starts(code: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(code.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// 1-based line number of the byte at `offset`, given the [`line_starts`] of its code.
+fn line_number(line_starts: &[usize], offset: usize) -> usize {
+    line_starts.partition_point(|&start| start <= offset)
+}
+
+/// Compile a [`ScanConfig::safe_revision_patterns`] entry, anchored to match the whole
+/// revision.
+pub fn safe_revision_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{pattern})$"))
+}
+
+/// Find and classify every download call in `code`, ordered by position.
+pub fn scan_code(code: &str, config: &ScanConfig) -> Vec<Finding> {
+    scan_code_with_aliases(code, config, &ImportAliasMap::from_code(code))
+}
+
+/// [`scan_code`] with the import `aliases` of `code` already collected.
+pub(crate) fn scan_code_with_aliases(
+    code: &str,
+    config: &ScanConfig,
+    aliases: &ImportAliasMap,
+) -> Vec<Finding> {
+    let imports_hashlib = IMPORT_HASHLIB_RE.is_match(code);
+
+    let line_starts = line_starts(code);
+    let source_lines: Vec<&str> = if config.context_lines.is_some() {
+        code.lines().collect()
+    } else {
+        Vec::new()
+    };
+    // Patterns are matched with import aliases resolved, but findings point at the original.
+    let resolved = aliases.resolve(code);
+    let mut findings = Vec::new();
+    let mut matched_spans: Vec<(usize, usize)> = Vec::new();
+
+    for pattern in active_pattern_refs(config) {
+        if let Some((_, import_re)) = IMPORT_GATED_RES
+            .iter()
+            .find(|(name, _)| *name == pattern.name)
+            && !import_re.is_match(code)
+        {
+            continue;
+        }
+        for caps in pattern.regex.captures_iter(resolved.text()) {
+            let Some(m) = caps.get(0) else { continue };
+            let (start, end) = resolved.original_span(m.start(), m.end());
+            // `safetensors.torch.load(data)` only reads tensors.
+            if pattern.name == TORCH_LOAD && code[..start].ends_with('.') {
+                continue;
+            }
+            if matched_spans.iter().any(|&(s, e)| start < e && s < end) {
+                continue;
+            }
+            matched_spans.push((start, end));
+            let full_call = &code[start..end];
+
+            let status = if pattern.name == JOBLIB_LOAD {
+                Status::Unsafe
+            } else if pattern.name == TORCH_LOAD {
+                // The resolved call, so `th.load` after `import torch as th` is still
+                // recognised as loading a URL.
+                torch_load_status(m.as_str())
+            } else if LOCAL_FILE_LOAD_PATTERNS.contains(&pattern.name.as_str()) {
+                local_file_load_status(full_call)
+            } else if pattern.name == OPENAI_CREATE {
+                if is_dated_openai_model(full_call) {
+                    Status::Safe
+                } else {
+                    Status::ApiVersionUnpinned
+                }
+            } else if pattern.name == SAFETENSORS_LOAD_FILE {
+                if imports_hashlib && has_preceding_hash_check(code, start) {
+                    Status::Safe
+                } else {
+                    Status::PartiallySafe
+                }
+            } else if USE_AUTH_OR_LOCAL_RE.is_match(full_call) {
+                Status::Safe
+            } else if let Some(uri_status) = mlflow_model_uri_status(full_call) {
+                uri_status
+            } else if let Some(rev_caps) = REVISION_CAPTURE_RE.captures(full_call) {
+                let val = rev_caps
+                    .get(1)
+                    .or_else(|| rev_caps.get(2))
+                    .map_or("", |m| m.as_str());
+                if config
+                    .safe_revision_patterns
+                    .iter()
+                    .any(|re| re.is_match(val))
+                {
+                    Status::Safe
+                } else if is_commit_sha(val) || (config.accept_short_sha && is_abbreviated_sha(val))
+                {
+                    if config.flag_suspicious_shas && !looks_random(val) {
+                        Status::PartiallySafe
+                    } else {
+                        Status::Safe
+                    }
+                } else {
+                    Status::PartiallySafe
+                }
+            } else {
+                Status::Unsafe
+            };
+            let model_name = extract_model_name(full_call);
+            let status = match model_name {
+                Some(ModelName::DynamicName(_)) => status.max(Status::PartiallySafe),
+                _ => status,
+            };
+
+            let line = line_number(&line_starts, start);
+            let context = match config.context_lines {
+                Some(n) => {
+                    let first = (line - 1).saturating_sub(n);
+                    let last = (line + n).min(source_lines.len());
+                    source_lines[first..last].join("\n")
+                }
+                None => String::new(),
+            };
+
+            let suppression_reason =
+                has_noscan_comment(code, start, end).then_some(SuppressionReason::NoscanComment);
+            let status = if suppression_reason.is_some() {
+                Status::Ignored
+            } else if uses_traversal_subfolder(full_call) {
+                Status::PathTraversal
+            } else {
+                status.min(pattern.severity)
+            };
+
+            findings.push(Finding {
+                file: String::new(),
+                line,
+                pattern: pattern.name.clone(),
+                status,
+                call: full_call.to_string(),
+                model_name,
+                in_loop: is_in_loop(code, start),
+                in_init_empty_weights: is_in_init_empty_weights(code, start),
+                in_test_fixture: is_in_test_fixture(code, start),
+                enclosing_function: enclosing_function(code, start),
+                shared_cache_dir: uses_shared_cache_dir(full_call),
+                incomplete_download: pattern.name == "snapshot_download"
+                    && excludes_weight_files(full_call),
+                private_repo: false,
+                context,
+                cwe: Vec::new(),
+                suppression_reason,
+            });
+        }
+    }
+
+    if is_training_code(code) {
+        for finding in &mut findings {
+            if finding.status == Status::Unsafe && finding.call.contains("from_pretrained") {
+                finding.status = Status::TrainingWithUnsafeBase;
+            }
+        }
+    }
+
+    findings.sort_by_key(|f| f.line);
+    findings
+}
+
+/// Whether `code` constructs a `Trainer` (or `SFTTrainer`, `Seq2SeqTrainer`, ...) and calls
+/// `.train()`. A heuristic; the trainer is not tied to any particular model load.
+fn is_training_code(code: &str) -> bool {
+    let (constructs_trainer, calls_train) = training_markers(code);
+    constructs_trainer && calls_train
+}
+
+/// Whether `code` constructs a trainer and whether it calls `.train()`, checked separately.
+pub(crate) fn training_markers(code: &str) -> (bool, bool) {
+    (TRAINER_RE.is_match(code), TRAIN_RE.is_match(code))
+}
+
+static TRAINER_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b\w*Trainer\s*\(").unwrap());
+
+static TRAIN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\.train\s*\(").unwrap());
+
+/// Count findings as `(safe, partial, unsafe)`. Metadata loads, local file loads and remote
+/// calls count as partial, training findings and path traversals as unsafe.
+pub fn count_usages(findings: &[Finding]) -> (usize, usize, usize) {
+    let mut safe_count = 0;
+    let mut partial_count = 0;
+    let mut unsafe_count = 0;
+
+    for finding in findings {
+        match finding.status {
+            Status::Ignored => {}
+            Status::Safe => safe_count += 1,
+            Status::PartiallySafe
+            | Status::MetadataLoad
+            | Status::LocalFileLoad
+            | Status::ApiVersionUnpinned
+            | Status::RemoteCall => partial_count += 1,
+            Status::Unsafe | Status::TrainingWithUnsafeBase | Status::PathTraversal => {
+                unsafe_count += 1
+            }
+        }
+    }
+
+    (safe_count, partial_count, unsafe_count)
+}
+
+pub fn scan_code_for_usage(code: &str, config: &ScanConfig) -> (usize, usize, usize) {
+    count_usages(&scan_code(code, config))
+}
+
+/// Find `huggingface-cli download <repo>` commands in a shell script or Makefile.
+///
+/// Commands pinned with `--revision <sha>` are safe, other revisions partially safe and
+/// commands without `--revision` unsafe. Backslash line continuations are followed.
+pub fn scan_shell_script(content: &str) -> Vec<Finding> {
+    let line_starts = line_starts(content);
+    HUGGINGFACE_CLI_RE
+        .captures_iter(content)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let repo = caps[1].trim_matches(['"', '\'']);
+            let suppression_reason = has_noscan_comment(content, m.start(), m.end())
+                .then_some(SuppressionReason::NoscanComment);
+            let status = match CLI_REVISION_RE.captures(m.as_str()) {
+                _ if suppression_reason.is_some() => Status::Ignored,
+                Some(rev) if is_commit_sha(&rev[1]) => Status::Safe,
+                Some(_) => Status::PartiallySafe,
+                None => Status::Unsafe,
+            };
+            let model_name = if repo.contains('$') {
+                ModelName::DynamicName(repo.to_string())
+            } else {
+                ModelName::Literal(repo.to_string())
+            };
+            Some(Finding {
+                file: String::new(),
+                line: line_number(&line_starts, m.start()),
+                pattern: "huggingface_cli".to_string(),
+                status,
+                call: m.as_str().trim_end().to_string(),
+                model_name: Some(model_name),
+                in_loop: false,
+                in_init_empty_weights: false,
+                in_test_fixture: false,
+                enclosing_function: None,
+                shared_cache_dir: false,
+                incomplete_download: false,
+                private_repo: false,
+                context: String::new(),
+                cwe: Vec::new(),
+                suppression_reason,
+            })
+        })
+        .collect()
+}
+
+static HUGGINGFACE_CLI_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"huggingface-cli\s+download\s+(\S+)(?:\\\r?\n|[^\n])*").unwrap());
+
+static CLI_REVISION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"--revision(?:\s+|=)["']?([^\s"']+)"#).unwrap());
+
+/// Findings for one file along with its size.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileScan {
+    pub findings: Vec<Finding>,
+    pub lines: usize,
+    pub bytes: u64,
+    /// Skipped because `skip_generated` is set and the file has a generated-code marker.
+    pub generated: bool,
+}
+
+/// Decode the bytes of a source file. Invalid UTF-8 is an [`io::ErrorKind::InvalidData`]
+/// error, like from [`fs::read_to_string`], unless `mode` is [`EncodingMode::Replace`].
+pub fn decode_source(bytes: Vec<u8>, mode: EncodingMode) -> io::Result<String> {
+    match String::from_utf8(bytes) {
+        Ok(content) => Ok(content),
+        Err(e) if mode == EncodingMode::Replace => {
+            Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        }
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+/// Read a source file, decoding it with [`decode_source`].
+pub fn read_source(path: &Path, mode: EncodingMode) -> io::Result<String> {
+    decode_source(fs::read(path)?, mode)
+}
+
+/// Scan a single file. Python files are scanned for download calls, anything else as a
+/// shell script. Unreadable files, and with [`EncodingMode::Strict`] files that are not
+/// UTF-8, are logged and yield an empty [`FileScan`].
+pub fn scan_file(path: &Path, config: &ScanConfig) -> FileScan {
+    let content = match read_source(path, config.encoding_errors) {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!(path = %path.display(), "Failed to read file: {e}");
+            return FileScan::default();
+        }
+    };
+    scan_content(path, &content, config)
+}
+
+/// Markers of generated code recognised by `skip_generated`, in addition to
+/// `generated_markers`. Only the first five lines of a file are checked.
+pub const DEFAULT_GENERATED_MARKERS: &[&str] = &[
+    r"(?i)#\s*DO NOT EDIT",
+    r"(?i)#\s*generated by",
+    r"(?i)#\s*auto-generated",
+    r"#\s*@generated",
+];
+
+static DEFAULT_GENERATED_MARKER_RES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    DEFAULT_GENERATED_MARKERS
+        .iter()
+        .map(|marker| Regex::new(marker).unwrap())
+        .collect()
+});
+
+/// Whether one of the first five lines of `content` carries a generated-code marker.
+fn is_generated(content: &str, config: &ScanConfig) -> bool {
+    let markers = DEFAULT_GENERATED_MARKER_RES
+        .iter()
+        .chain(&config.generated_markers);
+    content
+        .lines()
+        .take(5)
+        .any(|line| markers.clone().any(|re| re.is_match(line)))
+}
+
+/// Scan already read file contents: Python for `.py` files, shell commands otherwise.
+pub fn scan_content(path: &Path, content: &str, config: &ScanConfig) -> FileScan {
+    if config.skip_generated && is_generated(content, config) {
+        tracing::debug!(path = %path.display(), "skipping generated file");
+        return FileScan {
+            generated: true,
+            ..Default::default()
+        };
+    }
+    let findings = if path.extension().is_some_and(|ext| ext == "py") {
+        match &config.cache {
+            Some(cache) => {
+                let hash = content_hash(content);
+                cache.get(&hash).unwrap_or_else(|| {
+                    let findings = scan_code(content, config);
+                    cache.insert(hash, findings.clone());
+                    findings
+                })
+            }
+            None => scan_code(content, config),
+        }
+    } else {
+        scan_shell_script(content)
+    };
+    FileScan {
+        findings,
+        lines: content.lines().count(),
+        bytes: content.len() as u64,
+        generated: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_test::traced_test;
+
+    #[test]
+    fn test_status_serde_round_trip() {
+        for status in Status::ALL {
+            let json = serde_json::to_string(&status).unwrap();
+            assert_eq!(json, format!("\"{status}\""));
+            assert_eq!(serde_json::from_str::<Status>(&json).unwrap(), status);
+        }
+        assert_eq!(Status::PartiallySafe.to_string(), "partially_safe");
+        assert!(serde_json::from_str::<Status>("\"trusted\"").is_err());
+    }
+
+    #[test]
+    fn test_is_plausible_commit_sha() {
+        assert!(is_plausible_commit_sha(
+            "5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d"
+        ));
+        assert!(is_plausible_commit_sha(
+            "9b8e7a0c4f1d2e3b5a6c7d8e9f0a1b2c3d4e5f60"
+        ));
+        assert!(is_plausible_commit_sha(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4"
+        ));
+
+        assert!(!is_plausible_commit_sha(
+            "0000000000000000000000000000000000000000"
+        ));
+        assert!(!is_plausible_commit_sha(
+            "abababababababababababababababababababab"
+        ));
+        assert!(!is_plausible_commit_sha(
+            "0123456789abcdef0123456789abcdef01234567"
+        ));
+        assert!(!is_plausible_commit_sha(
+            "aaaaaaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbbbbb"
+        ));
+        assert!(!is_plausible_commit_sha("main"));
+
+        assert!(has_suspicious_sha(
+            r#"AutoModel.from_pretrained("m", revision="0000000")"#
+        ));
+        assert!(!has_suspicious_sha(
+            r#"AutoModel.from_pretrained("m", revision="5d0f2e8")"#
+        ));
+        assert!(!has_suspicious_sha(
+            r#"AutoModel.from_pretrained("m", revision="main")"#
+        ));
+    }
+
+    #[test]
+    fn test_scan_code_flag_suspicious_shas() {
+        let code = r#"
+a = AutoModel.from_pretrained("m", revision="0000000000000000000000000000000000000000")
+b = AutoModel.from_pretrained("m", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+c = AutoModel.from_pretrained("m", revision="abababa")
+"#;
+        let statuses = |config: &ScanConfig| -> Vec<Status> {
+            scan_code(code, config).iter().map(|f| f.status).collect()
+        };
+        let short = ScanConfig {
+            accept_short_sha: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            statuses(&short),
+            vec![Status::Safe, Status::Safe, Status::Safe]
+        );
+        let flagged = ScanConfig {
+            flag_suspicious_shas: true,
+            ..short
+        };
+        assert_eq!(
+            statuses(&flagged),
+            vec![Status::PartiallySafe, Status::Safe, Status::PartiallySafe]
+        );
+    }
+
+    #[test]
+    fn test_is_commit_sha() {
+        // Valid SHA
+        assert!(is_commit_sha("5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d"));
+        assert!(is_commit_sha("abcdef1234567890abcdef1234567890abcdef12"));
+
+        // Invalid SHA
+        assert!(!is_commit_sha("main"));
+        assert!(!is_commit_sha("v1.0"));
+        assert!(!is_commit_sha("5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5")); // 39 chars
+        assert!(!is_commit_sha("5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d1")); // 41 chars
+        assert!(!is_commit_sha("5D0F2E8A7F1B2C3D4E5F6A7B8C9D0E1F2A3B4C5D")); // uppercase
+        assert!(!is_commit_sha("5g0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")); // contains 'g'
+    }
+
+    #[test]
+    fn test_is_abbreviated_sha() {
+        assert!(is_abbreviated_sha("5d0f2e8")); // 7 chars
+        assert!(is_abbreviated_sha("5d0f2e8a7f1b")); // 12 chars
+        assert!(is_abbreviated_sha(
+            "5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d"
+        )); // 40 chars
+
+        assert!(!is_abbreviated_sha("5d0f2e")); // 6 chars
+        assert!(!is_abbreviated_sha(
+            "5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d1"
+        )); // 41 chars
+        assert!(!is_abbreviated_sha("v1.0.0a"));
+        assert!(!is_abbreviated_sha("main"));
+    }
+
+    #[test]
+    fn test_scan_code_with_short_sha() {
+        let code = r#"
+model = AutoModel.from_pretrained("model", revision="5d0f2e8")
+tokenizer = AutoTokenizer.from_pretrained("model", revision="5d0f2e8a7f1b")
+"#;
+        assert_eq!(scan_code_for_usage(code, &ScanConfig::default()), (0, 2, 0));
+
+        let config = ScanConfig {
+            accept_short_sha: true,
+            ..Default::default()
+        };
+        assert_eq!(scan_code_for_usage(code, &config), (2, 0, 0));
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_basic() {
+        let code = r#"
+from transformers import AutoModel
+model = AutoModel.from_pretrained("model")
+"#;
+        let (safe, partial, unsafe_) = scan_code_for_usage(code, &ScanConfig::default());
+        assert_eq!(safe, 0);
+        assert_eq!(partial, 0);
+        assert_eq!(unsafe_, 1);
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_with_sha() {
+        let code = r#"
+from transformers import AutoModel
+model = AutoModel.from_pretrained("model", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+"#;
+        let (safe, partial, unsafe_) = scan_code_for_usage(code, &ScanConfig::default());
+        assert_eq!(safe, 1);
+        assert_eq!(partial, 0);
+        assert_eq!(unsafe_, 0);
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_with_tag() {
+        let code = r#"
+from transformers import AutoModel
+model = AutoModel.from_pretrained("model", revision="main")
+"#;
+        let (safe, partial, unsafe_) = scan_code_for_usage(code, &ScanConfig::default());
+        assert_eq!(safe, 0);
+        assert_eq!(partial, 1);
+        assert_eq!(unsafe_, 0);
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_dynamic_import() {
+        let code = r#"
+import importlib
+model = importlib.import_module("transformers").AutoModel.from_pretrained("m")
+"#;
+        assert_eq!(scan_code_for_usage(code, &ScanConfig::default()), (0, 0, 1));
+
+        let code = r#"
+model = __import__("transformers").AutoModel.from_pretrained("m")
+"#;
+        assert_eq!(scan_code_for_usage(code, &ScanConfig::default()), (0, 0, 1));
+
+        let code = r#"
+model = importlib.import_module('transformers').AutoModel.from_pretrained(
+    "m",
+    revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d",
+)
+"#;
+        assert_eq!(scan_code_for_usage(code, &ScanConfig::default()), (1, 0, 0));
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_in_comprehensions() {
+        let list_comp = r#"
+models = [AutoModel.from_pretrained(name) for name in model_names]
+"#;
+        assert_eq!(
+            scan_code_for_usage(list_comp, &ScanConfig::default()),
+            (0, 0, 1)
+        );
+
+        let dict_comp = r#"
+models = {name: AutoModel.from_pretrained(name, revision="main") for name in model_names}
+"#;
+        assert_eq!(
+            scan_code_for_usage(dict_comp, &ScanConfig::default()),
+            (0, 1, 0)
+        );
+
+        let generator = r#"
+models = list(
+    AutoModel.from_pretrained(name, revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+    for name in model_names
+)
+"#;
+        let findings = scan_code(generator, &ScanConfig::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].status, Status::Safe);
+        assert!(!findings[0].call.contains("for name"));
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_speech_models() {
+        let code = r#"
+model = AutoModelForSpeechSeq2Seq.from_pretrained("openai/whisper-large-v3")
+processor = AutoProcessor.from_pretrained("openai/whisper-large-v3", revision="main")
+encoder = Wav2Vec2Model.from_pretrained(
+    "facebook/wav2vec2-base-960h",
+    revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d",
+)
+whisper = WhisperForConditionalGeneration.from_pretrained("openai/whisper-small")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let by_line: Vec<(usize, &str, Status)> = findings
+            .iter()
+            .map(|f| (f.line, f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            by_line,
+            vec![
+                (2, "AutoModel", Status::Unsafe),
+                (3, "AutoClass", Status::PartiallySafe),
+                (4, "speech_model", Status::Safe),
+                (8, "speech_model", Status::Unsafe),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_torchaudio_pipeline() {
+        let code = r#"
+bundle = torchaudio.pipelines.WAV2VEC2_BASE
+model = bundle.get_model()
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].pattern, "torchaudio_pipeline");
+        assert_eq!(findings[0].status, Status::PartiallySafe);
+    }
+
+    #[test]
+    fn test_scan_code_extra_patterns() {
+        let code = r#"
+model = mylib.load_model("org/model")
+pinned = mylib.load_model("org/model", revision="main")
+tokenizer = AutoTokenizer.from_pretrained("model")
+"#;
+        let custom = Pattern {
+            name: "mylib".to_string(),
+            regex: Regex::new(r#"mylib\.load_model\s*\((?s:.*?)\)"#).unwrap(),
+            severity: Status::PartiallySafe,
+            since_version: "",
+        };
+        let config = ScanConfig {
+            extra_patterns: vec![custom],
+            ..Default::default()
+        };
+        assert_eq!(scan_code_for_usage(code, &config), (0, 2, 1));
+
+        let config = ScanConfig {
+            no_builtin_patterns: true,
+            ..config
+        };
+        let findings = scan_code(code, &config);
+        assert_eq!(findings.len(), 2);
+        assert!(findings.iter().all(|f| f.pattern == "mylib"));
+    }
+
+    #[test]
+    fn test_scan_code_finding_lines() {
+        let code = r#"
+tokenizer = AutoTokenizer.from_pretrained("model", revision="main")
+
+model = AutoModel.from_pretrained("model")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].line, 2);
+        assert_eq!(findings[0].status, Status::PartiallySafe);
+        assert_eq!(findings[1].line, 4);
+        assert_eq!(findings[1].status, Status::Unsafe);
+        assert_eq!(findings[1].call, r#"AutoModel.from_pretrained("model")"#);
+    }
+
+    #[test]
+    fn test_uses_trust_remote_code() {
+        assert!(uses_trust_remote_code(
+            r#"AutoModel.from_pretrained("model", trust_remote_code=True)"#
+        ));
+        assert!(uses_trust_remote_code("f(x, trust_remote_code = True)"));
+        assert!(!uses_trust_remote_code(
+            r#"AutoModel.from_pretrained("model", trust_remote_code=False)"#
+        ));
+    }
+
+    #[test]
+    fn test_extract_model_name_string_forms() {
+        assert_eq!(
+            extract_model_name(r#"AutoModel.from_pretrained("bert-base-uncased")"#),
+            Some(ModelName::Literal("bert-base-uncased".to_string()))
+        );
+        assert_eq!(
+            extract_model_name(r#"AutoModel.from_pretrained(b"bert-base")"#),
+            Some(ModelName::Literal("bert-base".to_string()))
+        );
+        assert_eq!(
+            extract_model_name(r"AutoModel.from_pretrained(r'org\model')"),
+            Some(ModelName::Literal(r"org\model".to_string()))
+        );
+        assert_eq!(
+            extract_model_name(r#"AutoModel.from_pretrained(f"model-{version}")"#),
+            Some(ModelName::DynamicName("model-{version}".to_string()))
+        );
+        assert_eq!(
+            extract_model_name(
+                r#"importlib.import_module("transformers").AutoModel.from_pretrained("gpt2")"#
+            ),
+            Some(ModelName::Literal("gpt2".to_string()))
+        );
+        assert_eq!(
+            extract_model_name("AutoModel.from_pretrained(model_id)"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_model_name_keyword_argument() {
+        assert_eq!(
+            extract_model_name(
+                r#"AutoModel.from_pretrained(pretrained_model_name_or_path="bert-base")"#
+            ),
+            Some(ModelName::Literal("bert-base".to_string()))
+        );
+        assert_eq!(
+            extract_model_name(
+                r#"AutoTokenizer.from_pretrained(cache_dir=d, model_name_or_path=f'org/{name}')"#
+            ),
+            Some(ModelName::DynamicName("org/{name}".to_string()))
+        );
+        assert_eq!(
+            extract_model_name(r#"AutoModel.from_pretrained(config="bert-base")"#),
+            None
+        );
+
+        let code = r#"
+a = AutoModel.from_pretrained(pretrained_model_name_or_path="bert-base")
+b = AutoModel.from_pretrained(
+    pretrained_model_name_or_path="bert-base",
+    revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d",
+)
+c = AutoModel.from_pretrained(pretrained_model_name_or_path=f"org/{name}", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(Option<&str>, Status)> = findings
+            .iter()
+            .map(|f| (f.model_name.as_ref().map(ModelName::as_str), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some("bert-base"), Status::Unsafe),
+                (Some("bert-base"), Status::Safe),
+                (Some("org/{name}"), Status::PartiallySafe),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_functools_partial() {
+        let code = r#"model_factory = functools.partial(AutoModel.from_pretrained, "model")
+pinned = partial(AutoModel.from_pretrained, "model", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status, &str)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status, f.call.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "callable_from_pretrained",
+                    Status::Unsafe,
+                    r#"functools.partial(AutoModel.from_pretrained, "model")"#
+                ),
+                (
+                    "callable_from_pretrained",
+                    Status::Safe,
+                    r#"partial(AutoModel.from_pretrained, "model", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")"#
+                ),
+            ]
+        );
+        assert_eq!(
+            findings[0].model_name,
+            Some(ModelName::Literal("model".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_scan_code_incomplete_download() {
+        let code = r#"a = snapshot_download("org/model", ignore_patterns=["*.bin"])
+b = snapshot_download("org/model", ignore_patterns=["*.md"])
+c = snapshot_download("org/model")
+d = snapshot_download("org/model", ignore_patterns="*.safetensors")
+e = snapshot_download("org/model", ignore_patterns=["*.md", "model-*.pt"])
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let flags: Vec<bool> = findings.iter().map(|f| f.incomplete_download).collect();
+        assert_eq!(flags, vec![true, false, false, true, true]);
+        assert!(findings.iter().all(|f| f.status == Status::Unsafe));
+
+        let json = serde_json::to_value(&findings[0]).unwrap();
+        assert_eq!(json["incomplete_download"], true);
+    }
+
+    #[test]
+    fn test_scan_code_local_file_load() {
+        let code = r#"import xgboost as xgb
+import lightgbm as lgb
+a = xgb.Booster().load_model("models/model.json")
+b = lgb.Booster(model_file="model.txt")
+c = xgb.Booster().load_model("/opt/models/model.json")
+d = lgb.Booster(model_file="/tmp/model.txt")
+e = xgb.XGBClassifier().load_model("https://example.com/model.json")
+f = lgb.Booster(model_file="http://localhost:8000/model.txt")
+g = xgb.Booster().load_model(path)
+h = lgb.Booster(params=params, train_set=train)
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(usize, &str, Status)> = findings
+            .iter()
+            .map(|f| (f.line, f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (3, "xgboost_load", Status::LocalFileLoad),
+                (4, "lightgbm_load", Status::LocalFileLoad),
+                (5, "xgboost_load", Status::LocalFileLoad),
+                (6, "lightgbm_load", Status::Unsafe),
+                (7, "xgboost_load", Status::Unsafe),
+                (8, "lightgbm_load", Status::LocalFileLoad),
+                (9, "xgboost_load", Status::LocalFileLoad),
+            ]
+        );
+        assert_eq!(count_usages(&findings), (0, 5, 2));
+    }
+
+    #[test]
+    fn test_scan_code_path_traversal() {
+        let code = r#"a = AutoModel.from_pretrained("org/model", subfolder="../../etc")
+b = AutoModel.from_pretrained("org/model", subfolder="encoder")
+c = AutoModel.from_pretrained("org/model", subfolder="encoder", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+d = hf_hub_download("org/model", "config.json", subfolder='a\..\..\b', revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+e = AutoModel.from_pretrained("org/model", subfolder="v1..v2")
+"#;
+        let statuses: Vec<Status> = scan_code(code, &ScanConfig::default())
+            .iter()
+            .map(|f| f.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Status::PathTraversal,
+                Status::Unsafe,
+                Status::Safe,
+                Status::PathTraversal,
+                Status::Unsafe,
+            ]
+        );
+        assert_eq!(Status::ALL.iter().max(), Some(&Status::PathTraversal));
+    }
+
+    #[test]
+    fn test_scan_code_callable_from_pretrained() {
+        let code = r#"async def load_model():
+    a = await asyncio.to_thread(AutoModel.from_pretrained, "org/a")
+    b = await asyncio.to_thread(
+        AutoModel.from_pretrained, "org/b", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d"
+    )
+    c = await asyncio.to_thread(AutoModel.from_pretrained, './local')
+    d = AutoModel.from_pretrained("org/d")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status, Option<&str>)> = findings
+            .iter()
+            .map(|f| {
+                (
+                    f.pattern.as_str(),
+                    f.status,
+                    f.model_name.as_ref().map(ModelName::as_str),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("callable_from_pretrained", Status::Unsafe, Some("org/a")),
+                ("callable_from_pretrained", Status::Safe, Some("org/b")),
+                ("callable_from_pretrained", Status::Safe, Some("./local")),
+                ("AutoModel", Status::Unsafe, Some("org/d")),
+            ]
+        );
+        assert_eq!(findings[0].call, r#"AutoModel.from_pretrained, "org/a")"#);
+        assert_eq!(findings[1].line, 4);
+    }
+
+    #[test]
+    fn test_extract_revision() {
+        assert_eq!(
+            extract_revision(r#"AutoModel.from_pretrained("gpt2", revision="v1.0")"#),
+            Some("v1.0".to_string())
+        );
+        assert_eq!(
+            extract_revision(r#"HuggingFacePipeline(model_kwargs={'revision': 'abc'})"#),
+            Some("abc".to_string())
+        );
+        assert_eq!(
+            extract_revision(r#"AutoModel.from_pretrained("gpt2")"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_scan_code_dynamic_model_name() {
+        let code = r#"
+a = AutoModel.from_pretrained(f"org/model-{version}", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+b = AutoModel.from_pretrained(f"org/model-{version}")
+c = AutoModel.from_pretrained(b"org/model", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let statuses: Vec<Status> = findings.iter().map(|f| f.status).collect();
+        assert_eq!(
+            statuses,
+            vec![Status::PartiallySafe, Status::Unsafe, Status::Safe]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_mlflow() {
+        let code = r#"
+latest = mlflow.pyfunc.load_model("models:/MyModel/latest")
+pinned = mlflow.transformers.load_model("models:/MyModel/1")
+staged = mlflow.pyfunc.load_model(model_uri="models:/MyModel/staging")
+aliased = mlflow.pyfunc.load_model("models:/MyModel@champion")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        assert!(findings.iter().all(|f| f.pattern == "mlflow_load_model"));
+        let statuses: Vec<Status> = findings.iter().map(|f| f.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Status::Unsafe,
+                Status::Safe,
+                Status::PartiallySafe,
+                Status::PartiallySafe
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_langchain() {
+        let code = r#"
+llm = HuggingFacePipeline.from_model_id(model_id="gpt2", task="text-generation")
+llm = HuggingFacePipeline.from_model_id(
+    model_id="gpt2",
+    task="text-generation",
+    model_kwargs={"revision": "5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d"},
+)
+llm = HuggingFacePipeline.from_model_id(model_id="gpt2", task="text-generation", model_kwargs={'revision': 'main'})
+hub = HuggingFaceHub(repo_id="google/flan-t5-xl")
+endpoint = HuggingFaceEndpoint(repo_id="mistralai/Mistral-7B-Instruct-v0.2")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        assert!(findings.iter().all(|f| f.pattern == "langchain"));
+        let statuses: Vec<Status> = findings.iter().map(|f| f.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Status::Unsafe,
+                Status::Safe,
+                Status::PartiallySafe,
+                Status::Unsafe,
+                Status::Unsafe
+            ]
+        );
+    }
+
+    #[test]
+    #[traced_test]
+    fn test_scan_file_read_error_is_logged() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.py");
+
+        assert_eq!(
+            scan_file(&missing, &ScanConfig::default()),
+            FileScan::default()
+        );
+        assert!(logs_contain("ERROR"));
+        assert!(logs_contain("Failed to read file"));
+        assert!(logs_contain("missing.py"));
+    }
+
+    #[test]
+    fn test_scan_file_encoding_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("latin1.py");
+        // "café" in Latin-1: 0xE9 is not valid UTF-8.
+        fs::write(
+            &path,
+            b"# caf\xe9\nm = AutoModel.from_pretrained(\"gpt2\")\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            scan_file(&path, &ScanConfig::default()),
+            FileScan::default()
+        );
+
+        let config = ScanConfig {
+            encoding_errors: EncodingMode::Replace,
+            ..Default::default()
+        };
+        let scan = scan_file(&path, &config);
+        assert_eq!(scan.findings.len(), 1);
+        assert_eq!(scan.findings[0].line, 2);
+        assert_eq!(scan.findings[0].status, Status::Unsafe);
+        assert!(
+            read_source(&path, EncodingMode::Replace)
+                .unwrap()
+                .starts_with("# caf\u{FFFD}\n")
+        );
+    }
+
+    #[test]
+    fn test_scan_code_context_lines() {
+        let code = "import torch\nfrom transformers import AutoModel\n\nmodel = AutoModel.from_pretrained(\"gpt2\")\nmodel.eval()\n";
+        let findings = scan_code(code, &ScanConfig::default());
+        assert_eq!(findings[0].context, "");
+
+        let config = ScanConfig {
+            context_lines: Some(1),
+            ..Default::default()
+        };
+        let findings = scan_code(code, &config);
+        assert_eq!(
+            findings[0].context,
+            "\nmodel = AutoModel.from_pretrained(\"gpt2\")\nmodel.eval()"
+        );
+
+        let config = ScanConfig {
+            context_lines: Some(10),
+            ..Default::default()
+        };
+        let findings = scan_code(code, &config);
+        assert_eq!(findings[0].context, code.trim_end());
+    }
+
+    #[test]
+    fn test_scan_code_safe_revision_patterns() {
+        let code = r#"
+model = AutoModel.from_pretrained("model", revision="sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")
+model = AutoModel.from_pretrained("model", revision="sha256:9f86d081")
+model = AutoModel.from_pretrained("model", revision="sha1:5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+"#;
+        assert_eq!(scan_code_for_usage(code, &ScanConfig::default()), (0, 3, 0));
+
+        let config = ScanConfig {
+            safe_revision_patterns: vec![
+                safe_revision_regex("sha256:[a-f0-9]{64}").unwrap(),
+                safe_revision_regex("sha1:[a-f0-9]{40}").unwrap(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(scan_code_for_usage(code, &config), (2, 1, 0));
+    }
+
+    #[test]
+    fn test_scan_code_in_loop() {
+        let code = r#"
+direct = AutoModel.from_pretrained("model")
+
+for name in tqdm(model_names):
+    if name:
+        # load every model
+        model = AutoModel.from_pretrained(name)
+    print(name)
+
+def load():
+    return AutoModel.from_pretrained("model")
+
+for name in names: AutoModel.from_pretrained(name)
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let in_loop: Vec<bool> = findings.iter().map(|f| f.in_loop).collect();
+        assert_eq!(in_loop, vec![false, true, false, true]);
+    }
+
+    #[test]
+    fn test_scan_code_shared_cache_dir() {
+        let code = r#"
+a = AutoModel.from_pretrained("model", cache_dir="/tmp/shared_cache")
+b = AutoModel.from_pretrained("model", cache_dir="/home/user/.cache/models")
+c = AutoModel.from_pretrained("model", cache_dir='/dev/shm/models')
+d = AutoModel.from_pretrained("model", cache_dir="~/tmp/models")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let shared: Vec<bool> = findings.iter().map(|f| f.shared_cache_dir).collect();
+        assert_eq!(shared, vec![true, false, true, false]);
+        assert!(findings.iter().all(|f| f.status == Status::Unsafe));
+    }
+
+    #[test]
+    fn test_scan_code_joblib_load() {
+        let code = r#"
+import joblib
+
+clf = joblib.load("model.pkl")
+clf = joblib.load(hf_hub_download("org/model", "model.pkl", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d"))
+joblib.dump(clf, "model.pkl")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(JOBLIB_LOAD, Status::Unsafe), (JOBLIB_LOAD, Status::Unsafe)]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_torch_load() {
+        let code = r#"
+import torch
+
+a = torch.load("https://example.com/model.pt")
+b = torch.load("https://example.com/model.pt", weights_only=True)
+c = torch.load(f="http://example.com/model.pt", weights_only=True)
+d = torch.load("/local/path.pt", weights_only=True)
+e = torch.load("/local/path.pt")
+f = torch.load(path, weights_only=False)
+g = torch.load(open(path, "rb"), map_location="cpu", weights_only=True)
+h = safetensors.torch.load(data)
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(usize, &str, Status)> = findings
+            .iter()
+            .map(|f| (f.line, f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (4, TORCH_LOAD, Status::Unsafe),
+                (5, TORCH_LOAD, Status::Unsafe),
+                (6, TORCH_LOAD, Status::Unsafe),
+                (7, TORCH_LOAD, Status::Safe),
+                (8, TORCH_LOAD, Status::Unsafe),
+                (9, TORCH_LOAD, Status::Unsafe),
+                (10, TORCH_LOAD, Status::Safe),
+            ]
+        );
+
+        let aliased = r#"
+import torch as th
+
+a = th.load("https://example.com/model.pt", weights_only=True)
+b = th.load("/local/path.pt", weights_only=True)
+"#;
+        let findings = scan_code(aliased, &ScanConfig::default());
+        let summary: Vec<(usize, Status, &str)> = findings
+            .iter()
+            .map(|f| (f.line, f.status, f.call.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    4,
+                    Status::Unsafe,
+                    "th.load(\"https://example.com/model.pt\", weights_only=True)"
+                ),
+                (
+                    5,
+                    Status::Safe,
+                    "th.load(\"/local/path.pt\", weights_only=True)"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_multimodal_models() {
+        let code = r#"
+blip = Blip2ForConditionalGeneration.from_pretrained("x")
+llava = LlavaForConditionalGeneration.from_pretrained("x", revision="main")
+whisper = WhisperForConditionalGeneration.from_pretrained("x")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("conditional_generation", Status::Unsafe),
+                ("conditional_generation", Status::PartiallySafe),
+                ("speech_model", Status::Unsafe),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_patterns_added_since() {
+        assert_eq!(parse_version("0.1.0"), Some((0, 1, 0)));
+        assert_eq!(parse_version("v1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version("latest"), None);
+
+        let added = patterns_added_since("0.1.0").unwrap();
+        assert!(added.contains(&"llama_cpp".to_string()));
+        assert!(added.contains(&"huggingface_cli".to_string()));
+        assert!(!added.contains(&"AutoModel".to_string()));
+        assert!(patterns_added_since("0.2.0").unwrap().is_empty());
+        assert_eq!(
+            patterns_added_since("0.0.9").unwrap().len(),
+            builtin_patterns().len() + 1
+        );
+        assert_eq!(patterns_added_since("next"), None);
+    }
+
+    #[test]
+    fn test_scan_code_load_from_disk() {
+        let code = r#"
+from datasets import load_dataset, load_from_disk
+a = load_from_disk(dataset_path)
+b = datasets.load_from_disk("data/saved")
+c = DatasetDict.load_from_disk(
+    os.path.join(root, "cache"),
+)
+d = load_dataset("squad")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("load_from_disk", Status::Safe),
+                ("load_from_disk", Status::Safe),
+                ("load_from_disk", Status::Safe),
+                ("load_dataset", Status::Unsafe),
+            ]
+        );
+        assert_eq!(scan_code_for_usage(code, &ScanConfig::default()), (3, 0, 1));
+    }
+
+    /// Inputs in the spirit of the `scan_code` fuzz target: deeply nested and unbalanced
+    /// parentheses and unterminated strings must neither panic nor take long.
+    #[test]
+    fn test_scan_code_pathological_input() {
+        let nested = format!(
+            "AutoModel.from_pretrained({}\"gpt2\"{}",
+            "(".repeat(5000),
+            ")".repeat(5000)
+        );
+        let unbalanced = "AutoModel.from_pretrained(\"gpt2\", revision=\"".repeat(2000);
+        let no_close = format!("{}\n", "AutoModel.from_pretrained(x, ".repeat(2000));
+        for code in [nested, unbalanced, no_close] {
+            let start = std::time::Instant::now();
+            let findings = scan_code(&code, &ScanConfig::default());
+            let (safe, partial, unsafe_) = count_usages(&findings);
+            assert!(safe + partial + unsafe_ <= findings.len());
+            assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        }
+    }
+
+    #[test]
+    fn test_scan_code_metadata_load() {
+        let code = r#"
+from huggingface_hub import ModelCard
+card = ModelCard.load("org/model")
+info = DatasetInfo.from_hf_datasets_cache("squad")
+local = ModelCard.load("./README.md", local_files_only=True)
+model = AutoModel.from_pretrained("org/model")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (METADATA_LOAD, Status::MetadataLoad),
+                (METADATA_LOAD, Status::MetadataLoad),
+                (METADATA_LOAD, Status::Safe),
+                ("AutoModel", Status::Unsafe),
+            ]
+        );
+        assert_eq!(scan_code_for_usage(code, &ScanConfig::default()), (1, 2, 1));
+    }
+
+    #[test]
+    fn test_scan_code_enclosing_function() {
+        let code = r#"
+model = AutoModel.from_pretrained("gpt2")
+
+def load_prod_model(name):
+    if name:
+        return AutoModel.from_pretrained(name)
+
+class Loader:
+    async def load(self):
+
+        # the tokenizer too
+        return AutoTokenizer.from_pretrained("gpt2")
+
+def example_demo(): return AutoModel.from_pretrained("gpt2")
+
+tokenizer = AutoTokenizer.from_pretrained("gpt2")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let functions: Vec<Option<&str>> = findings
+            .iter()
+            .map(|f| f.enclosing_function.as_deref())
+            .collect();
+        assert_eq!(
+            functions,
+            vec![
+                None,
+                Some("load_prod_model"),
+                Some("load"),
+                Some("example_demo"),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_import_aliases() {
+        let code = r#"
+import transformers.AutoModel as AM
+from transformers import AutoModel as MyModel
+from datasets import load_dataset as ld
+
+a = AM.from_pretrained("gpt2")
+b = MyModel.from_pretrained(
+    "gpt2", revision="main"
+)
+c = ld("squad")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(usize, &str, Status, &str)> = findings
+            .iter()
+            .map(|f| (f.line, f.pattern.as_str(), f.status, f.call.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    6,
+                    "AutoModel",
+                    Status::Unsafe,
+                    r#"AM.from_pretrained("gpt2")"#
+                ),
+                (
+                    7,
+                    "AutoModel",
+                    Status::PartiallySafe,
+                    "MyModel.from_pretrained(\n    \"gpt2\", revision=\"main\"\n)"
+                ),
+                (10, "load_dataset", Status::Unsafe, r#"ld("squad")"#),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_llama_cpp() {
+        let code = r#"
+from llama_cpp import Llama
+a = Llama.from_pretrained(repo_id="ggml-org/Meta-Llama-3-8B-Q4_0-GGUF", filename="*q4_0.gguf")
+b = Llama.from_pretrained("ggml-org/Meta-Llama-3-8B-Q4_0-GGUF", filename="*q4_0.gguf", revision="main")
+c = Llama.from_pretrained(
+    "ggml-org/Meta-Llama-3-8B-Q4_0-GGUF",
+    revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d",
+)
+d = LlamaCpp.from_pretrained("ggml-org/Meta-Llama-3-8B-Q4_0-GGUF")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("llama_cpp", Status::Unsafe),
+                ("llama_cpp", Status::PartiallySafe),
+                ("llama_cpp", Status::Safe),
+                ("llama_cpp", Status::Unsafe),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_evaluate_load() {
+        let code = r#"
+import evaluate
+
+accuracy = evaluate.load("accuracy")
+accuracy = evaluate.load("accuracy", revision="main")
+accuracy = evaluate.load("accuracy", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("evaluate_load", Status::Unsafe),
+                ("evaluate_load", Status::PartiallySafe),
+                ("evaluate_load", Status::Safe),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_inference_api() {
+        let code = r#"
+from huggingface_hub import InferenceApi, InferenceClient
+
+client = InferenceClient(model="org/model")
+pinned = InferenceClient(model="org/model", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+legacy = InferenceApi(repo_id="org/model", revision="main")
+legacy = InferenceApi(repo_id="org/model")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("inference_api", Status::RemoteCall),
+                ("inference_api", Status::Safe),
+                ("inference_api", Status::PartiallySafe),
+                ("inference_api", Status::RemoteCall),
+            ]
+        );
+        assert_eq!(count_usages(&findings), (1, 3, 0));
+    }
+
+    #[test]
+    fn test_scan_code_openai_create() {
+        let code = r#"
+import openai
+
+a = openai.ChatCompletion.create(model="gpt-4", messages=messages)
+b = openai.ChatCompletion.create(model="gpt-4-0314", messages=messages)
+c = openai.Completion.create(model='text-davinci-003', prompt=prompt)
+d = openai.ChatCompletion.create(model="gpt-4o-2024-05-13", messages=messages)
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(&str, Status)> = findings
+            .iter()
+            .map(|f| (f.pattern.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("openai_create", Status::ApiVersionUnpinned),
+                ("openai_create", Status::Safe),
+                ("openai_create", Status::ApiVersionUnpinned),
+                ("openai_create", Status::Safe),
+            ]
+        );
+        assert_eq!(count_usages(&findings), (2, 2, 0));
+    }
+
+    #[test]
+    fn test_scan_code_in_test_fixture() {
+        let code = r#"
+import pytest
+
+@pytest.fixture(scope="session")
+def model():
+    return AutoModel.from_pretrained("model")
+
+@fixture
+async def tokenizer():
+    return AutoTokenizer.from_pretrained("model")
+
+class LoadTest(unittest.TestCase):
+    def setUp(self):
+        self.model = AutoModel.from_pretrained("model")
+
+    @staticmethod
+    def helper():
+        return AutoModel.from_pretrained("model")
+
+def production():
+    return AutoModel.from_pretrained("model")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(usize, bool)> = findings
+            .iter()
+            .map(|f| (f.line, f.in_test_fixture))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(6, true), (10, true), (14, true), (18, false), (21, false)]
+        );
+        assert!(findings.iter().all(|f| f.status == Status::Unsafe));
+    }
+
+    #[test]
+    fn test_scan_code_noscan_comment() {
+        let code = r#"
+a = AutoModel.from_pretrained("org/model")  # noscan
+b = AutoModel.from_pretrained(
+    "org/model",  # noscan: vetted by security
+)
+c = AutoModel.from_pretrained("org/model")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(usize, Status, Option<SuppressionReason>)> = findings
+            .iter()
+            .map(|f| (f.line, f.status, f.suppression_reason))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, Status::Ignored, Some(SuppressionReason::NoscanComment)),
+                (3, Status::Ignored, Some(SuppressionReason::NoscanComment)),
+                (6, Status::Unsafe, None),
+            ]
+        );
+        assert_eq!(count_usages(&findings), (0, 0, 1));
+
+        let json = serde_json::to_value(&findings[0]).unwrap();
+        assert_eq!(json["status"], "ignored");
+        assert_eq!(json["suppression_reason"], "noscan_comment");
+        assert!(findings[2].suppression_reason.is_none());
+        assert_eq!(
+            serde_json::to_value(SuppressionReason::IgnoreFile).unwrap(),
+            "ignore_file"
+        );
+
+        let shell = scan_shell_script("huggingface-cli download org/model  # noscan\n");
+        assert_eq!(shell[0].status, Status::Ignored);
+    }
+
+    #[test]
+    fn test_scan_code_indented_in_with_block() {
+        let code = r#"
+from accelerate import init_empty_weights
+
+with init_empty_weights():
+    model = AutoModel.from_pretrained("model")
+
+def load():
+    with accelerate.init_empty_weights():
+        if offload:
+            model = AutoModel.from_pretrained("model", revision="main")
+
+with torch.no_grad():
+    model = AutoModel.from_pretrained("model", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+
+with accelerate.init_empty_weights(): model = AutoModel.from_pretrained("model")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        let summary: Vec<(usize, Status, bool)> = findings
+            .iter()
+            .map(|f| (f.line, f.status, f.in_init_empty_weights))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (5, Status::Unsafe, true),
+                (10, Status::PartiallySafe, true),
+                (13, Status::Safe, false),
+                (15, Status::Unsafe, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_code_safetensors_load_file() {
+        let unverified = r#"
+from safetensors.torch import load_file
+
+tensors = load_file("model.safetensors")
+"#;
+        let findings = scan_code(unverified, &ScanConfig::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].pattern, "safetensors_load_file");
+        assert_eq!(findings[0].status, Status::PartiallySafe);
+
+        let verified = r#"
+import hashlib
+import safetensors.torch
+
+with open(path, "rb") as f:
+    digest = hashlib.sha256(f.read()).hexdigest()
+if digest != EXPECTED_SHA256:
+    raise ValueError("checksum mismatch")
+tensors = safetensors.torch.load_file(path)
+"#;
+        assert_eq!(
+            scan_code_for_usage(verified, &ScanConfig::default()),
+            (1, 0, 0)
+        );
+
+        let too_far = r#"
+import hashlib
+from safetensors.torch import load_file
+
+digest = hashlib.sha256(data).hexdigest()
+assert digest == EXPECTED_SHA256
+step_one()
+step_two()
+step_three()
+step_four()
+step_five()
+tensors = load_file(path)
+"#;
+        assert_eq!(
+            scan_code_for_usage(too_far, &ScanConfig::default()),
+            (0, 1, 0)
+        );
+
+        let not_safetensors = "config = load_file(\"settings.yaml\")\n";
+        assert!(scan_code(not_safetensors, &ScanConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_vllm() {
+        let code = r#"
+from vllm import LLM
+import vllm
+
+unpinned = LLM("mistralai/Mistral-7B-v0.1")
+branch = LLM("mistralai/Mistral-7B-v0.1", revision="main")
+pinned = vllm.LLM(model="mistralai/Mistral-7B-v0.1", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+"#;
+        let findings = scan_code(code, &ScanConfig::default());
+        assert!(findings.iter().all(|f| f.pattern == "vllm"));
+        assert_eq!(scan_code_for_usage(code, &ScanConfig::default()), (1, 1, 1));
+
+        let other_llm = "llm = LLM(\"model\")\n";
+        assert!(scan_code(other_llm, &ScanConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_scan_shell_script() {
+        let script = r#"#!/bin/bash
+set -e
+huggingface-cli download mistralai/Mistral-7B-v0.1 --local-dir ./models
+huggingface-cli download org/model --revision main
+huggingface-cli download "org/model" \
+    --revision 5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d \
+    --local-dir ./pinned
+huggingface-cli download $MODEL_ID --revision=v1.0
+"#;
+        let findings = scan_shell_script(script);
+        let statuses: Vec<Status> = findings.iter().map(|f| f.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Status::Unsafe,
+                Status::PartiallySafe,
+                Status::Safe,
+                Status::PartiallySafe
+            ]
+        );
+        assert_eq!(
+            findings.iter().map(|f| f.line).collect::<Vec<_>>(),
+            vec![3, 4, 5, 8]
+        );
+        assert_eq!(
+            findings[2].model_name,
+            Some(ModelName::Literal("org/model".to_string()))
+        );
+        assert_eq!(
+            findings[3].model_name,
+            Some(ModelName::DynamicName("$MODEL_ID".to_string()))
+        );
+        assert!(scan_shell_script("pip install huggingface_hub\n").is_empty());
+    }
+
+    #[test]
+    fn test_scan_code_for_usage_local_files_only() {
+        let code = r#"
+model = AutoModel.from_pretrained("model", local_files_only=True)
+tokenizer = AutoTokenizer.from_pretrained("model", local_files_only=False)
+"#;
+        assert_eq!(scan_code_for_usage(code, &ScanConfig::default()), (1, 0, 1));
+    }
+
+    #[test]
+    fn test_scan_code_training_with_unsafe_base() {
+        let fine_tune = r#"
+model = AutoModelForCausalLM.from_pretrained("gpt2")
+pinned = AutoTokenizer.from_pretrained("gpt2", revision="5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d")
+trainer = Trainer(model=model, args=training_args, train_dataset=dataset)
+trainer.train()
+"#;
+        let findings = scan_code(fine_tune, &ScanConfig::default());
+        assert_eq!(findings[0].status, Status::TrainingWithUnsafeBase);
+        assert_eq!(findings[1].status, Status::Safe);
+        assert_eq!(
+            scan_code_for_usage(fine_tune, &ScanConfig::default()),
+            (1, 0, 1)
+        );
+
+        let train_only = r#"
+trainer = Trainer(model=model, args=training_args)
+trainer.train()
+"#;
+        assert!(scan_code(train_only, &ScanConfig::default()).is_empty());
+
+        let no_trainer = r#"
+model = AutoModel.from_pretrained("gpt2")
+model.train()
+"#;
+        assert_eq!(
+            scan_code(no_trainer, &ScanConfig::default())[0].status,
+            Status::Unsafe
+        );
+    }
+}
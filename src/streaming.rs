@@ -0,0 +1,326 @@
+use regex::Regex;
+use std::collections::VecDeque;
+use std::io::{BufRead, Lines};
+use std::sync::LazyLock;
+
+use crate::aliases::ImportAliasMap;
+use crate::config::ScanConfig;
+use crate::scanner::{Finding, Status, scan_code_with_aliases, training_markers};
+
+static IMPORT_LINE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:from|import)\s").unwrap());
+
+/// Lines a single call may span in [`scan_code_streaming`] unless told otherwise.
+pub const DEFAULT_WINDOW_LINES: usize = 50;
+
+/// Scan Python source line by line, holding at most three windows of `window_lines` lines
+/// in memory instead of the whole file, plus the file's import lines and the headers of
+/// the blocks still open above the windows.
+///
+/// Each window is classified with [`scan_code`](crate::scan_code), together with the
+/// window before it and the one after it. The carried headers keep the enclosing function,
+/// loops, `with init_empty_weights():` blocks and test fixture decorators of a call however
+/// far above it they are, so findings match those of `scan_code` on the whole file as
+/// long as:
+///
+/// - no call spans more than `window_lines` lines and `context_lines` is at most as large,
+/// - `window_lines` is at least 5, the lines searched for a hash check above a
+///   `safetensors` load,
+/// - the imports gating `safetensors` and `vllm` patterns and defining import aliases come
+///   before the calls, and
+/// - the `Trainer(...)` and `.train()` that raise unsafe loads to
+///   [`Status::TrainingWithUnsafeBase`] are read before the window holding the load ends.
+///
+/// Read errors end the iteration and are logged.
+pub fn scan_code_streaming<R: BufRead>(
+    reader: R,
+    config: &ScanConfig,
+    window_lines: usize,
+) -> StreamingScan<'_, R> {
+    StreamingScan {
+        lines: reader.lines(),
+        config,
+        window_lines: window_lines.max(1),
+        buffer: VecDeque::new(),
+        buffer_first_line: 1,
+        emit_from: 1,
+        imports: Vec::new(),
+        open_blocks: Vec::new(),
+        decorators: Vec::new(),
+        alias_imports: Vec::new(),
+        aliases: ImportAliasMap::default(),
+        constructs_trainer: false,
+        calls_train: false,
+        pending: VecDeque::new(),
+        eof: false,
+    }
+}
+
+/// Iterator returned by [`scan_code_streaming`].
+pub struct StreamingScan<'a, R> {
+    lines: Lines<R>,
+    config: &'a ScanConfig,
+    window_lines: usize,
+    /// Lines `buffer_first_line..`, reaching one window back from `emit_from`.
+    buffer: VecDeque<String>,
+    buffer_first_line: usize,
+    /// First line whose findings have not been emitted yet.
+    emit_from: usize,
+    /// Import statements already dropped from `buffer`, kept for the import-gated patterns
+    /// and import aliases.
+    imports: Vec<String>,
+    /// Lines dropped from `buffer` that still open a block, as `(indent, lines)` with any
+    /// decorators of the header first, outermost block first.
+    open_blocks: Vec<(usize, Vec<String>)>,
+    /// Decorator lines dropped from `buffer` right above its first line.
+    decorators: Vec<String>,
+    /// Import lines `aliases` was collected from.
+    alias_imports: Vec<String>,
+    aliases: ImportAliasMap,
+    constructs_trainer: bool,
+    calls_train: bool,
+    pending: VecDeque<Finding>,
+    eof: bool,
+}
+
+impl<R: BufRead> StreamingScan<'_, R> {
+    /// Read until the buffer holds one full window after the lines about to be emitted.
+    fn fill(&mut self) {
+        let wanted = self.emit_from + 2 * self.window_lines - self.buffer_first_line;
+        while !self.eof && self.buffer.len() < wanted {
+            match self.lines.next() {
+                Some(Ok(line)) => {
+                    let (trainer, train) = training_markers(&line);
+                    self.constructs_trainer |= trainer;
+                    self.calls_train |= train;
+                    self.buffer.push_back(line);
+                }
+                Some(Err(e)) => {
+                    tracing::error!("Failed to read source: {e}");
+                    self.eof = true;
+                }
+                None => self.eof = true,
+            }
+        }
+    }
+
+    /// Drop the first line of `buffer`, keeping what later windows need of it.
+    fn drop_line(&mut self) {
+        let Some(line) = self.buffer.pop_front() else {
+            return;
+        };
+        self.buffer_first_line += 1;
+        let trimmed = line.trim_start();
+        // Mirrors how `scan_code` walks back from a call: blank lines and comments are
+        // skipped, and any line indented less than the ones after it opens a block.
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            self.decorators.clear();
+            return;
+        }
+        let indent = line.len() - trimmed.len();
+        while self
+            .open_blocks
+            .last()
+            .is_some_and(|&(open, _)| open >= indent)
+        {
+            self.open_blocks.pop();
+        }
+        if IMPORT_LINE_RE.is_match(&line) {
+            self.imports.push(line.trim().to_string());
+            self.decorators.clear();
+        } else if trimmed.starts_with('@') {
+            self.decorators.push(line);
+        } else {
+            let mut lines = std::mem::take(&mut self.decorators);
+            lines.push(line);
+            self.open_blocks.push((indent, lines));
+        }
+    }
+
+    /// Classify the next window and queue its findings.
+    fn scan_window(&mut self) {
+        let mut code = String::new();
+        let prefix = self
+            .imports
+            .iter()
+            .chain(self.open_blocks.iter().flat_map(|(_, lines)| lines))
+            .chain(&self.decorators);
+        let mut prefix_lines = 0;
+        for line in prefix {
+            code.push_str(line);
+            code.push('\n');
+            prefix_lines += 1;
+        }
+        for line in &self.buffer {
+            code.push_str(line);
+            code.push('\n');
+        }
+
+        // Import aliases only change with the import lines, so they are collected again
+        // only when those do.
+        let import_lines = self.imports.iter().chain(
+            self.buffer
+                .iter()
+                .filter(|line| IMPORT_LINE_RE.is_match(line)),
+        );
+        if !import_lines.clone().eq(&self.alias_imports) {
+            self.alias_imports = import_lines.cloned().collect();
+            self.aliases = ImportAliasMap::from_code(&self.alias_imports.join("\n"));
+        }
+        let emit_until = if self.eof {
+            self.buffer_first_line + self.buffer.len()
+        } else {
+            self.emit_from + self.window_lines
+        };
+        let is_training = self.constructs_trainer && self.calls_train;
+
+        for mut finding in scan_code_with_aliases(&code, self.config, &self.aliases) {
+            // Skip the lines in front of the buffer.
+            let Some(buffer_line) = finding.line.checked_sub(prefix_lines + 1) else {
+                continue;
+            };
+            let line = self.buffer_first_line + buffer_line;
+            if line < self.emit_from || line >= emit_until {
+                continue;
+            }
+            finding.line = line;
+            // `scan_code` only saw this window; decide from everything read so far instead.
+            if finding.status == Status::TrainingWithUnsafeBase && !is_training {
+                finding.status = Status::Unsafe;
+            } else if finding.status == Status::Unsafe
+                && is_training
+                && finding.call.contains("from_pretrained")
+            {
+                finding.status = Status::TrainingWithUnsafeBase;
+            }
+            self.pending.push_back(finding);
+        }
+
+        self.emit_from = emit_until;
+        while self.buffer_first_line + self.window_lines < self.emit_from && !self.buffer.is_empty()
+        {
+            self.drop_line();
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for StreamingScan<'_, R> {
+    type Item = Finding;
+
+    fn next(&mut self) -> Option<Finding> {
+        loop {
+            if let Some(finding) = self.pending.pop_front() {
+                return Some(finding);
+            }
+            if self.eof && self.emit_from >= self.buffer_first_line + self.buffer.len() {
+                return None;
+            }
+            self.fill();
+            self.scan_window();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::scan_code;
+
+    /// Lines between a block header and the call inside it, over four 7 line windows.
+    fn filler(indent: &str) -> String {
+        format!("{indent}x = 1\n").repeat(30)
+    }
+
+    /// The findings of `code` streamed in 7 line windows, checked against [`scan_code`].
+    fn stream_like_scan_code(code: &str) -> Vec<Finding> {
+        let config = ScanConfig::default();
+        let streamed: Vec<Finding> = scan_code_streaming(code.as_bytes(), &config, 7).collect();
+        assert_eq!(streamed, scan_code(code, &config));
+        streamed
+    }
+
+    #[test]
+    fn test_scan_code_streaming_matches_scan_code() {
+        let mut code = String::from(
+            "import hashlib\nfrom safetensors.torch import load_file\nfrom transformers import AutoModel\n",
+        );
+        code.push_str(include_str!("../examples/project-1/repo-1/mixed_unsafe.py"));
+        for i in 0..20 {
+            code.push_str(&format!("\ndef step_{i}(names):\n    for name in names:\n        model = AutoModel.from_pretrained(\n            name,\n            revision=\"main\",\n        )\n"));
+        }
+        code.push_str("weights = load_file(\"model.safetensors\")\n");
+
+        let config = ScanConfig {
+            context_lines: Some(2),
+            ..Default::default()
+        };
+        let expected = scan_code(&code, &config);
+        assert!(expected.len() > 20);
+        for window_lines in [7, DEFAULT_WINDOW_LINES] {
+            let streamed: Vec<Finding> =
+                scan_code_streaming(code.as_bytes(), &config, window_lines).collect();
+            assert_eq!(streamed, expected, "window of {window_lines} lines");
+        }
+    }
+
+    #[test]
+    fn test_scan_code_streaming_training() {
+        let code = "model = AutoModel.from_pretrained(\"model\")\ntrainer = Trainer(model=model)\ntrainer.train()\n";
+        let streamed: Vec<Finding> = scan_code_streaming(
+            code.as_bytes(),
+            &ScanConfig::default(),
+            DEFAULT_WINDOW_LINES,
+        )
+        .collect();
+        assert_eq!(streamed, scan_code(code, &ScanConfig::default()));
+        assert_eq!(streamed[0].status, Status::TrainingWithUnsafeBase);
+    }
+
+    #[test]
+    fn test_scan_code_streaming_enclosing_function() {
+        let code = format!(
+            "def load_all(names):\n{}    AutoModel.from_pretrained(\"gpt2\")\n",
+            filler("    ")
+        );
+        let streamed = stream_like_scan_code(&code);
+        assert_eq!(streamed[0].enclosing_function.as_deref(), Some("load_all"));
+    }
+
+    #[test]
+    fn test_scan_code_streaming_test_fixture() {
+        let code = format!(
+            "@pytest.fixture\ndef model():\n{}    return AutoModel.from_pretrained(\"gpt2\")\n",
+            filler("    ")
+        );
+        assert!(stream_like_scan_code(&code)[0].in_test_fixture);
+    }
+
+    #[test]
+    fn test_scan_code_streaming_init_empty_weights() {
+        let code = format!(
+            "with init_empty_weights():\n{}    AutoModel.from_pretrained(\"gpt2\")\n",
+            filler("    ")
+        );
+        assert!(stream_like_scan_code(&code)[0].in_init_empty_weights);
+    }
+
+    #[test]
+    fn test_scan_code_streaming_loop() {
+        let code = format!(
+            "for name in names:\n{}    AutoModel.from_pretrained(name)\n",
+            filler("    ")
+        );
+        assert!(stream_like_scan_code(&code)[0].in_loop);
+    }
+
+    #[test]
+    fn test_scan_code_streaming_import_alias() {
+        let code = format!(
+            "from transformers import AutoModel as AM\n{}AM.from_pretrained(\"gpt2\")\n",
+            filler("")
+        );
+        let streamed = stream_like_scan_code(&code);
+        assert_eq!(streamed.len(), 1);
+        assert_eq!(streamed[0].call, "AM.from_pretrained(\"gpt2\")");
+    }
+}
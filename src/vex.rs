@@ -0,0 +1,230 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cache::content_hash;
+use crate::directory::ScanResults;
+use crate::remediation::remediation_action;
+use crate::scanner::{Finding, Status};
+
+/// JSON-LD context of the OpenVEX version written by [`openvex_document`].
+pub const OPENVEX_CONTEXT: &str = "https://openvex.dev/ns/v0.2.0";
+
+/// Vulnerability every statement refers to: CWE-494, Download of Code Without Integrity
+/// Check, stands in for the risk of loading an unpinned model.
+pub const VEX_VULNERABILITY: &str = "CWE-494";
+
+#[derive(Debug, Serialize)]
+pub struct VexDocument {
+    #[serde(rename = "@context")]
+    pub context: &'static str,
+    #[serde(rename = "@id")]
+    pub id: String,
+    pub author: String,
+    pub timestamp: String,
+    pub version: u32,
+    pub tooling: String,
+    pub statements: Vec<VexStatement>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct VexStatement {
+    pub vulnerability: VexVulnerability,
+    pub products: Vec<VexProduct>,
+    /// `affected`, `not_affected` or `under_investigation`.
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub justification: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impact_statement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_statement: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct VexVulnerability {
+    pub name: &'static str,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct VexProduct {
+    #[serde(rename = "@id")]
+    pub id: String,
+    pub subcomponents: Vec<VexSubcomponent>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct VexSubcomponent {
+    #[serde(rename = "@id")]
+    pub id: String,
+}
+
+/// `seconds` since the Unix epoch as an RFC 3339 UTC timestamp.
+pub(crate) fn rfc3339(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let secs = seconds % 86_400;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's `civil_from_days`).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// The statement for one finding, `None` for ignored findings. Unsafe findings are
+/// `affected`, with the [`remediation_action`] for the call as the action, safe ones
+/// `not_affected`, and those pinned to a mutable revision or otherwise partially safe
+/// `under_investigation`.
+fn statement(finding: &Finding, project: (&str, &str)) -> Option<VexStatement> {
+    let (status, justification, impact_statement, action_statement) = match finding.status {
+        Status::Ignored => return None,
+        Status::Safe => (
+            "not_affected",
+            Some("inline_mitigations_already_exist"),
+            Some("The download is pinned to an immutable commit SHA or a local path.".to_string()),
+            None,
+        ),
+        status if status >= Status::Unsafe => {
+            ("affected", None, None, Some(remediation_action(finding)))
+        }
+        _ => ("under_investigation", None, None, None),
+    };
+    Some(VexStatement {
+        vulnerability: VexVulnerability {
+            name: VEX_VULNERABILITY,
+        },
+        products: vec![VexProduct {
+            id: format!("pkg:generic/{}/{}", project.0, project.1),
+            subcomponents: vec![VexSubcomponent {
+                id: format!("{}#L{}", finding.file, finding.line),
+            }],
+        }],
+        status,
+        justification,
+        impact_statement,
+        action_statement,
+    })
+}
+
+/// An OpenVEX document with one statement per finding, for SBOM workflows. The product of
+/// each statement is the finding's `org/repo` project and its subcomponent the file and
+/// line of the call; calls on the same line with the same outcome share a statement. The
+/// document `@id` is derived from its statements.
+pub fn openvex_document(results: &ScanResults, timestamp: SystemTime) -> VexDocument {
+    let projects: HashMap<&str, (&str, &str)> = results
+        .file_rows
+        .iter()
+        .map(|row| (row.file.as_str(), (row.org.as_str(), row.repo.as_str())))
+        .collect();
+    let mut statements: Vec<VexStatement> = Vec::new();
+    for finding in &results.findings {
+        let project = projects
+            .get(finding.file.as_str())
+            .copied()
+            .unwrap_or(("unknown", "unknown"));
+        if let Some(statement) = statement(finding, project)
+            && !statements.contains(&statement)
+        {
+            statements.push(statement);
+        }
+    }
+    let statements_json = serde_json::to_string(&statements).expect("statements serialize");
+    let seconds = timestamp
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    VexDocument {
+        context: OPENVEX_CONTEXT,
+        id: format!(
+            "https://openvex.dev/docs/public/vex-{}",
+            content_hash(&statements_json)
+        ),
+        author: "hfscanner".to_string(),
+        timestamp: rfc3339(seconds),
+        version: 1,
+        tooling: format!("hfscanner {}", env!("CARGO_PKG_VERSION")),
+        statements,
+    }
+}
+
+impl VexDocument {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("VEX document serializes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use crate::directory::scan_directory;
+    use serde_json::Value;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(951_827_696), "2000-02-29T12:34:56Z");
+        assert_eq!(rfc3339(1_735_689_599), "2024-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_openvex_document() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        let repo_dir = root.path().join("org").join("repo");
+        fs::create_dir_all(&repo_dir)?;
+        fs::write(
+            repo_dir.join("load.py"),
+            "a = AutoModel.from_pretrained(\"gpt2\")\n\
+             b = AutoModel.from_pretrained(\"gpt2\", revision=\"5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d\")\n\
+             c = AutoModel.from_pretrained(\"gpt2\", revision=\"main\")\n\
+             d = AutoModel.from_pretrained(\"gpt2\")  # noscan\n",
+        )?;
+        let results = scan_directory(root.path(), &ScanConfig::default());
+
+        let timestamp = UNIX_EPOCH + Duration::from_secs(1_735_689_599);
+        let document = openvex_document(&results, timestamp);
+        let doc: Value = serde_json::from_str(&document.to_json())?;
+
+        assert_eq!(doc["timestamp"], "2024-12-31T23:59:59Z");
+        let statuses: Vec<&str> = doc["statements"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["status"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            statuses,
+            vec!["affected", "not_affected", "under_investigation"]
+        );
+        let first = &doc["statements"][0];
+        assert_eq!(first["vulnerability"]["name"], "CWE-494");
+        assert_eq!(
+            first["action_statement"],
+            "Pin the download to a commit SHA: \
+             AutoModel.from_pretrained(\"gpt2\", revision=\"<commit-sha>\")"
+        );
+        assert_eq!(first["products"][0]["@id"], "pkg:generic/org/repo");
+        assert_eq!(
+            first["products"][0]["subcomponents"][0]["@id"],
+            "org/repo/load.py#L1"
+        );
+        assert_eq!(
+            openvex_document(&results, timestamp).id,
+            document.id,
+            "the document ID is derived from its statements"
+        );
+
+        Ok(())
+    }
+}
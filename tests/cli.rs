@@ -0,0 +1,249 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn hfscanner(root: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hfscanner"))
+        .arg(root)
+        .args(args)
+        .env_remove("GITHUB_ACTIONS")
+        .output()
+        .expect("failed to run hfscanner")
+}
+
+fn unsafe_fixture() -> std::io::Result<tempfile::TempDir> {
+    let root = tempfile::tempdir()?;
+    let repo = root.path().join("org").join("repo");
+    std::fs::create_dir_all(&repo)?;
+    std::fs::write(
+        repo.join("load.py"),
+        "model = AutoModel.from_pretrained(\"gpt2\", revision=\"main\")\n\
+         tokenizer = AutoTokenizer.from_pretrained(\"gpt2\")\n",
+    )?;
+    Ok(root)
+}
+
+#[test]
+fn test_ci_mode() -> std::io::Result<()> {
+    let root = unsafe_fixture()?;
+
+    let output = hfscanner(root.path(), &["--ci-mode"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("::error file=org/repo/load.py,line=2::AutoTokenizer call"));
+    assert!(stdout.contains("::warning file=org/repo/load.py,line=1::"));
+    assert!(!stdout.contains("Scan Summary"));
+
+    // Without --ci-mode, unsafe findings alone do not fail the scan.
+    let output = hfscanner(root.path(), &[]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Scan Summary"));
+
+    Ok(())
+}
+
+#[test]
+fn test_ci_mode_overrides() -> std::io::Result<()> {
+    let root = unsafe_fixture()?;
+
+    let output = hfscanner(root.path(), &["--ci-mode", "--summary"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Scan Summary"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hfscanner"))
+        .arg(root.path())
+        .env("GITHUB_ACTIONS", "true")
+        .output()?;
+    assert!(!output.status.success());
+    let output = Command::new(env!("CARGO_BIN_EXE_hfscanner"))
+        .arg(root.path())
+        .arg("--no-ci-mode")
+        .env("GITHUB_ACTIONS", "true")
+        .output()?;
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("::warning"));
+
+    let output = hfscanner(root.path(), &["--ci-mode", "--no-line-annotations"]);
+    assert!(!output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("::"));
+
+    Ok(())
+}
+
+#[test]
+fn test_version() {
+    for flag in ["--version", "-V"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_hfscanner"))
+            .arg(flag)
+            .output()
+            .expect("failed to run hfscanner");
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let version = stdout.trim().strip_prefix("hfscanner ").unwrap();
+        assert_eq!(version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(version.split('.').count(), 3);
+        assert!(hfscanner::parse_version(version).is_some());
+    }
+}
+
+#[test]
+fn test_perf_profile_json() -> std::io::Result<()> {
+    let root = unsafe_fixture()?;
+    let out = tempfile::tempdir()?;
+    let profile = out.path().join("profile.json");
+
+    let output = hfscanner(
+        root.path(),
+        &["--perf-profile-json", profile.to_str().unwrap()],
+    );
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&profile)?)?;
+    let phases = json.as_object().unwrap();
+    let mut keys: Vec<&str> = phases.keys().map(String::as_str).collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec!["aggregate", "output", "read", "regex_match", "walk"]
+    );
+    assert!(
+        phases
+            .values()
+            .all(|ms| ms.as_f64().is_some_and(|ms| ms >= 0.0))
+    );
+    assert!(phases["regex_match"].as_f64().unwrap() > 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn test_pyproject_config() -> std::io::Result<()> {
+    // One unsafe and one partially safe finding: a risk score of 11.
+    let root = unsafe_fixture()?;
+    let pyproject = "[project]\nname = \"models\"\n\n[tool.silentinjection]\nfail-on-score = 5\n";
+    let in_dir = |dir: &Path, args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_hfscanner"))
+            .arg(root.path())
+            .args(args)
+            .current_dir(dir)
+            .env_remove("GITHUB_ACTIONS")
+            .output()
+            .expect("failed to run hfscanner")
+    };
+
+    // Config files in the scanned tree are never read.
+    std::fs::write(root.path().join("org").join("pyproject.toml"), pyproject)?;
+    std::fs::write(root.path().join("pyproject.toml"), pyproject)?;
+    assert!(hfscanner(root.path(), &[]).status.success());
+
+    // Only those in the working directory are.
+    let cwd = tempfile::tempdir()?;
+    std::fs::write(cwd.path().join("pyproject.toml"), pyproject)?;
+    assert!(!in_dir(cwd.path(), &[]).status.success());
+    assert!(
+        in_dir(cwd.path(), &["--fail-on-score", "15"])
+            .status
+            .success()
+    );
+
+    // And they cannot set options that reach the network, write files or weaken the scan.
+    std::fs::write(
+        cwd.path().join(".silentinjection.toml"),
+        "github-api-url = \"https://attacker.example\"\n",
+    )?;
+    let output = in_dir(cwd.path(), &[]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("github-api-url can only be set in a file given with --config")
+    );
+    std::fs::remove_file(cwd.path().join(".silentinjection.toml"))?;
+
+    let explicit = root.path().join("ci.toml");
+    std::fs::write(&explicit, "fail-on-score = 15\n")?;
+    assert!(
+        hfscanner(root.path(), &["--config", explicit.to_str().unwrap()])
+            .status
+            .success()
+    );
+
+    // A command line flag also overrides the config options it is exclusive with.
+    let csv = root.path().join("header.csv");
+    std::fs::write(&explicit, "fail-on-score = 5\ncsv-no-header = true\n")?;
+    let output = hfscanner(
+        root.path(),
+        &[
+            "--config",
+            explicit.to_str().unwrap(),
+            "--severity-threshold",
+            "15",
+            "--csv",
+            csv.to_str().unwrap(),
+            "--csv-header-only",
+        ],
+    );
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&csv)?.lines().count(), 1);
+
+    std::fs::write(&explicit, "verbose = true\n")?;
+    let output = hfscanner(root.path(), &["--config", explicit.to_str().unwrap()]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("DEBUG"));
+
+    std::fs::write(&explicit, "fail-on-score = true\n")?;
+    let output = hfscanner(root.path(), &["--config", explicit.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("fail-on-score expects a string or number")
+    );
+
+    std::fs::write(&explicit, "verbos = true\n")?;
+    let output = hfscanner(root.path(), &["--config", explicit.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown option 'verbos'"));
+
+    Ok(())
+}
+
+#[test]
+fn test_severity_threshold() -> std::io::Result<()> {
+    // One unsafe and one partially safe finding: a risk score of 11.
+    let root = unsafe_fixture()?;
+
+    assert!(
+        !hfscanner(root.path(), &["--severity-threshold", "5"])
+            .status
+            .success()
+    );
+    assert!(
+        hfscanner(root.path(), &["--severity-threshold", "15"])
+            .status
+            .success()
+    );
+    assert!(
+        !hfscanner(
+            root.path(),
+            &["--severity-threshold", "15", "--fail-on", "unsafe"]
+        )
+        .status
+        .success()
+    );
+    assert!(
+        !hfscanner(root.path(), &["--severity-threshold", "high"])
+            .status
+            .success()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_pattern_flags() -> std::io::Result<()> {
+    let root = unsafe_fixture()?;
+
+    for flag in ["--generated-marker", "--revision-pattern"] {
+        let output = hfscanner(root.path(), &[flag, "("]);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains(&format!("Invalid {flag}")));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,285 @@
+use hfscanner::{ScanConfig, cyclonedx_bom, openvex_document, scan_directory};
+use regex::Regex;
+use serde_json::{Value, json};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn load_schema(name: &str) -> Value {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("schemas")
+        .join(name);
+    let json = std::fs::read_to_string(&path).expect("schema is readable");
+    serde_json::from_str(&json).expect("schema is valid JSON")
+}
+
+/// Errors from validating `value` against the vendored JSON `schema`, empty when it is
+/// valid. Only the keywords used by the schemas in `tests/schemas` are implemented; any
+/// other keyword panics rather than being silently skipped.
+fn schema_errors(schema: &Value, value: &Value) -> Vec<String> {
+    schema_errors_at(schema, schema, value)
+}
+
+/// [`schema_errors`] for `schema` within the schema document `root`.
+fn schema_errors_at(root: &Value, schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate(root, schema, value, "", &mut errors);
+    errors
+}
+
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(keywords) = schema.as_object() else {
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{path}: not allowed"));
+        }
+        return;
+    };
+    for (keyword, arg) in keywords {
+        match keyword.as_str() {
+            "$ref" => {
+                let pointer = arg.as_str().unwrap().strip_prefix('#').unwrap();
+                let target = root.pointer(pointer).expect("$ref resolves");
+                validate(root, target, value, path, errors);
+            }
+            "type" => {
+                let types: Vec<&str> = match arg {
+                    Value::Array(types) => types.iter().map(|t| t.as_str().unwrap()).collect(),
+                    kind => vec![kind.as_str().unwrap()],
+                };
+                if !types.iter().any(|kind| has_type(value, kind)) {
+                    errors.push(format!("{path}: expected {types:?}, got {value}"));
+                }
+            }
+            "enum" => {
+                if !arg.as_array().unwrap().contains(value) {
+                    errors.push(format!("{path}: {value} is not one of {arg}"));
+                }
+            }
+            "const" => {
+                if arg != value {
+                    errors.push(format!("{path}: expected {arg}, got {value}"));
+                }
+            }
+            "required" => {
+                if let Some(object) = value.as_object() {
+                    for key in arg.as_array().unwrap() {
+                        if !object.contains_key(key.as_str().unwrap()) {
+                            errors.push(format!("{path}: missing {key}"));
+                        }
+                    }
+                }
+            }
+            "properties" => {
+                if let Some(object) = value.as_object() {
+                    for (key, property) in arg.as_object().unwrap() {
+                        if let Some(field) = object.get(key) {
+                            validate(root, property, field, &format!("{path}/{key}"), errors);
+                        }
+                    }
+                }
+            }
+            "additionalProperties" => {
+                if let Some(object) = value.as_object() {
+                    let known = keywords.get("properties").and_then(Value::as_object);
+                    for (key, field) in object {
+                        if !known.is_some_and(|known| known.contains_key(key)) {
+                            validate(root, arg, field, &format!("{path}/{key}"), errors);
+                        }
+                    }
+                }
+            }
+            "items" => {
+                if let Some(items) = value.as_array() {
+                    for (i, item) in items.iter().enumerate() {
+                        validate(root, arg, item, &format!("{path}/{i}"), errors);
+                    }
+                }
+            }
+            "minItems" => {
+                if value
+                    .as_array()
+                    .is_some_and(|items| (items.len() as u64) < arg.as_u64().unwrap())
+                {
+                    errors.push(format!("{path}: fewer than {arg} items"));
+                }
+            }
+            "uniqueItems" => {
+                if arg == &Value::Bool(true)
+                    && let Some(items) = value.as_array()
+                {
+                    for (i, item) in items.iter().enumerate() {
+                        if items[..i].contains(item) {
+                            errors.push(format!("{path}/{i}: duplicate item"));
+                        }
+                    }
+                }
+            }
+            "minimum" => {
+                if value.as_f64().is_some_and(|n| n < arg.as_f64().unwrap()) {
+                    errors.push(format!("{path}: {value} is below {arg}"));
+                }
+            }
+            "minLength" => {
+                if value
+                    .as_str()
+                    .is_some_and(|s| (s.chars().count() as u64) < arg.as_u64().unwrap())
+                {
+                    errors.push(format!("{path}: shorter than {arg}"));
+                }
+            }
+            "pattern" => {
+                let re = Regex::new(arg.as_str().unwrap()).unwrap();
+                if value.as_str().is_some_and(|s| !re.is_match(s)) {
+                    errors.push(format!("{path}: {value} does not match {arg}"));
+                }
+            }
+            "format" => {
+                if arg == "date-time" && value.as_str().is_some_and(|s| !DATE_TIME_RE.is_match(s)) {
+                    errors.push(format!("{path}: {value} is not a date-time"));
+                }
+            }
+            "allOf" => {
+                for sub in arg.as_array().unwrap() {
+                    validate(root, sub, value, path, errors);
+                }
+            }
+            "anyOf" | "oneOf" => {
+                let matches = arg
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter(|sub| schema_errors_at(root, sub, value).is_empty())
+                    .count();
+                if matches == 0 || (keyword == "oneOf" && matches > 1) {
+                    errors.push(format!("{path}: {matches} of the {keyword} schemas match"));
+                }
+            }
+            "if" => {
+                let branch = if schema_errors_at(root, arg, value).is_empty() {
+                    keywords.get("then")
+                } else {
+                    keywords.get("else")
+                };
+                if let Some(branch) = branch {
+                    validate(root, branch, value, path, errors);
+                }
+            }
+            "then" | "else" | "$schema" | "$id" | "$comment" | "$defs" | "definitions"
+            | "title" | "description" | "default" | "examples" => {}
+            keyword => panic!("unsupported schema keyword {keyword} at {path}"),
+        }
+    }
+}
+
+static DATE_TIME_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:\d{2})$").unwrap()
+});
+
+fn has_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        kind => panic!("unknown type {kind}"),
+    }
+}
+
+#[test]
+fn test_openvex_schema() -> std::io::Result<()> {
+    let schema = load_schema("openvex-0.2.0.json");
+    let root = tempfile::tempdir()?;
+    let repo = root.path().join("org").join("repo");
+    std::fs::create_dir_all(&repo)?;
+    std::fs::write(
+        repo.join("load.py"),
+        "a = AutoModel.from_pretrained(\"gpt2\")\n\
+         b = AutoModel.from_pretrained(\"gpt2\", revision=\"5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d\")\n\
+         c = AutoModel.from_pretrained(\"gpt2\", revision=\"main\")\n\
+         d = AutoModel.from_pretrained(\"gpt2\")  # noscan\n\
+         e = joblib.load(\"model.pkl\")\n\
+         f = AutoModel.from_pretrained(\"a\"); g = AutoModel.from_pretrained(\"a\")\n",
+    )?;
+    let results = scan_directory(root.path(), &ScanConfig::default());
+
+    let timestamp = UNIX_EPOCH + Duration::from_secs(1_735_689_599);
+    let doc: Value = serde_json::from_str(&openvex_document(&results, timestamp).to_json())?;
+    assert_eq!(schema_errors(&schema, &doc), Vec::<String>::new());
+
+    // The validator does catch violations.
+    let mut invalid = doc.clone();
+    invalid["statements"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("action_statement");
+    invalid["statements"][1]["justification"] = json!("trust_me");
+    invalid["version"] = json!(0);
+    assert_eq!(schema_errors(&schema, &invalid).len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_cyclonedx_schema() -> std::io::Result<()> {
+    let schema = load_schema("cyclonedx-1.5.json");
+    let root = tempfile::tempdir()?;
+    let repo = root.path().join("org").join("repo");
+    std::fs::create_dir_all(&repo)?;
+    std::fs::write(
+        repo.join("load.py"),
+        "a = AutoModel.from_pretrained(\"gpt2\", trust_remote_code=True)\n\
+         b = AutoModel.from_pretrained(\"google/bert-base\")\n\
+         c = AutoModel.from_pretrained(\"meta-llama/Llama-2-7b\", revision=\"main\")\n\
+         d = AutoModel.from_pretrained(\"meta-llama/Llama-2-7b\", revision=\"5d0f2e8a7f1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d\")\n",
+    )?;
+    let results = scan_directory(root.path(), &ScanConfig::default());
+
+    let timestamp = UNIX_EPOCH + Duration::from_secs(1_735_689_599);
+    let doc: Value = serde_json::from_str(&cyclonedx_bom(&results, timestamp).to_json())?;
+    assert_eq!(schema_errors(&schema, &doc), Vec::<String>::new());
+
+    // The schema cannot check references, so check them here: bom-refs are unique and
+    // every affected ref is a component.
+    let refs: Vec<&str> = doc["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|component| component["bom-ref"].as_str().unwrap())
+        .collect();
+    let unique: BTreeSet<&str> = refs.iter().copied().collect();
+    assert_eq!(unique.len(), refs.len());
+    let vulnerabilities = doc["vulnerabilities"].as_array().unwrap();
+    for vulnerability in vulnerabilities {
+        for affected in vulnerability["affects"].as_array().unwrap() {
+            assert!(unique.contains(affected["ref"].as_str().unwrap()));
+        }
+    }
+    // One vulnerability per weakness, affecting every component loaded unsafely.
+    let ids: Vec<&str> = vulnerabilities
+        .iter()
+        .map(|vulnerability| vulnerability["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["CWE-494", "CWE-829"]);
+    assert_eq!(
+        vulnerabilities[0]["affects"],
+        json!([{"ref": "pkg:huggingface/google/bert-base"}, {"ref": "pkg:huggingface/gpt2"}])
+    );
+
+    // The validator does catch violations.
+    let mut invalid = doc.clone();
+    invalid["bomFormat"] = json!("SPDX");
+    invalid["components"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("name");
+    invalid["components"][1]["type"] = json!("model");
+    invalid["vulnerabilities"][0]["cwes"][0] = json!("494");
+    assert_eq!(schema_errors(&schema, &invalid).len(), 4);
+
+    Ok(())
+}
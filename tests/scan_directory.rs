@@ -0,0 +1,260 @@
+use hfscanner::{
+    ConcurrencyMode, PatternCounts, ScanCache, ScanConfig, ScanResults, Status, scan_directory,
+    scan_git_index,
+};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+fn examples_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples")
+}
+
+fn project(org: &str, repo: &str) -> (String, String) {
+    (org.to_string(), repo.to_string())
+}
+
+#[test]
+fn test_scan_directory_examples() {
+    let results = scan_directory(&examples_dir(), &ScanConfig::default());
+
+    let statuses = &results.project_statuses;
+    assert_eq!(statuses[&project("project-1", "repo-1")], Status::Unsafe);
+    assert_eq!(statuses[&project("project-2", "repo-2")], Status::Unsafe);
+    assert_eq!(statuses[&project("project-3", "repo-3")], Status::Unsafe);
+    assert_eq!(
+        statuses[&project("project-4", "safe-project")],
+        Status::Safe
+    );
+    assert_eq!(
+        statuses[&project("org, with comma", "repo \"quoted\"")],
+        Status::Unsafe
+    );
+
+    let unsafe_only = results
+        .file_rows
+        .iter()
+        .find(|row| row.file.ends_with("project-2/repo-2/unsafe_only.py"))
+        .expect("unsafe_only.py should have findings");
+    assert_eq!(
+        (
+            unsafe_only.safe_usages,
+            unsafe_only.partial_usages,
+            unsafe_only.unsafe_usages
+        ),
+        (0, 0, 7)
+    );
+
+    let safe_only = results
+        .file_rows
+        .iter()
+        .find(|row| row.file.ends_with("project-4/safe-project/safe_only.py"))
+        .expect("safe_only.py should have findings");
+    assert_eq!(
+        (
+            safe_only.safe_usages,
+            safe_only.partial_usages,
+            safe_only.unsafe_usages
+        ),
+        (8, 0, 0)
+    );
+
+    let stats = &results.stats;
+    assert_eq!(stats.files_scanned, results.file_rows.len());
+    assert_eq!(
+        stats.safe_usages + stats.partial_usages + stats.unsafe_usages,
+        results.findings.len()
+    );
+    assert_eq!(
+        stats.safe_projects + stats.partial_projects + stats.unsafe_projects,
+        statuses.len()
+    );
+    assert!(
+        results
+            .findings
+            .iter()
+            .all(|f| !f.file.is_empty() && f.line > 0)
+    );
+}
+
+#[test]
+fn test_concurrency_modes_agree() {
+    let rayon = scan_directory(&examples_dir(), &ScanConfig::default());
+    let config = ScanConfig {
+        concurrency_mode: ConcurrencyMode::Tokio,
+        ..Default::default()
+    };
+    let tokio = scan_directory(&examples_dir(), &config);
+
+    assert_eq!(tokio.findings, rayon.findings);
+    assert_eq!(tokio.file_rows, rayon.file_rows);
+    assert_eq!(tokio.project_statuses, rayon.project_statuses);
+    assert_eq!(tokio.stats, rayon.stats);
+    assert_eq!(tokio.risk_score, rayon.risk_score);
+}
+
+#[test]
+fn test_lazy_walk_agrees() {
+    let mut eager = scan_directory(&examples_dir(), &ScanConfig::default());
+    let config = ScanConfig {
+        lazy_walk: true,
+        ..Default::default()
+    };
+    let mut lazy = scan_directory(&examples_dir(), &config);
+
+    // The lazy walk sorts by path; the eager one keeps the directory order.
+    for results in [&mut eager, &mut lazy] {
+        results.file_rows.sort_by(|a, b| a.file.cmp(&b.file));
+        results
+            .findings
+            .sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    }
+    assert_eq!(lazy.findings, eager.findings);
+    assert_eq!(lazy.file_rows, eager.file_rows);
+    assert_eq!(lazy.project_statuses, eager.project_statuses);
+    assert_eq!(lazy.files_by_depth, eager.files_by_depth);
+    assert_eq!(lazy.stats, eager.stats);
+    assert_eq!(lazy.risk_score, eager.risk_score);
+}
+
+#[test]
+fn test_cache_skips_unchanged_files() -> std::io::Result<()> {
+    let root = tempfile::tempdir()?;
+    let repo = root.path().join("org").join("repo");
+    std::fs::create_dir_all(&repo)?;
+    let code: String = (0..10)
+        .map(|i| {
+            format!("m{i} = AutoModel.from_pretrained(\"org/model-{i}\", revision=\"main\")\n")
+        })
+        .collect();
+    for i in 0..50 {
+        std::fs::write(repo.join(format!("file_{i}.py")), format!("# {i}\n{code}"))?;
+    }
+
+    let cache_dir = tempfile::tempdir()?;
+    let scan = || -> std::io::Result<(ScanResults, usize)> {
+        let mut config = ScanConfig::default();
+        config.cache = Some(Arc::new(ScanCache::open(cache_dir.path(), &config)?));
+        let results = scan_directory(root.path(), &config);
+        let cache = config.cache.unwrap();
+        cache.save()?;
+        Ok((results, cache.hits()))
+    };
+    let (cold, cold_hits) = scan()?;
+    let (warm, warm_hits) = scan()?;
+
+    assert_eq!(cold_hits, 0);
+    assert_eq!(warm_hits, 50);
+    assert_eq!(warm.findings, cold.findings);
+    assert_eq!(warm.stats, cold.stats);
+
+    // A changed file is scanned again.
+    std::fs::write(repo.join("file_0.py"), format!("# changed\n{code}"))?;
+    let (_, hits) = scan()?;
+    assert_eq!(hits, 49);
+
+    Ok(())
+}
+
+#[test]
+fn test_scan_directory_projects() {
+    let results = scan_directory(&examples_dir(), &ScanConfig::default());
+
+    let projects: Vec<(&str, &str, Status)> = results
+        .projects
+        .iter()
+        .map(|p| (p.org.as_str(), p.repo.as_str(), p.status))
+        .collect();
+    assert_eq!(
+        projects,
+        vec![
+            ("org, with comma", "repo \"quoted\"", Status::Unsafe),
+            ("project-1", "repo-1", Status::Unsafe),
+            ("project-2", "repo-2", Status::Unsafe),
+            ("project-3", "repo-3", Status::Unsafe),
+            ("project-4", "safe-project", Status::Safe),
+            ("unknown", "unknown", Status::Unsafe),
+        ]
+    );
+    for project in &results.projects {
+        let key = (project.org.clone(), project.repo.clone());
+        assert_eq!(results.project_statuses[&key], project.status);
+    }
+
+    let safe = &results.projects[4];
+    assert_eq!(safe.worst_status(), Some(Status::Safe));
+    assert_eq!(safe.unsafe_findings().count(), 0);
+    assert_eq!(
+        safe.total_counts(),
+        PatternCounts {
+            safe_usages: 8,
+            partial_usages: 0,
+            unsafe_usages: 0,
+        }
+    );
+    let unsafe_only = &results.projects[2];
+    assert_eq!(
+        unsafe_only.unsafe_findings().count(),
+        unsafe_only.total_counts().unsafe_usages
+    );
+
+    let total: usize = results
+        .projects
+        .iter()
+        .map(|p| p.total_counts().unsafe_usages)
+        .sum();
+    assert_eq!(total, results.stats.unsafe_usages);
+    let findings: usize = results.projects.iter().map(|p| p.findings.len()).sum();
+    assert_eq!(findings, results.findings.len());
+}
+
+fn git(dir: &Path, args: &[&str]) -> std::io::Result<()> {
+    let status = Command::new("git").arg("-C").arg(dir).args(args).status()?;
+    assert!(status.success(), "git {args:?} failed");
+    Ok(())
+}
+
+#[test]
+fn test_scan_git_index() -> std::io::Result<()> {
+    let root = tempfile::tempdir()?;
+    let repo = root.path().join("org").join("repo");
+    std::fs::create_dir_all(&repo)?;
+    git(root.path(), &["init", "--quiet"])?;
+
+    let unsafe_load = "model = AutoModel.from_pretrained(\"gpt2\")\n";
+    std::fs::write(repo.join("staged.py"), unsafe_load)?;
+    std::fs::write(repo.join("notes.txt"), unsafe_load)?;
+    git(
+        root.path(),
+        &["add", "org/repo/staged.py", "org/repo/notes.txt"],
+    )?;
+    // Neither the fix in the working tree nor the untracked file is scanned.
+    std::fs::write(
+        repo.join("staged.py"),
+        "model = AutoModel.from_pretrained(\"gpt2\", revision=\"main\")\n",
+    )?;
+    std::fs::write(repo.join("untracked.py"), unsafe_load)?;
+
+    let results = scan_git_index(&[root.path()], &ScanConfig::default())?;
+    assert_eq!(results.stats.files_scanned, 1);
+    let findings: Vec<(&str, usize, Status)> = results
+        .findings
+        .iter()
+        .map(|f| (f.file.as_str(), f.line, f.status))
+        .collect();
+    assert_eq!(findings, vec![("org/repo/staged.py", 1, Status::Unsafe)]);
+    assert_eq!(
+        results.project_statuses.get(&project("org", "repo")),
+        Some(&Status::Unsafe)
+    );
+
+    // A subdirectory only covers the files below it.
+    let results = scan_git_index(&[repo.as_path()], &ScanConfig::default())?;
+    assert_eq!(results.findings.len(), 1);
+    assert_eq!(results.findings[0].file, "staged.py");
+
+    let not_a_repo = tempfile::tempdir()?;
+    assert!(scan_git_index(&[not_a_repo.path()], &ScanConfig::default()).is_err());
+
+    Ok(())
+}